@@ -3,98 +3,23 @@
 //! This module provides a ClaudeAgent that implements the CodingAgent trait
 //! by invoking the Claude CLI with streaming JSON output.
 
-use std::io::{BufRead, BufReader, Lines};
-use std::process::{Child, ChildStdout, Command, Stdio};
+use std::path::Path;
+use std::process::{Command, Stdio};
 
 use serde::Deserialize;
 
+use super::stream::{AgentStream, LineParser};
 use super::{CodingAgent, Prompt, Response, StreamEvent};
 use crate::error::{Error, Result};
 
-/// Streaming iterator over agent output.
-///
-/// Wraps a child process and parses NDJSON events from its stdout.
-pub struct AgentStream {
-    child: Child,
-    lines: Lines<BufReader<ChildStdout>>,
-    done: bool,
-}
-
-impl AgentStream {
-    /// Create an AgentStream for testing purposes.
-    #[cfg(test)]
-    pub fn new_for_test(child: Child, lines: Lines<BufReader<ChildStdout>>) -> Self {
-        Self {
-            child,
-            lines,
-            done: false,
-        }
-    }
-}
-
-impl Iterator for AgentStream {
-    type Item = StreamEvent;
-
-    fn next(&mut self) -> Option<Self::Item> {
-        if self.done {
-            return None;
-        }
-
-        loop {
-            let line = match self.lines.next() {
-                Some(Ok(line)) => line,
-                Some(Err(_)) => {
-                    self.done = true;
-                    return None;
-                }
-                None => {
-                    self.done = true;
-                    return None;
-                }
-            };
-
-            // Skip empty lines
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            // Parse JSON event
-            let event: ClaudeEvent = match serde_json::from_str(&line) {
-                Ok(e) => e,
-                Err(_) => continue, // Skip unparseable lines
-            };
-
-            match event {
-                ClaudeEvent::System(_) => continue, // Ignore system events
-                ClaudeEvent::Assistant(assistant) => {
-                    // Extract text from first text content block
-                    for content in assistant.message.content {
-                        if let ClaudeContent::Text { text } = content {
-                            return Some(StreamEvent::Message(text));
-                        }
-                    }
-                    continue;
-                }
-                ClaudeEvent::Result(result) => {
-                    self.done = true;
-                    let response = Response {
-                        content: result.result,
-                        turns: result.num_turns,
-                        tokens: result.usage.input_tokens + result.usage.output_tokens,
-                        cost: result.total_cost_usd,
-                    };
-                    return Some(StreamEvent::Done(response));
-                }
-            }
-        }
-    }
-}
+/// Parses the NDJSON events of `claude --output-format stream-json`.
+#[derive(Debug, Default)]
+pub struct ClaudeParser;
 
-impl Drop for AgentStream {
-    fn drop(&mut self) {
-        // Kill the child process if still running
-        let _ = self.child.kill();
-        let _ = self.child.wait();
+impl LineParser for ClaudeParser {
+    fn parse_line(&mut self, line: &str) -> Vec<StreamEvent> {
+        // Skip unparseable lines
+        parse_event(line).map(stream_events).unwrap_or_default()
     }
 }
 
@@ -142,14 +67,17 @@ fn build_command_args(prompt: &Prompt) -> Vec<String> {
 }
 
 impl CodingAgent for ClaudeAgent {
-    fn run(&self, prompt: &Prompt) -> Result<AgentStream> {
-        let mut cmd = Command::new("claude");
+    fn run(&self, prompt: &Prompt, work_dir: &Path) -> Result<AgentStream> {
+        let mut cmd = tokio::process::Command::new("claude");
         let args = build_command_args(prompt);
         cmd.args(&args);
+        cmd.current_dir(work_dir);
         cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::null());
+        cmd.stderr(Stdio::piped());
+        cmd.process_group(0);
+        cmd.kill_on_drop(true);
 
-        let mut child = cmd.spawn().map_err(|e| {
+        let child = cmd.spawn().map_err(|e| {
             if e.kind() == std::io::ErrorKind::NotFound {
                 Error::ClaudeNotFound
             } else {
@@ -157,18 +85,34 @@ impl CodingAgent for ClaudeAgent {
             }
         })?;
 
-        let stdout = child.stdout.take().ok_or_else(|| {
-            Error::AgentExecution("Failed to capture stdout from claude process".to_string())
-        })?;
-
-        let reader = BufReader::new(stdout);
-        let lines = reader.lines();
+        AgentStream::new(child, ClaudeParser)
+    }
+}
 
-        Ok(AgentStream {
-            child,
-            lines,
-            done: false,
-        })
+/// Converts a Claude event into stream events, one per content block.
+fn stream_events(event: ClaudeEvent) -> Vec<StreamEvent> {
+    match event {
+        ClaudeEvent::System(_) => Vec::new(), // Ignore system events
+        ClaudeEvent::Assistant(ClaudeMessageEvent { message })
+        | ClaudeEvent::User(ClaudeMessageEvent { message }) => message
+            .content
+            .into_iter()
+            .filter_map(|content| match content {
+                ClaudeContent::Text { text } => Some(StreamEvent::Message(text)),
+                ClaudeContent::Thinking { thinking } => Some(StreamEvent::Thinking(thinking)),
+                ClaudeContent::ToolUse { name, input } => Some(StreamEvent::ToolUse { name, input }),
+                ClaudeContent::ToolResult { content, is_error } => {
+                    Some(StreamEvent::tool_result(is_error, &content.text()))
+                }
+                ClaudeContent::Other => None,
+            })
+            .collect(),
+        ClaudeEvent::Result(result) => vec![StreamEvent::Done(Response {
+            content: result.result,
+            turns: result.num_turns,
+            tokens: result.usage.input_tokens + result.usage.output_tokens,
+            cost: result.total_cost_usd,
+        })],
     }
 }
 
@@ -180,7 +124,10 @@ enum ClaudeEvent {
     #[serde(rename = "system")]
     System(ClaudeSystemEvent),
     #[serde(rename = "assistant")]
-    Assistant(ClaudeAssistantEvent),
+    Assistant(ClaudeMessageEvent),
+    /// Tool results fed back to the agent.
+    #[serde(rename = "user")]
+    User(ClaudeMessageEvent),
     #[serde(rename = "result")]
     Result(ClaudeResultEvent),
 }
@@ -189,13 +136,13 @@ enum ClaudeEvent {
 #[derive(Debug, Deserialize)]
 struct ClaudeSystemEvent {}
 
-/// Assistant message event from Claude CLI.
+/// Assistant or user message event from Claude CLI.
 #[derive(Debug, Deserialize)]
-struct ClaudeAssistantEvent {
+struct ClaudeMessageEvent {
     message: ClaudeMessage,
 }
 
-/// Message structure in assistant event.
+/// Message structure in assistant and user events.
 #[derive(Debug, Deserialize)]
 struct ClaudeMessage {
     content: Vec<ClaudeContent>,
@@ -207,10 +154,60 @@ struct ClaudeMessage {
 enum ClaudeContent {
     #[serde(rename = "text")]
     Text { text: String },
+    #[serde(rename = "thinking")]
+    Thinking { thinking: String },
+    #[serde(rename = "tool_use")]
+    ToolUse {
+        name: String,
+        #[serde(default)]
+        input: serde_json::Value,
+    },
+    #[serde(rename = "tool_result")]
+    ToolResult {
+        #[serde(default)]
+        content: ClaudeToolOutput,
+        #[serde(default)]
+        is_error: bool,
+    },
     #[serde(other)]
     Other,
 }
 
+/// Output of a tool call: plain text or a list of content blocks.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum ClaudeToolOutput {
+    Text(String),
+    Blocks(Vec<ClaudeToolOutputBlock>),
+}
+
+impl Default for ClaudeToolOutput {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl ClaudeToolOutput {
+    /// Joins the text of the output; non-text blocks (images) are skipped.
+    fn text(&self) -> String {
+        match self {
+            Self::Text(text) => text.clone(),
+            Self::Blocks(blocks) => blocks
+                .iter()
+                .filter_map(|block| block.text.as_deref())
+                .collect::<Vec<_>>()
+                .join("\n"),
+        }
+    }
+}
+
+/// Content block in a tool result.
+#[derive(Debug, Deserialize)]
+struct ClaudeToolOutputBlock {
+    #[serde(default)]
+    text: Option<String>,
+}
+
 /// Result event from Claude CLI (final response).
 #[derive(Debug, Deserialize)]
 struct ClaudeResultEvent {
@@ -300,17 +297,90 @@ mod tests {
 
     #[test]
     fn parses_assistant_with_tool_use_content() {
-        let json = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"123","name":"read"}]}}"#;
+        let json = r#"{"type":"assistant","message":{"content":[{"type":"tool_use","id":"123","name":"Read","input":{"file_path":"src/main.rs"}}]}}"#;
         let event = parse_event(json).unwrap();
         match event {
             ClaudeEvent::Assistant(assistant) => {
                 assert_eq!(assistant.message.content.len(), 1);
-                assert!(matches!(assistant.message.content[0], ClaudeContent::Other));
+                match &assistant.message.content[0] {
+                    ClaudeContent::ToolUse { name, input } => {
+                        assert_eq!(name, "Read");
+                        assert_eq!(input["file_path"], "src/main.rs");
+                    }
+                    other => panic!("Expected tool use content, got {:?}", other),
+                }
             }
             _ => panic!("Expected assistant event"),
         }
     }
 
+    #[test]
+    fn parses_user_event_with_tool_results() {
+        let json = r#"{"type":"user","message":{"role":"user","content":[
+            {"type":"tool_result","tool_use_id":"1","content":"ok"},
+            {"type":"tool_result","tool_use_id":"2","content":[{"type":"text","text":"error: not found"}],"is_error":true}
+        ]}}"#;
+        let events = stream_events(parse_event(json).unwrap());
+        assert!(matches!(
+            &events[..],
+            [
+                StreamEvent::ToolResult { is_error: false, summary: first },
+                StreamEvent::ToolResult { is_error: true, summary: second },
+            ] if first == "ok" && second == "error: not found"
+        ));
+    }
+
+    #[test]
+    fn every_block_of_a_message_is_surfaced() {
+        let json = r#"{"type":"assistant","message":{"content":[
+            {"type":"thinking","thinking":"Check the tests first","signature":"x"},
+            {"type":"text","text":"Running tests"},
+            {"type":"tool_use","id":"1","name":"Bash","input":{"command":"cargo test"}},
+            {"type":"text","text":"Then fixing"},
+            {"type":"image"}
+        ]}}"#;
+        let events = stream_events(parse_event(json).unwrap());
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], StreamEvent::Thinking(text) if text == "Check the tests first"));
+        assert!(matches!(&events[1], StreamEvent::Message(text) if text == "Running tests"));
+        assert!(matches!(&events[2], StreamEvent::ToolUse { name, input } if name == "Bash" && input["command"] == "cargo test"));
+        assert!(matches!(&events[3], StreamEvent::Message(text) if text == "Then fixing"));
+    }
+
+    /// Spawns `sh -c script` as a fake agent.
+    fn spawn_fake_agent(script: &str) -> AgentStream {
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Failed to spawn fake agent");
+        AgentStream::new(child, ClaudeParser).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stream_yields_messages_then_done() {
+        let mut stream = spawn_fake_agent(concat!(
+            r#"echo '{"type":"system","subtype":"init"}';"#,
+            r#"echo 'not json';"#,
+            r#"echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Working"},{"type":"tool_use","name":"Bash","input":{}}]}}';"#,
+            r#"echo '{"type":"result","result":"<promise>COMPLETE</promise>","num_turns":2}'"#,
+        ));
+
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text == "Working"));
+        assert!(matches!(stream.next().await, Some(StreamEvent::ToolUse { name, .. }) if name == "Bash"));
+        match stream.next().await {
+            Some(StreamEvent::Done(response)) => {
+                assert_eq!(response.content, "<promise>COMPLETE</promise>");
+                assert_eq!(response.turns, 2);
+            }
+            other => panic!("Expected done event, got {:?}", other),
+        }
+        assert!(matches!(stream.next().await, Some(StreamEvent::Exited(exit)) if exit.success()));
+        assert!(stream.next().await.is_none());
+    }
+
     #[test]
     fn agent_new_creates_instance() {
         let agent = ClaudeAgent::new();
@@ -0,0 +1,406 @@
+//! Codex CLI agent implementation.
+//!
+//! This module provides a CodexAgent that implements the CodingAgent trait
+//! by invoking `codex exec --json`, which prints one JSON event per line:
+//! thread and turn lifecycle events, and `item.started`/`item.completed`
+//! events for each agent message, reasoning step, command and file change.
+
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use super::stream::{AgentStream, LineParser};
+use super::{CodingAgent, Prompt, Response, StreamEvent};
+use crate::error::{Error, Result};
+
+/// Codex CLI agent implementation.
+///
+/// Runs `codex exec --json` non-interactively, without approval prompts or
+/// sandbox, like the Claude backend's `--dangerously-skip-permissions`.
+#[derive(Debug)]
+pub struct CodexAgent {
+    /// Program to run, looked up on PATH.
+    program: PathBuf,
+}
+
+impl CodexAgent {
+    /// Create a new Codex agent.
+    pub fn new() -> Self {
+        Self::with_program("codex")
+    }
+
+    /// Create a Codex agent running `program` instead of `codex`.
+    pub fn with_program(program: impl Into<PathBuf>) -> Self {
+        Self {
+            program: program.into(),
+        }
+    }
+}
+
+impl Default for CodexAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Build the command-line arguments for the Codex CLI.
+///
+/// `codex exec` has no system prompt flag, so a non-empty system prompt is
+/// put before the user prompt.
+fn build_command_args(prompt: &Prompt) -> Vec<String> {
+    let instructions = if prompt.system.is_empty() {
+        prompt.user.clone()
+    } else {
+        format!("{}\n\n{}", prompt.system, prompt.user)
+    };
+
+    vec![
+        "exec".to_string(),
+        "--json".to_string(),
+        "--dangerously-bypass-approvals-and-sandbox".to_string(),
+        // The snapshot checkpoint backend runs outside any git repository
+        "--skip-git-repo-check".to_string(),
+        instructions,
+    ]
+}
+
+impl CodingAgent for CodexAgent {
+    fn run(&self, prompt: &Prompt, work_dir: &Path) -> Result<AgentStream> {
+        let mut cmd = tokio::process::Command::new(&self.program);
+        cmd.args(build_command_args(prompt));
+        cmd.current_dir(work_dir);
+        // Codex reads the prompt from stdin when it is not a terminal
+        cmd.stdin(Stdio::null());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.process_group(0);
+        cmd.kill_on_drop(true);
+
+        let child = cmd.spawn().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Error::AgentNotFound(self.program.display().to_string())
+            } else {
+                Error::AgentExecution(format!("Failed to spawn {}: {}", self.program.display(), e))
+            }
+        })?;
+
+        AgentStream::new(child, CodexParser::default())
+    }
+}
+
+/// Parses the JSONL events of `codex exec --json`.
+///
+/// Codex has no final result event, so the parser remembers the last agent
+/// message and the token usage, and reports them as the response when the
+/// turn completes.
+#[derive(Debug, Default)]
+pub struct CodexParser {
+    /// Text of the most recent agent message.
+    last_message: String,
+    /// Turns completed so far.
+    turns: u32,
+    /// Input and output tokens used so far.
+    tokens: u32,
+}
+
+impl LineParser for CodexParser {
+    fn parse_line(&mut self, line: &str) -> Vec<StreamEvent> {
+        match serde_json::from_str(line) {
+            Ok(event) => self.stream_events(event),
+            Err(_) => Vec::new(), // Skip unparseable lines
+        }
+    }
+}
+
+impl CodexParser {
+    /// Converts a Codex event into stream events.
+    fn stream_events(&mut self, event: CodexEvent) -> Vec<StreamEvent> {
+        match event {
+            // A command is shown when it starts; its output when it completes
+            CodexEvent::ItemStarted { item: CodexItem::CommandExecution { command, .. } } => {
+                vec![StreamEvent::ToolUse {
+                    name: "shell".to_string(),
+                    input: json!({ "command": command }),
+                }]
+            }
+            CodexEvent::ItemStarted { .. } => Vec::new(),
+            CodexEvent::ItemCompleted { item } => self.completed_item(item),
+            CodexEvent::TurnCompleted { usage } => {
+                self.turns += 1;
+                self.tokens += usage.input_tokens + usage.output_tokens;
+                vec![StreamEvent::Done(Response {
+                    content: self.last_message.clone(),
+                    turns: self.turns,
+                    tokens: self.tokens,
+                    // Codex does not report a cost
+                    cost: 0.0,
+                })]
+            }
+            CodexEvent::TurnFailed { error } => {
+                vec![StreamEvent::Message(format!("Error: {}", error.message))]
+            }
+            CodexEvent::Error { message } => vec![StreamEvent::Message(format!("Error: {}", message))],
+            CodexEvent::Other => Vec::new(),
+        }
+    }
+
+    /// Converts a completed item into stream events.
+    fn completed_item(&mut self, item: CodexItem) -> Vec<StreamEvent> {
+        match item {
+            CodexItem::AgentMessage { text } => {
+                self.last_message = text.clone();
+                vec![StreamEvent::Message(text)]
+            }
+            CodexItem::Reasoning { text } => vec![StreamEvent::Thinking(text)],
+            CodexItem::CommandExecution {
+                aggregated_output,
+                exit_code,
+                ..
+            } => vec![StreamEvent::tool_result(exit_code != Some(0), &aggregated_output)],
+            CodexItem::FileChange { changes, status } => {
+                let mut events = vec![StreamEvent::ToolUse {
+                    name: "apply_patch".to_string(),
+                    input: json!({ "changes": changes }),
+                }];
+                if status == "failed" {
+                    events.push(StreamEvent::tool_result(true, "patch failed"));
+                }
+                events
+            }
+            CodexItem::McpToolCall {
+                server,
+                tool,
+                arguments,
+                status,
+            } => {
+                let mut events = vec![StreamEvent::ToolUse {
+                    name: format!("{}.{}", server, tool),
+                    input: arguments,
+                }];
+                if status == "failed" {
+                    events.push(StreamEvent::tool_result(true, "tool call failed"));
+                }
+                events
+            }
+            CodexItem::WebSearch { query } => vec![StreamEvent::ToolUse {
+                name: "web_search".to_string(),
+                input: json!({ "query": query }),
+            }],
+            CodexItem::Other => Vec::new(),
+        }
+    }
+}
+
+/// Codex CLI JSONL event. The event type is determined by the "type" field.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum CodexEvent {
+    #[serde(rename = "item.started")]
+    ItemStarted { item: CodexItem },
+    #[serde(rename = "item.completed")]
+    ItemCompleted { item: CodexItem },
+    #[serde(rename = "turn.completed")]
+    TurnCompleted {
+        #[serde(default)]
+        usage: CodexUsage,
+    },
+    #[serde(rename = "turn.failed")]
+    TurnFailed { error: CodexError },
+    #[serde(rename = "error")]
+    Error { message: String },
+    /// Thread and turn start events carry nothing to show.
+    #[serde(other)]
+    Other,
+}
+
+/// Item of a Codex turn.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum CodexItem {
+    AgentMessage {
+        text: String,
+    },
+    Reasoning {
+        text: String,
+    },
+    CommandExecution {
+        command: String,
+        #[serde(default)]
+        aggregated_output: String,
+        #[serde(default)]
+        exit_code: Option<i32>,
+    },
+    FileChange {
+        #[serde(default)]
+        changes: Vec<CodexFileChange>,
+        #[serde(default)]
+        status: String,
+    },
+    McpToolCall {
+        server: String,
+        tool: String,
+        #[serde(default)]
+        arguments: serde_json::Value,
+        #[serde(default)]
+        status: String,
+    },
+    WebSearch {
+        query: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+/// A file touched by a file change item.
+#[derive(Debug, Deserialize, Serialize)]
+struct CodexFileChange {
+    path: String,
+    kind: String,
+}
+
+/// Token usage of a completed turn.
+#[derive(Debug, Deserialize, Default)]
+struct CodexUsage {
+    #[serde(default)]
+    input_tokens: u32,
+    #[serde(default)]
+    output_tokens: u32,
+}
+
+/// Error of a failed turn.
+#[derive(Debug, Deserialize)]
+struct CodexError {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::os::unix::fs::PermissionsExt;
+
+    fn parse_lines(lines: &[&str]) -> Vec<StreamEvent> {
+        let mut parser = CodexParser::default();
+        lines.iter().flat_map(|line| parser.parse_line(line)).collect()
+    }
+
+    #[test]
+    fn maps_messages_reasoning_and_commands() {
+        let events = parse_lines(&[
+            r#"{"type":"thread.started","thread_id":"t1"}"#,
+            r#"{"type":"turn.started"}"#,
+            r#"{"type":"item.completed","item":{"id":"item_0","type":"reasoning","text":"Look at the tests"}}"#,
+            r#"{"type":"item.started","item":{"id":"item_1","type":"command_execution","command":"bash -lc 'cargo test'","aggregated_output":"","status":"in_progress"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_1","type":"command_execution","command":"bash -lc 'cargo test'","aggregated_output":"error: 1 test failed\n","exit_code":101,"status":"failed"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_2","type":"agent_message","text":"Fixed it"}}"#,
+        ]);
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], StreamEvent::Thinking(text) if text == "Look at the tests"));
+        assert!(matches!(&events[1], StreamEvent::ToolUse { name, input }
+            if name == "shell" && input["command"] == "bash -lc 'cargo test'"));
+        assert!(matches!(&events[2], StreamEvent::ToolResult { is_error: true, summary }
+            if summary == "error: 1 test failed"));
+        assert!(matches!(&events[3], StreamEvent::Message(text) if text == "Fixed it"));
+    }
+
+    #[test]
+    fn turn_completed_reports_the_last_message_and_usage() {
+        let events = parse_lines(&[
+            r#"{"type":"item.completed","item":{"id":"item_0","type":"agent_message","text":"Working"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_1","type":"agent_message","text":"<promise>COMPLETE</promise>"}}"#,
+            r#"{"type":"turn.completed","usage":{"input_tokens":1200,"cached_input_tokens":1000,"output_tokens":300}}"#,
+        ]);
+
+        match events.last() {
+            Some(StreamEvent::Done(response)) => {
+                assert_eq!(response.content, "<promise>COMPLETE</promise>");
+                assert_eq!(response.turns, 1);
+                assert_eq!(response.tokens, 1500);
+            }
+            other => panic!("Expected done event, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn maps_file_changes_and_failures() {
+        let events = parse_lines(&[
+            r#"{"type":"item.completed","item":{"id":"item_0","type":"file_change","changes":[{"path":"src/lib.rs","kind":"update"}],"status":"completed"}}"#,
+            r#"{"type":"item.completed","item":{"id":"item_1","type":"todo_list","items":[]}}"#,
+            r#"{"type":"turn.failed","error":{"message":"stream disconnected"}}"#,
+        ]);
+
+        assert_eq!(events.len(), 2);
+        assert!(matches!(&events[0], StreamEvent::ToolUse { name, input }
+            if name == "apply_patch" && input["changes"][0]["path"] == "src/lib.rs"));
+        assert!(matches!(&events[1], StreamEvent::Message(text) if text == "Error: stream disconnected"));
+    }
+
+    #[test]
+    fn build_args_run_exec_with_json_output() {
+        let prompt = Prompt {
+            system: String::new(),
+            user: "test prompt".to_string(),
+        };
+
+        let args = build_command_args(&prompt);
+        assert_eq!(args[..2], ["exec".to_string(), "--json".to_string()]);
+        assert_eq!(args.last().map(String::as_str), Some("test prompt"));
+    }
+
+    #[test]
+    fn build_args_put_the_system_prompt_first() {
+        let prompt = Prompt {
+            system: "You are helpful".to_string(),
+            user: "test prompt".to_string(),
+        };
+
+        let args = build_command_args(&prompt);
+        assert_eq!(args.last().map(String::as_str), Some("You are helpful\n\ntest prompt"));
+    }
+
+    /// Writes a fake `codex` into `dir` that echoes its prompt as a Codex run
+    /// and returns its path.
+    fn write_fake_codex(dir: &Path) -> PathBuf {
+        let script = dir.join("codex");
+        fs::write(
+            &script,
+            concat!(
+                "#!/bin/sh\n",
+                "[ \"$1\" = exec ] && [ \"$2\" = --json ] || { echo \"bad args: $*\" >&2; exit 2; }\n",
+                "for last; do :; done\n",
+                "echo '{\"type\":\"thread.started\",\"thread_id\":\"t1\"}'\n",
+                "printf '{\"type\":\"item.completed\",\"item\":{\"id\":\"item_0\",\"type\":\"agent_message\",\"text\":\"%s\"}}\\n' \"$last\"\n",
+                "echo '{\"type\":\"turn.completed\",\"usage\":{\"input_tokens\":10,\"output_tokens\":5}}'\n",
+            ),
+        )
+        .unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+        script
+    }
+
+    #[tokio::test]
+    async fn run_streams_a_fake_codex() {
+        let bin_dir = tempfile::TempDir::new().unwrap();
+        let codex = write_fake_codex(bin_dir.path());
+        let work_dir = tempfile::TempDir::new().unwrap();
+        let prompt = Prompt {
+            system: String::new(),
+            user: "Implement story 1".to_string(),
+        };
+
+        let mut stream = CodexAgent::with_program(codex).run(&prompt, work_dir.path()).unwrap();
+
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text == "Implement story 1"));
+        match stream.next().await {
+            Some(StreamEvent::Done(response)) => {
+                assert_eq!(response.content, "Implement story 1");
+                assert_eq!(response.tokens, 15);
+            }
+            other => panic!("Expected done event, got {:?}", other),
+        }
+        assert!(matches!(stream.next().await, Some(StreamEvent::Exited(exit)) if exit.success()));
+    }
+}
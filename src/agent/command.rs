@@ -0,0 +1,670 @@
+//! Command-template agent implementation.
+//!
+//! This module provides a CommandAgent that runs any agent CLI described by
+//! a JSON config file, so in-house wrappers can be plugged in without new
+//! code:
+//!
+//! ```json
+//! {
+//!   "program": "my-agent",
+//!   "args": ["run", "--system", "{system_prompt}", "{user_prompt}"],
+//!   "prompt_input": "arg",
+//!   "output": {
+//!     "format": "ndjson",
+//!     "fields": { "message": "$.text", "result": "$.final", "tokens": "$.usage.total" }
+//!   }
+//! }
+//! ```
+//!
+//! `prompt_input` decides how the prompt reaches the agent: `arg` expands
+//! `{user_prompt}` and `{system_prompt}` to the prompt text, `file` expands
+//! them to the paths of temp files holding the text, and `stdin` writes the
+//! user prompt to the agent's stdin. If the args have no `{system_prompt}`,
+//! the system prompt is put before the user prompt.
+//!
+//! `output.format` decides how stdout is read: `text` shows each line and
+//! takes all of stdout as the final response; `ndjson` maps JSONPath-like
+//! fields of each line onto messages, tool calls and the final result; and
+//! `result_file` shows stdout lines and reads the final response from the
+//! file at `path` or at `{result_file}`. A `path` must be absolute and
+//! outside the work dir, so the answer is not committed with the story.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use serde::Deserialize;
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+
+use super::stream::{AgentStream, LineParser};
+use super::{CodingAgent, Prompt, Response, StreamEvent};
+use crate::error::{Error, Result};
+
+/// Config of a command-template agent, read from a JSON file.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandConfig {
+    /// Program to run, looked up on PATH.
+    pub program: String,
+    /// Argument template; `{user_prompt}`, `{system_prompt}` and
+    /// `{result_file}` are expanded.
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// How the prompt reaches the agent.
+    #[serde(default)]
+    pub prompt_input: PromptInput,
+    /// How the agent's output is read.
+    #[serde(default)]
+    pub output: OutputFormat,
+}
+
+/// How the prompt reaches a command agent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PromptInput {
+    /// The placeholders expand to the prompt text.
+    #[default]
+    Arg,
+    /// The user prompt is written to stdin.
+    Stdin,
+    /// The placeholders expand to paths of temp files holding the prompt.
+    File,
+}
+
+/// How a command agent's stdout is read.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(tag = "format", rename_all = "snake_case", deny_unknown_fields)]
+pub enum OutputFormat {
+    /// Plain text lines; all of stdout is the final response.
+    #[default]
+    Text,
+    /// One JSON object per line, mapped onto events by `fields`.
+    Ndjson { fields: Box<FieldMap> },
+    /// Plain text lines; the final response is read from a file.
+    ResultFile {
+        /// Absolute path of the file the agent writes, outside the work dir.
+        /// Without it the agent gets a temp file path as `{result_file}`.
+        #[serde(default)]
+        path: Option<String>,
+    },
+}
+
+/// JSONPath-like paths (`$.message.content[0].text`) of the fields of an
+/// NDJSON line. Each field found in a line produces its event.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FieldMap {
+    /// Text of an agent message.
+    pub message: Option<String>,
+    /// The agent's reasoning.
+    pub thinking: Option<String>,
+    /// Name of a called tool.
+    pub tool_name: Option<String>,
+    /// Arguments of a called tool.
+    pub tool_input: Option<String>,
+    /// Output of a tool call.
+    pub tool_output: Option<String>,
+    /// Whether a tool call failed (a boolean).
+    pub tool_error: Option<String>,
+    /// Final response; a line with it ends the run.
+    pub result: Option<String>,
+    /// Turns taken, read from the result line.
+    pub turns: Option<String>,
+    /// Tokens used, read from the result line.
+    pub tokens: Option<String>,
+    /// Cost in USD, read from the result line.
+    pub cost: Option<String>,
+}
+
+impl CommandConfig {
+    /// Reads the config from a JSON file.
+    pub fn load(path: &Path) -> Result<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| Error::Config(format!("cannot read {}: {}", path.display(), e)))?;
+        let config: Self = serde_json::from_str(&contents)
+            .map_err(|e| Error::Config(format!("{}: {}", path.display(), e)))?;
+        if config.program.trim().is_empty() {
+            return Err(Error::Config(format!("{}: program is empty", path.display())));
+        }
+        if let OutputFormat::ResultFile { path: Some(result) } = &config.output {
+            if !Path::new(result).is_absolute() {
+                return Err(Error::Config(format!(
+                    "{}: output path {} must be absolute; files in the work dir are committed",
+                    path.display(),
+                    result
+                )));
+            }
+        }
+        Ok(config)
+    }
+
+    /// Returns true if the args take the system prompt separately.
+    fn takes_system_prompt(&self) -> bool {
+        self.args.iter().any(|arg| arg.contains("{system_prompt}"))
+    }
+}
+
+/// Agent running the CLI described by a `CommandConfig`.
+#[derive(Debug, Clone)]
+pub struct CommandAgent {
+    config: CommandConfig,
+}
+
+impl CommandAgent {
+    /// Create an agent running the configured command.
+    pub fn new(config: CommandConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl CodingAgent for CommandAgent {
+    fn run(&self, prompt: &Prompt, work_dir: &Path) -> Result<AgentStream> {
+        let config = &self.config;
+        let user_prompt = if config.takes_system_prompt() || prompt.system.is_empty() {
+            prompt.user.clone()
+        } else {
+            format!("{}\n\n{}", prompt.system, prompt.user)
+        };
+
+        let needs_scratch = config.prompt_input == PromptInput::File
+            || config.output == OutputFormat::ResultFile { path: None };
+        let scratch = needs_scratch.then(ScratchDir::create).transpose()?;
+
+        let result_file = match &config.output {
+            OutputFormat::ResultFile { path: Some(path) } => {
+                let file = std::path::absolute(work_dir.join(path))?;
+                if file.starts_with(std::path::absolute(work_dir)?) {
+                    return Err(Error::Config(format!(
+                        "result file {} is inside the work dir and would be committed",
+                        file.display()
+                    )));
+                }
+                Some(file)
+            }
+            OutputFormat::ResultFile { path: None } => scratch.as_ref().map(|dir| dir.path.join("result")),
+            _ => None,
+        };
+        if let Some(file) = &result_file {
+            // A result left by an earlier attempt must not count for this one
+            let _ = fs::remove_file(file);
+        }
+
+        let (user_value, system_value) = match (config.prompt_input, &scratch) {
+            (PromptInput::File, Some(dir)) => (
+                dir.write("user_prompt.md", &user_prompt)?,
+                dir.write("system_prompt.md", &prompt.system)?,
+            ),
+            _ => (user_prompt.clone(), prompt.system.clone()),
+        };
+        let result_value = result_file
+            .as_ref()
+            .map(|file| file.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let vars = [
+            ("user_prompt", user_value.as_str()),
+            ("system_prompt", system_value.as_str()),
+            ("result_file", result_value.as_str()),
+        ];
+
+        let mut cmd = tokio::process::Command::new(&config.program);
+        cmd.args(config.args.iter().map(|arg| expand(arg, &vars)));
+        cmd.current_dir(work_dir);
+        cmd.stdin(if config.prompt_input == PromptInput::Stdin {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        });
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        cmd.process_group(0);
+        cmd.kill_on_drop(true);
+
+        let mut child = cmd.spawn().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                Error::AgentNotFound(config.program.clone())
+            } else {
+                Error::AgentExecution(format!("Failed to spawn {}: {}", config.program, e))
+            }
+        })?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            // Written concurrently so a large prompt cannot block on a full pipe
+            tokio::spawn(async move {
+                let _ = stdin.write_all(user_prompt.as_bytes()).await;
+            });
+        }
+
+        let mode = match (&config.output, result_file) {
+            (OutputFormat::Ndjson { fields }, _) => ParseMode::Ndjson(fields.clone()),
+            (OutputFormat::ResultFile { .. }, Some(file)) => ParseMode::ResultFile(file),
+            _ => ParseMode::Text,
+        };
+        AgentStream::new(child, CommandParser::new(mode, scratch))
+    }
+}
+
+/// Expands `{name}` placeholders in `template` in a single pass, so text
+/// substituted for one placeholder is never expanded again.
+fn expand(template: &str, vars: &[(&str, &str)]) -> String {
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let placeholder = vars.iter().find_map(|(name, value)| {
+            let after = rest[1..].strip_prefix(name)?.strip_prefix('}')?;
+            Some((*value, after))
+        });
+        match placeholder {
+            Some((value, after)) => {
+                expanded.push_str(value);
+                rest = after;
+            }
+            None => {
+                expanded.push('{');
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    expanded.push_str(rest);
+    expanded
+}
+
+/// Looks up a JSONPath-like `path` in `value`.
+///
+/// Supports object keys and array indexes, as `$.a.b[0].c` or `a.b.0.c`.
+fn lookup<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix('$').unwrap_or(path).replace('[', ".").replace(']', "");
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |value, segment| match value {
+            Value::Array(items) => items.get(segment.parse::<usize>().ok()?),
+            _ => value.get(segment),
+        })
+        .filter(|value| !value.is_null())
+}
+
+/// Renders a looked-up value as text; strings lose their quotes.
+fn as_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        other => other.to_string(),
+    }
+}
+
+/// How the parser reads stdout.
+#[derive(Debug)]
+enum ParseMode {
+    /// Plain text lines, collected as the final response.
+    Text,
+    /// NDJSON lines mapped by the field paths.
+    Ndjson(Box<FieldMap>),
+    /// Plain text lines; the final response is read from the file.
+    ResultFile(PathBuf),
+}
+
+/// Parses a command agent's stdout according to its output format.
+#[derive(Debug)]
+struct CommandParser {
+    mode: ParseMode,
+    /// All stdout lines, in `Text` mode.
+    transcript: Vec<String>,
+    /// Text of the most recent message, in `Ndjson` mode.
+    last_message: Option<String>,
+    /// Whether a result line was seen, in `Ndjson` mode.
+    done: bool,
+    /// Temp files of the run, removed once the stream is dropped.
+    _scratch: Option<ScratchDir>,
+}
+
+impl CommandParser {
+    fn new(mode: ParseMode, scratch: Option<ScratchDir>) -> Self {
+        Self {
+            mode,
+            transcript: Vec::new(),
+            last_message: None,
+            done: false,
+            _scratch: scratch,
+        }
+    }
+
+    /// Maps the fields of an NDJSON line onto events.
+    fn ndjson_events(&mut self, line: &Value) -> Vec<StreamEvent> {
+        let ParseMode::Ndjson(fields) = &self.mode else {
+            return Vec::new();
+        };
+        let field = |path: &Option<String>| lookup(line, path.as_deref()?);
+        let number = |path: &Option<String>| field(path).and_then(Value::as_f64).unwrap_or(0.0);
+        let mut events = Vec::new();
+
+        if let Some(thinking) = field(&fields.thinking) {
+            events.push(StreamEvent::Thinking(as_text(thinking)));
+        }
+        if let Some(message) = field(&fields.message) {
+            let message = as_text(message);
+            self.last_message = Some(message.clone());
+            events.push(StreamEvent::Message(message));
+        }
+        if let Some(name) = field(&fields.tool_name) {
+            events.push(StreamEvent::ToolUse {
+                name: as_text(name),
+                input: field(&fields.tool_input).cloned().unwrap_or(Value::Null),
+            });
+        }
+        if let Some(output) = field(&fields.tool_output) {
+            let is_error = field(&fields.tool_error).and_then(Value::as_bool).unwrap_or(false);
+            events.push(StreamEvent::tool_result(is_error, &as_text(output)));
+        }
+        if let Some(result) = field(&fields.result) {
+            self.done = true;
+            events.push(StreamEvent::Done(Response {
+                content: as_text(result),
+                turns: number(&fields.turns) as u32,
+                tokens: number(&fields.tokens) as u32,
+                cost: number(&fields.cost),
+            }));
+        }
+        events
+    }
+}
+
+impl LineParser for CommandParser {
+    fn parse_line(&mut self, line: &str) -> Vec<StreamEvent> {
+        match &self.mode {
+            ParseMode::Text => {
+                self.transcript.push(line.to_string());
+                vec![StreamEvent::Message(line.to_string())]
+            }
+            ParseMode::ResultFile(_) => vec![StreamEvent::Message(line.to_string())],
+            ParseMode::Ndjson(_) => match serde_json::from_str::<Value>(line) {
+                Ok(value) => self.ndjson_events(&value),
+                Err(_) => Vec::new(), // Skip unparseable lines
+            },
+        }
+    }
+
+    fn finish(&mut self) -> Vec<StreamEvent> {
+        let content = match &self.mode {
+            ParseMode::Text => Some(self.transcript.join("\n")),
+            // Without a result line, the last message is the response
+            ParseMode::Ndjson(_) if !self.done => self.last_message.clone(),
+            ParseMode::Ndjson(_) => None,
+            // A missing file means the agent died before finishing
+            ParseMode::ResultFile(file) => fs::read_to_string(file).ok(),
+        };
+        content
+            .map(|content| {
+                vec![StreamEvent::Done(Response {
+                    content,
+                    ..Response::default()
+                })]
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Temp directory for the prompt and result files of one run.
+#[derive(Debug)]
+struct ScratchDir {
+    path: PathBuf,
+}
+
+impl ScratchDir {
+    /// Creates a fresh directory under /tmp/ralphtool.
+    fn create() -> Result<Self> {
+        static NEXT: AtomicUsize = AtomicUsize::new(0);
+        let path = PathBuf::from("/tmp/ralphtool").join(format!(
+            "agent-{}-{}",
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)?;
+        Ok(Self { path })
+    }
+
+    /// Writes `contents` to `name` in the directory and returns its path.
+    fn write(&self, name: &str, contents: &str) -> Result<String> {
+        let file = self.path.join(name);
+        fs::write(&file, contents)?;
+        Ok(file.to_string_lossy().into_owned())
+    }
+}
+
+impl Drop for ScratchDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tempfile::TempDir;
+
+    fn config(json: Value) -> CommandConfig {
+        serde_json::from_value(json).unwrap()
+    }
+
+    fn prompt() -> Prompt {
+        Prompt {
+            system: "Be brief".to_string(),
+            user: "Implement story 1".to_string(),
+        }
+    }
+
+    /// Runs the agent and collects its events up to the exit.
+    async fn run_events(config: CommandConfig, work_dir: &Path) -> Vec<StreamEvent> {
+        let mut stream = CommandAgent::new(config).run(&prompt(), work_dir).unwrap();
+        let mut events = Vec::new();
+        while let Some(event) = stream.next().await {
+            events.push(event);
+        }
+        events
+    }
+
+    fn final_content(events: &[StreamEvent]) -> Option<&str> {
+        events.iter().find_map(|event| match event {
+            StreamEvent::Done(response) => Some(response.content.as_str()),
+            _ => None,
+        })
+    }
+
+    #[test]
+    fn config_defaults_to_arg_input_and_text_output() {
+        let config = config(json!({ "program": "my-agent" }));
+        assert_eq!(config.prompt_input, PromptInput::Arg);
+        assert_eq!(config.output, OutputFormat::Text);
+        assert!(config.args.is_empty());
+    }
+
+    #[test]
+    fn load_rejects_unknown_fields_and_empty_programs() {
+        let dir = TempDir::new().unwrap();
+        let file = dir.path().join("agent.json");
+
+        fs::write(&file, r#"{"program":"my-agent","prompt":"stdin"}"#).unwrap();
+        assert!(matches!(CommandConfig::load(&file), Err(Error::Config(_))));
+
+        fs::write(&file, r#"{"program":" "}"#).unwrap();
+        assert!(matches!(CommandConfig::load(&file), Err(Error::Config(msg)) if msg.contains("program is empty")));
+
+        fs::write(&file, r#"{"program":"my-agent","output":{"format":"result_file","path":"out.md"}}"#).unwrap();
+        assert!(matches!(CommandConfig::load(&file), Err(Error::Config(msg)) if msg.contains("must be absolute")));
+
+        fs::write(&file, r#"{"program":"my-agent","output":{"format":"result_file","path":"/tmp/out.md"}}"#).unwrap();
+        let config = CommandConfig::load(&file).unwrap();
+        assert_eq!(
+            config.output,
+            OutputFormat::ResultFile {
+                path: Some("/tmp/out.md".to_string())
+            }
+        );
+    }
+
+    #[test]
+    fn expand_replaces_placeholders_once() {
+        let vars = [("user_prompt", "say {system_prompt}"), ("system_prompt", "hi")];
+        assert_eq!(expand("--prompt={user_prompt}", &vars), "--prompt=say {system_prompt}");
+        assert_eq!(expand("{system_prompt}/{unknown}/{", &vars), "hi/{unknown}/{");
+    }
+
+    #[test]
+    fn lookup_follows_keys_and_indexes() {
+        let value = json!({ "message": { "content": [{ "text": "a" }, { "text": "b" }] }, "none": null });
+        assert_eq!(lookup(&value, "$.message.content[1].text"), Some(&json!("b")));
+        assert_eq!(lookup(&value, "message.content.0.text"), Some(&json!("a")));
+        assert_eq!(lookup(&value, "$.message.missing"), None);
+        assert_eq!(lookup(&value, "$.none"), None);
+    }
+
+    #[test]
+    fn ndjson_fields_map_onto_events() {
+        let fields: FieldMap = serde_json::from_value(json!({
+            "message": "$.text",
+            "tool_name": "$.tool.name",
+            "tool_input": "$.tool.args",
+            "tool_output": "$.tool_output",
+            "tool_error": "$.failed",
+            "result": "$.final",
+            "tokens": "$.usage.total",
+            "cost": "$.usage.usd"
+        }))
+        .unwrap();
+        let mut parser = CommandParser::new(ParseMode::Ndjson(Box::new(fields)), None);
+
+        let events: Vec<StreamEvent> = [
+            r#"{"text":"Looking around"}"#,
+            r#"{"tool":{"name":"shell","args":{"command":"ls"}}}"#,
+            r#"{"tool_output":"ls: cannot access","failed":true}"#,
+            "not json",
+            r#"{"final":"<promise>COMPLETE</promise>","usage":{"total":900,"usd":0.02}}"#,
+        ]
+        .iter()
+        .flat_map(|line| parser.parse_line(line))
+        .collect();
+
+        assert_eq!(events.len(), 4);
+        assert!(matches!(&events[0], StreamEvent::Message(text) if text == "Looking around"));
+        assert!(matches!(&events[1], StreamEvent::ToolUse { name, input } if name == "shell" && input["command"] == "ls"));
+        assert!(matches!(&events[2], StreamEvent::ToolResult { is_error: true, .. }));
+        match &events[3] {
+            StreamEvent::Done(response) => {
+                assert_eq!(response.content, "<promise>COMPLETE</promise>");
+                assert_eq!(response.tokens, 900);
+                assert!((response.cost - 0.02).abs() < 1e-9);
+            }
+            other => panic!("Expected done event, got {:?}", other),
+        }
+        assert!(parser.finish().is_empty());
+    }
+
+    #[test]
+    fn ndjson_without_result_ends_with_the_last_message() {
+        let fields = FieldMap {
+            message: Some("$.text".to_string()),
+            ..FieldMap::default()
+        };
+        let mut parser = CommandParser::new(ParseMode::Ndjson(Box::new(fields)), None);
+        parser.parse_line(r#"{"text":"first"}"#);
+        parser.parse_line(r#"{"text":"<promise>COMPLETE</promise>"}"#);
+
+        assert!(matches!(&parser.finish()[..], [StreamEvent::Done(response)]
+            if response.content == "<promise>COMPLETE</promise>"));
+    }
+
+    #[tokio::test]
+    async fn text_output_with_prompt_as_argument() {
+        let work_dir = TempDir::new().unwrap();
+        let config = config(json!({
+            "program": "sh",
+            "args": ["-c", "echo \"$1\"; echo done", "agent", "{user_prompt}"]
+        }));
+
+        let events = run_events(config, work_dir.path()).await;
+
+        // No {system_prompt} in the args, so it leads the user prompt
+        assert!(matches!(&events[0], StreamEvent::Message(text) if text == "Be brief"));
+        assert_eq!(final_content(&events), Some("Be brief\nImplement story 1\ndone"));
+        assert!(matches!(events.last(), Some(StreamEvent::Exited(exit)) if exit.success()));
+    }
+
+    #[tokio::test]
+    async fn prompt_on_stdin() {
+        let work_dir = TempDir::new().unwrap();
+        let config = config(json!({
+            "program": "sh",
+            "args": ["-c", "echo \"system: $1\"; cat", "agent", "{system_prompt}"],
+            "prompt_input": "stdin"
+        }));
+
+        let events = run_events(config, work_dir.path()).await;
+
+        assert_eq!(final_content(&events), Some("system: Be brief\nImplement story 1"));
+    }
+
+    #[tokio::test]
+    async fn prompt_in_temp_files_and_result_file() {
+        let work_dir = TempDir::new().unwrap();
+        let config = config(json!({
+            "program": "sh",
+            "args": ["-c", "echo working; cat \"$1\" \"$2\" > \"$3\"", "agent",
+                     "{system_prompt}", "{user_prompt}", "{result_file}"],
+            "prompt_input": "file",
+            "output": { "format": "result_file" }
+        }));
+
+        let events = run_events(config, work_dir.path()).await;
+
+        assert!(matches!(&events[0], StreamEvent::Message(text) if text == "working"));
+        assert_eq!(final_content(&events), Some("Be briefImplement story 1"));
+    }
+
+    #[tokio::test]
+    async fn missing_result_file_gives_no_response() {
+        let work_dir = TempDir::new().unwrap();
+        let result_dir = TempDir::new().unwrap();
+        let result_file = result_dir.path().join("result.md");
+        fs::write(&result_file, "stale").unwrap();
+        let config = config(json!({
+            "program": "sh",
+            "args": ["-c", "exit 1"],
+            "output": { "format": "result_file", "path": result_file }
+        }));
+
+        let events = run_events(config, work_dir.path()).await;
+
+        assert_eq!(final_content(&events), None);
+        assert!(matches!(events.last(), Some(StreamEvent::Exited(exit)) if exit.code == Some(1)));
+    }
+
+    #[test]
+    fn run_rejects_a_result_file_in_the_work_dir() {
+        let work_dir = TempDir::new().unwrap();
+        let result_file = work_dir.path().join("result.md");
+        fs::write(&result_file, "project file").unwrap();
+        let config = config(json!({
+            "program": "sh",
+            "output": { "format": "result_file", "path": result_file }
+        }));
+
+        let result = CommandAgent::new(config).run(&prompt(), work_dir.path());
+
+        assert!(matches!(result, Err(Error::Config(msg)) if msg.contains("inside the work dir")));
+        assert_eq!(fs::read_to_string(&result_file).unwrap(), "project file");
+    }
+
+    #[test]
+    fn run_reports_a_missing_program() {
+        let config = config(json!({ "program": "ralphtool-no-such-agent" }));
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let _guard = runtime.enter();
+
+        let result = CommandAgent::new(config).run(&prompt(), Path::new("."));
+        assert!(matches!(result, Err(Error::AgentNotFound(program)) if program == "ralphtool-no-such-agent"));
+    }
+}
@@ -1,6 +1,6 @@
 //! Coding agent abstraction module.
 //!
-//! This module provides a trait for different AI coding backends (Claude Code, Amp, etc.)
+//! This module provides a trait for different AI coding backends (Claude Code, Codex, etc.)
 //! and implementations for spawning agents and capturing their output.
 //!
 //! Note: These types are defined for future integration with the TUI orchestrator.
@@ -8,11 +8,16 @@
 
 #[allow(dead_code)]
 pub mod claude;
+pub mod codex;
+pub mod command;
 mod prompt;
+pub mod stream;
 
 pub use prompt::PromptBuilder;
 
-use crate::error::Result;
+use std::path::Path;
+
+use crate::error::{Error, Result};
 
 /// Prompt for a coding agent with separate system and user components.
 #[derive(Debug, Clone, Default)]
@@ -42,19 +47,185 @@ pub struct Response {
 pub enum StreamEvent {
     /// Intermediate message from the agent.
     Message(String),
+    /// The agent's reasoning before it acts.
+    Thinking(String),
+    /// The agent called a tool.
+    ToolUse {
+        /// Tool name, e.g. `Bash` or `Edit`.
+        name: String,
+        /// Arguments the tool was called with.
+        input: serde_json::Value,
+    },
+    /// Output of a tool call, as fed back to the agent.
+    ToolResult {
+        /// Whether the tool reported an error.
+        is_error: bool,
+        /// First line of the output, shortened for display.
+        summary: String,
+    },
     /// Final result with execution metadata.
     Done(Response),
+    /// The agent process ended; always the last event of a stream.
+    Exited(AgentExit),
+}
+
+/// How an agent process ended.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AgentExit {
+    /// Exit code, or None if the process was killed by a signal.
+    pub code: Option<i32>,
+    /// Tail of the process's stderr.
+    pub stderr: String,
 }
 
-// Re-export ClaudeAgent and AgentStream for use
+impl AgentExit {
+    /// Returns true if the process exited with code 0.
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+
+    /// Describes the exit, e.g. "exited with code 1".
+    pub fn status(&self) -> String {
+        match self.code {
+            Some(code) => format!("exited with code {}", code),
+            None => "was killed by a signal".to_string(),
+        }
+    }
+}
+
+/// Longest tool result summary, in characters.
+const TOOL_SUMMARY_MAX_CHARS: usize = 120;
+
+impl StreamEvent {
+    /// Builds a `ToolResult` summarizing the tool's full `output`.
+    ///
+    /// The summary is the first non-blank line, cut to
+    /// `TOOL_SUMMARY_MAX_CHARS`, noting how many lines were left out.
+    pub fn tool_result(is_error: bool, output: &str) -> Self {
+        let mut lines = output.lines().map(str::trim).filter(|line| !line.is_empty());
+        let mut summary = match lines.next() {
+            Some(first) if first.chars().count() > TOOL_SUMMARY_MAX_CHARS => {
+                let cut: String = first.chars().take(TOOL_SUMMARY_MAX_CHARS).collect();
+                format!("{}…", cut)
+            }
+            Some(first) => first.to_string(),
+            None => "(no output)".to_string(),
+        };
+        let more = lines.count();
+        if more > 0 {
+            summary.push_str(&format!(" (+{} more lines)", more));
+        }
+        StreamEvent::ToolResult { is_error, summary }
+    }
+}
+
+// Re-export the agents and AgentStream for use
+#[allow(unused_imports)]
+pub use claude::ClaudeAgent;
 #[allow(unused_imports)]
-pub use claude::{AgentStream, ClaudeAgent};
+pub use codex::CodexAgent;
+pub use command::{CommandAgent, CommandConfig};
+pub use stream::AgentStream;
 
 /// Trait for AI coding agent backends.
 ///
-/// Implementations spawn an AI agent with a prompt,
+/// Implementations spawn an AI agent with a prompt in a working directory,
 /// then return a stream of events from the agent.
 pub trait CodingAgent {
-    /// Spawn agent with prompt, return a stream of events.
-    fn run(&self, prompt: &Prompt) -> Result<AgentStream>;
+    /// Spawn agent with prompt in `work_dir`, return a stream of events.
+    fn run(&self, prompt: &Prompt, work_dir: &Path) -> Result<AgentStream>;
+}
+
+/// Which coding agent CLI runs the stories.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum AgentBackend {
+    /// Claude Code (`claude -p`).
+    #[default]
+    Claude,
+    /// OpenAI Codex (`codex exec`).
+    Codex,
+    /// Any CLI, described by a command config file.
+    Command,
+}
+
+/// The agent backend to run, with the settings it needs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum AgentConfig {
+    /// Claude Code.
+    #[default]
+    Claude,
+    /// OpenAI Codex.
+    Codex,
+    /// A command-template agent.
+    Command(CommandConfig),
+}
+
+impl AgentConfig {
+    /// Pairs a backend with its command config, which only (and always)
+    /// goes with `AgentBackend::Command`.
+    pub fn new(backend: AgentBackend, command: Option<CommandConfig>) -> Result<Self> {
+        match (backend, command) {
+            (AgentBackend::Claude, None) => Ok(AgentConfig::Claude),
+            (AgentBackend::Codex, None) => Ok(AgentConfig::Codex),
+            (AgentBackend::Command, Some(config)) => Ok(AgentConfig::Command(config)),
+            (AgentBackend::Command, None) => Err(Error::Config(
+                "the command agent needs a config file (--agent-config)".to_string(),
+            )),
+            (_, Some(_)) => Err(Error::Config(
+                "an agent config file only applies to the command agent (--agent command)".to_string(),
+            )),
+        }
+    }
+}
+
+/// Creates the coding agent for the given config.
+pub fn create_agent(config: &AgentConfig) -> Box<dyn CodingAgent> {
+    match config {
+        AgentConfig::Claude => Box::new(ClaudeAgent::new()),
+        AgentConfig::Codex => Box::new(CodexAgent::new()),
+        AgentConfig::Command(command) => Box::new(CommandAgent::new(command.clone())),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn summary(event: StreamEvent) -> String {
+        match event {
+            StreamEvent::ToolResult { summary, .. } => summary,
+            other => panic!("Expected tool result, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn tool_result_summarizes_first_line() {
+        assert_eq!(summary(StreamEvent::tool_result(false, "\n  ok  \n")), "ok");
+        assert_eq!(
+            summary(StreamEvent::tool_result(false, "running 3 tests\ntest a ... ok\ntest b ... ok")),
+            "running 3 tests (+2 more lines)"
+        );
+        assert_eq!(summary(StreamEvent::tool_result(true, "")), "(no output)");
+    }
+
+    #[test]
+    fn agent_config_pairs_the_command_backend_with_its_config() {
+        let command: CommandConfig = serde_json::from_str(r#"{"program":"my-agent"}"#).unwrap();
+
+        assert_eq!(AgentConfig::new(AgentBackend::Codex, None).unwrap(), AgentConfig::Codex);
+        assert_eq!(
+            AgentConfig::new(AgentBackend::Command, Some(command.clone())).unwrap(),
+            AgentConfig::Command(command.clone())
+        );
+        assert!(matches!(AgentConfig::new(AgentBackend::Command, None), Err(Error::Config(_))));
+        assert!(matches!(AgentConfig::new(AgentBackend::Claude, Some(command)), Err(Error::Config(_))));
+    }
+
+    #[test]
+    fn tool_result_cuts_long_lines() {
+        let long = "x".repeat(TOOL_SUMMARY_MAX_CHARS + 10);
+        let summary = summary(StreamEvent::tool_result(false, &long));
+        assert_eq!(summary.chars().count(), TOOL_SUMMARY_MAX_CHARS + 1);
+        assert!(summary.ends_with('…'));
+    }
 }
@@ -247,6 +247,10 @@ mod tests {
             })
         }
 
+        fn tasks_file(&self) -> std::path::PathBuf {
+            std::path::PathBuf::from("tasks.md")
+        }
+
         fn tool_prompt(&self) -> String {
             "## Tool Instructions\nMock tool instructions".to_string()
         }
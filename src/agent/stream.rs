@@ -0,0 +1,288 @@
+//! Async event stream over an agent process.
+//!
+//! Every backend runs its CLI as a child process and reads its stdout line
+//! by line; only the line format differs. `AgentStream` owns the process,
+//! the stdout reader and a bounded stderr buffer, and hands each line to the
+//! backend's `LineParser`.
+
+use std::collections::VecDeque;
+
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, BufReader};
+use tokio::process::{Child, ChildStderr, ChildStdout};
+use tokio::task::JoinHandle;
+
+use super::{AgentExit, StreamEvent};
+use crate::async_cmd::ProcessGroupGuard;
+use crate::error::{Error, Result};
+
+/// Most bytes of the agent's stderr kept for failure diagnostics.
+const STDERR_TAIL_BYTES: usize = 8 * 1024;
+
+/// Turns lines of an agent's stdout into stream events.
+pub trait LineParser: Send {
+    /// Parses one non-blank line. Lines that carry nothing to show (or
+    /// cannot be parsed) yield no events.
+    fn parse_line(&mut self, line: &str) -> Vec<StreamEvent>;
+
+    /// Returns the events due once the process has exited, before
+    /// `StreamEvent::Exited`; e.g. a final response read from a file.
+    fn finish(&mut self) -> Vec<StreamEvent> {
+        Vec::new()
+    }
+}
+
+/// Async stream of agent output.
+///
+/// Wraps a child process and parses its stdout without blocking the
+/// runtime. Reading is cancel-safe, so `next()` can be raced against a stop
+/// request in `tokio::select!`; the process is killed when the stream is
+/// dropped. Agents spawned with `process_group(0)` have their whole group
+/// killed, including the shells and tools they started. Once stdout ends, the stream reports the exit code and the tail
+/// of stderr in a final `StreamEvent::Exited`.
+pub struct AgentStream {
+    child: Child,
+    /// Kills the agent's process group on `kill` or drop.
+    group: ProcessGroupGuard,
+    stdout: BufReader<ChildStdout>,
+    /// Bytes of the line being read; kept across cancelled reads.
+    line: Vec<u8>,
+    parser: Box<dyn LineParser>,
+    /// Collects the tail of stderr, if it is piped.
+    stderr: Option<JoinHandle<String>>,
+    /// Events parsed from a line but not yet returned.
+    pending: VecDeque<StreamEvent>,
+    done: bool,
+}
+
+impl AgentStream {
+    /// Wraps a spawned child whose stdout is piped, parsing it with `parser`.
+    ///
+    /// If stderr is piped as well, its tail is kept for the exit event.
+    pub fn new(mut child: Child, parser: impl LineParser + 'static) -> Result<Self> {
+        let stdout = child.stdout.take().ok_or_else(|| {
+            Error::AgentExecution("Failed to capture stdout from agent process".to_string())
+        })?;
+        let stderr = child.stderr.take().map(|stderr| tokio::spawn(read_tail(stderr, STDERR_TAIL_BYTES)));
+
+        Ok(Self {
+            group: ProcessGroupGuard::new(child.id()),
+            child,
+            stdout: BufReader::new(stdout),
+            line: Vec::new(),
+            parser: Box::new(parser),
+            stderr,
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    /// Returns the next event, or None after the exit event or a kill.
+    pub async fn next(&mut self) -> Option<StreamEvent> {
+        loop {
+            if let Some(event) = self.pending.pop_front() {
+                return Some(event);
+            }
+            if self.done {
+                return None;
+            }
+
+            // Read raw bytes, so invalid UTF-8 does not end the stream early
+            // and leave the agent blocked on a full pipe
+            let line = match self.stdout.read_until(b'\n', &mut self.line).await {
+                Ok(n) if n > 0 => {
+                    let line = String::from_utf8_lossy(&self.line)
+                        .trim_end_matches(['\n', '\r'])
+                        .to_string();
+                    self.line.clear();
+                    line
+                }
+                Ok(_) | Err(_) => {
+                    let exit = self.wait().await;
+                    self.done = true;
+                    self.pending.extend(self.parser.finish());
+                    self.pending.push_back(StreamEvent::Exited(exit));
+                    continue;
+                }
+            };
+
+            // Skip empty lines
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            self.pending.extend(self.parser.parse_line(&line));
+        }
+    }
+
+    /// Waits for the process to exit and collects its stderr.
+    async fn wait(&mut self) -> AgentExit {
+        let code = self.child.wait().await.ok().and_then(|status| status.code());
+        let stderr = match self.stderr.take() {
+            Some(handle) => handle.await.unwrap_or_default(),
+            None => String::new(),
+        };
+        AgentExit { code, stderr }
+    }
+
+    /// Kills the agent process and its group, and waits for it to exit.
+    pub async fn kill(&mut self) {
+        self.done = true;
+        self.group.kill();
+        let _ = self.child.kill().await;
+    }
+}
+
+impl Drop for AgentStream {
+    fn drop(&mut self) {
+        // Kill the child process if still running; tokio reaps it. The
+        // group guard kills the rest of its group.
+        let _ = self.child.start_kill();
+    }
+}
+
+/// Reads `stderr` to the end, keeping only its last `max_bytes`.
+///
+/// A cut tail starts at a line boundary, after a "…" line.
+async fn read_tail(mut stderr: ChildStderr, max_bytes: usize) -> String {
+    let mut tail: VecDeque<u8> = VecDeque::new();
+    let mut truncated = false;
+    let mut chunk = [0u8; 4096];
+
+    while let Ok(n) = stderr.read(&mut chunk).await {
+        if n == 0 {
+            break;
+        }
+        tail.extend(&chunk[..n]);
+        let excess = tail.len().saturating_sub(max_bytes);
+        if excess > 0 {
+            tail.drain(..excess);
+            truncated = true;
+        }
+    }
+
+    let text = String::from_utf8_lossy(tail.make_contiguous()).into_owned();
+    match text.split_once('\n') {
+        Some((_, rest)) if truncated => format!("…\n{}", rest),
+        _ => text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::Response;
+    use std::process::Stdio;
+    use std::time::Duration;
+
+    /// Surfaces every line as a message, then the line count as the result.
+    #[derive(Default)]
+    struct TextParser {
+        lines: usize,
+    }
+
+    impl LineParser for TextParser {
+        fn parse_line(&mut self, line: &str) -> Vec<StreamEvent> {
+            self.lines += 1;
+            vec![StreamEvent::Message(line.to_string())]
+        }
+
+        fn finish(&mut self) -> Vec<StreamEvent> {
+            vec![StreamEvent::Done(Response {
+                content: format!("{} lines", self.lines),
+                ..Default::default()
+            })]
+        }
+    }
+
+    /// Spawns `sh -c script` as a fake agent.
+    fn spawn_fake_agent(script: &str) -> AgentStream {
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", script])
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Failed to spawn fake agent");
+        AgentStream::new(child, TextParser::default()).unwrap()
+    }
+
+    /// Reads the stream up to its exit event.
+    async fn exit_of(mut stream: AgentStream) -> AgentExit {
+        while let Some(event) = stream.next().await {
+            if let StreamEvent::Exited(exit) = event {
+                return exit;
+            }
+        }
+        panic!("stream ended without an exit event");
+    }
+
+    #[tokio::test]
+    async fn stream_skips_blank_lines_and_ends_with_finish_and_exit() {
+        let mut stream = spawn_fake_agent("echo one; echo; echo two");
+
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text == "one"));
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text == "two"));
+        assert!(matches!(stream.next().await, Some(StreamEvent::Done(response)) if response.content == "2 lines"));
+        assert!(matches!(stream.next().await, Some(StreamEvent::Exited(exit)) if exit.success()));
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn invalid_utf8_does_not_end_the_stream() {
+        let mut stream = spawn_fake_agent("printf 'bad \\377 byte\\n'; echo after");
+
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text.starts_with("bad ")));
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(text)) if text == "after"));
+        assert!(matches!(stream.next().await, Some(StreamEvent::Done(response)) if response.content == "2 lines"));
+    }
+
+    #[tokio::test]
+    async fn kill_ends_a_running_agent() {
+        let mut stream = spawn_fake_agent("echo started; exec sleep 30");
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(_))));
+
+        let pending = tokio::time::timeout(Duration::from_millis(200), stream.next()).await;
+        assert!(pending.is_err(), "agent is still running");
+
+        stream.kill().await;
+        assert!(stream.next().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn kill_ends_the_agents_subprocesses() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let child = tokio::process::Command::new("sh")
+            .args(["-c", "(sleep 1; touch leaked.txt) & echo started; wait"])
+            .current_dir(dir.path())
+            .stdout(Stdio::piped())
+            .process_group(0)
+            .kill_on_drop(true)
+            .spawn()
+            .expect("Failed to spawn fake agent");
+        let mut stream = AgentStream::new(child, TextParser::default()).unwrap();
+        assert!(matches!(stream.next().await, Some(StreamEvent::Message(_))));
+
+        stream.kill().await;
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!dir.path().join("leaked.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn exit_event_reports_code_and_stderr() {
+        let exit = exit_of(spawn_fake_agent("echo 'Error: invalid API key' >&2; exit 3")).await;
+
+        assert_eq!(exit.code, Some(3));
+        assert_eq!(exit.stderr, "Error: invalid API key\n");
+        assert_eq!(exit.status(), "exited with code 3");
+    }
+
+    #[tokio::test]
+    async fn exit_event_keeps_only_the_stderr_tail() {
+        let exit = exit_of(spawn_fake_agent("for i in $(seq 1 2000); do echo \"line $i\" >&2; done")).await;
+
+        assert!(exit.stderr.len() <= STDERR_TAIL_BYTES + "…\n".len());
+        assert!(exit.stderr.starts_with("…\nline "));
+        assert!(exit.stderr.ends_with("line 2000\n"));
+    }
+}
@@ -1,18 +1,23 @@
 use std::collections::HashMap;
-use std::sync::atomic::AtomicBool;
+use std::path::PathBuf;
 use std::sync::mpsc::Receiver;
-use std::sync::Arc;
 use std::thread::JoinHandle;
 use std::time::Instant;
 
 use tokio::sync::oneshot;
-
-use crate::agent::StreamEvent;
-use crate::ralph_loop::{CompletionOption, LoopEvent, LoopState, DEFAULT_MAX_RETRIES, DEFAULT_COMMAND_TIMEOUT_SECS};
+use tokio_util::sync::CancellationToken;
+
+use crate::agent::{AgentConfig, StreamEvent};
+use crate::checkpoint::{
+    self, CheckpointBackend, CommitFormat, ExistingRun, InPlaceCheckpoint, PreflightIssue,
+    DEFAULT_UNTRACKED_SIZE_LIMIT_MB,
+};
+use crate::ralph_loop::verify::DEFAULT_VERIFY_TIMEOUT_SECS;
+use crate::ralph_loop::{CompletionOption, DiffLimitAction, DiffLimits, FailurePolicy, IntegrityAction, LoopEvent, LoopState, PlanChange, ScanConfig, ScanFinding, VerificationResult, DEFAULT_MAX_RETRIES, DEFAULT_COMMAND_TIMEOUT_SECS};
 use crate::spec::openspec::{ChangeInfo, OpenSpecAdapter};
 use crate::spec::SpecAdapter;
 use crate::spec::{Scenario, Story};
-use crate::ui::{CompletionData, CompletionReason, LoopResult};
+use crate::ui::{CleanupConflict, CompletionData, CompletionReason, DiffApproval, LoopResult, RevertedAttempt};
 use anyhow::Result;
 
 /// The current screen being displayed.
@@ -58,6 +63,10 @@ pub enum ResultTab {
     Tasks,
     /// Shows list of changed files from git diff.
     ChangedFiles,
+    /// Shows checkpoint commits per story, which can be rolled back to.
+    Checkpoints,
+    /// Shows failed attempts archived during the run.
+    Attempts,
 }
 
 /// Action to take after a quit key press during loop execution.
@@ -99,6 +108,21 @@ pub struct App {
     pub loop_state: LoopState,
     /// Stream events per story, keyed by story_id.
     pub story_events: HashMap<String, Vec<StreamEvent>>,
+    /// Verification command results per story, keyed by story_id.
+    pub story_verifications: HashMap<String, Vec<VerificationResult>>,
+    /// Pre-commit scan findings per story, keyed by story_id.
+    pub story_scan_findings: HashMap<String, Vec<ScanFinding>>,
+    /// Unchecked task IDs of each COMPLETE signal rejected because tasks.md
+    /// was not updated, keyed by story_id.
+    pub story_signal_mismatches: HashMap<String, Vec<Vec<String>>>,
+    /// Disallowed plan changes per story, with how each batch was handled,
+    /// keyed by story_id.
+    pub story_plan_changes: HashMap<String, Vec<(IntegrityAction, Vec<PlanChange>)>>,
+    /// Exceeded diff limits per story, with how each excess was handled,
+    /// keyed by story_id.
+    pub story_diff_limits: HashMap<String, Vec<(DiffLimitAction, Vec<String>)>>,
+    /// Failed attempts reverted to the last checkpoint, keyed by story_id.
+    pub story_reverts: HashMap<String, Vec<RevertedAttempt>>,
     /// Currently selected story index for navigation.
     pub loop_selected_story: usize,
     /// Active tab in the loop execution screen.
@@ -119,24 +143,71 @@ pub struct App {
     pub result_tab: ResultTab,
     /// Scroll offset for the Tasks tab in result screen.
     pub result_tasks_scroll: usize,
+    /// Index of the selected attempt in the Attempts tab.
+    pub result_attempts_selected: usize,
+    /// Diff of the attempt opened from the Attempts tab, if any.
+    pub result_attempt_diff: Option<String>,
+    /// Scroll offset for the attempt diff.
+    pub result_attempts_scroll: usize,
+    /// Index of the selected checkpoint in the Checkpoints tab.
+    pub result_checkpoints_selected: usize,
+    /// Whether a rollback to the selected checkpoint awaits confirmation.
+    pub result_rollback_confirm: bool,
+    /// Error from the last rollback attempt, shown in the Checkpoints tab.
+    pub result_rollback_error: Option<String>,
     /// Receiver for loop events from the orchestrator.
     pub loop_event_rx: Option<Receiver<LoopEvent>>,
     /// Stop flag to signal the orchestrator to stop.
-    pub loop_stop_flag: Option<Arc<AtomicBool>>,
+    pub loop_stop_token: Option<CancellationToken>,
     /// Handle to the orchestrator thread.
     pub loop_thread: Option<JoinHandle<()>>,
+    /// Coding agent CLI that works on the stories (CLI: --agent, --agent-config).
+    pub agent_config: AgentConfig,
     /// Maximum number of retries per story (CLI: --max-retries).
     pub max_retries: usize,
     /// Timeout in seconds for external commands (CLI: --command-timeout).
     pub command_timeout: u64,
+    /// Timeout in seconds for each verification command (CLI: --verify-timeout).
+    pub verify_timeout: u64,
+    /// Policy applied when a story exceeds max retries (CLI: --failure-policy).
+    pub failure_policy: FailurePolicy,
+    /// Where the ralph branch is checked out (CLI: --checkpoint-backend).
+    pub checkpoint_backend: CheckpointBackend,
+    /// Directory for exported patches, if not the per-change default (CLI: --patch-dir).
+    pub patch_dir: Option<PathBuf>,
+    /// Subject format of checkpoint commits (CLI: --commit-format).
+    pub commit_format: CommitFormat,
+    /// Author identity of checkpoint commits, if not the git user (CLI: --commit-author).
+    pub commit_author: Option<String>,
+    /// Untracked files above this many MiB are reported by the pre-flight checks
+    /// (CLI: --untracked-size-limit).
+    pub untracked_size_limit_mb: u64,
+    /// Scanner run before each checkpoint commit (CLI: --scan-*).
+    pub scan_config: ScanConfig,
+    /// Globs of paths an attempt must not change (CLI: --protect).
+    pub protected_paths: Vec<String>,
+    /// What to do when an attempt changes the plan in tasks.md beyond
+    /// ticking its own tasks (CLI: --plan-integrity).
+    pub integrity_action: IntegrityAction,
+    /// Limits on the size of a story's changes (CLI: --max-files-changed,
+    /// --max-lines-added, --max-lines-removed, --diff-limit-action).
+    pub diff_limits: DiffLimits,
+    /// Story changes over the diff limits, awaiting the user's approval.
+    pub diff_approval: Option<DiffApproval>,
+    /// Oneshot sender for the user's decision on `diff_approval`.
+    pub diff_approval_tx: Option<oneshot::Sender<bool>>,
     /// Count of consecutive 'q' presses for force-quit mechanism.
     pub quit_press_count: usize,
     /// Time of last 'q' press for tracking consecutive presses.
     pub last_quit_time: Option<Instant>,
     /// Completion screen data.
     pub completion_data: CompletionData,
-    /// Story ID that exceeded max retries (if any).
-    pub max_retries_exceeded_story: Option<String>,
+    /// Interrupted run found for the selected change, offered for resume.
+    pub existing_run: Option<ExistingRun>,
+    /// Issues found by the pre-flight checks, awaiting confirmation or abort.
+    pub preflight_issues: Option<Vec<PreflightIssue>>,
+    /// Whether the run awaiting pre-flight confirmation starts over.
+    pub preflight_new_run: bool,
     /// Oneshot sender for communicating user's completion choice to orchestrator.
     /// Stored when AwaitingUserChoice event is received, used when user confirms selection.
     pub completion_choice_tx: Option<oneshot::Sender<CompletionOption>>,
@@ -158,6 +229,12 @@ impl App {
             scenarios_scroll_offset: 0,
             loop_state: LoopState::new(""),
             story_events: HashMap::new(),
+            story_verifications: HashMap::new(),
+            story_scan_findings: HashMap::new(),
+            story_signal_mismatches: HashMap::new(),
+            story_plan_changes: HashMap::new(),
+            story_diff_limits: HashMap::new(),
+            story_reverts: HashMap::new(),
             loop_selected_story: 0,
             loop_tab: LoopTab::default(),
             loop_info_scroll: 0,
@@ -168,19 +245,47 @@ impl App {
             result_scroll_offset: 0,
             result_tab: ResultTab::default(),
             result_tasks_scroll: 0,
+            result_attempts_selected: 0,
+            result_attempt_diff: None,
+            result_attempts_scroll: 0,
+            result_checkpoints_selected: 0,
+            result_rollback_confirm: false,
+            result_rollback_error: None,
             loop_event_rx: None,
-            loop_stop_flag: None,
+            loop_stop_token: None,
             loop_thread: None,
+            agent_config: AgentConfig::default(),
             max_retries: DEFAULT_MAX_RETRIES,
             command_timeout: DEFAULT_COMMAND_TIMEOUT_SECS,
+            verify_timeout: DEFAULT_VERIFY_TIMEOUT_SECS,
+            failure_policy: FailurePolicy::default(),
+            checkpoint_backend: CheckpointBackend::default(),
+            patch_dir: None,
+            commit_format: CommitFormat::default(),
+            commit_author: None,
+            untracked_size_limit_mb: DEFAULT_UNTRACKED_SIZE_LIMIT_MB,
+            scan_config: ScanConfig::default(),
+            protected_paths: Vec::new(),
+            integrity_action: IntegrityAction::default(),
+            diff_limits: DiffLimits::default(),
+            diff_approval: None,
+            diff_approval_tx: None,
             quit_press_count: 0,
             last_quit_time: None,
             completion_data: CompletionData::default(),
-            max_retries_exceeded_story: None,
+            existing_run: None,
+            preflight_issues: None,
+            preflight_new_run: false,
             completion_choice_tx: None,
         }
     }
 
+    /// Sets the coding agent CLI that works on the stories.
+    pub fn with_agent_config(mut self, config: AgentConfig) -> Self {
+        self.agent_config = config;
+        self
+    }
+
     /// Sets the maximum number of retries per story.
     pub fn with_max_retries(mut self, max_retries: usize) -> Self {
         self.max_retries = max_retries;
@@ -193,12 +298,137 @@ impl App {
         self
     }
 
+    /// Sets the timeout in seconds for each verification command.
+    pub fn with_verify_timeout(mut self, timeout: u64) -> Self {
+        self.verify_timeout = timeout;
+        self
+    }
+
+    /// Sets the policy applied when a story exceeds max retries.
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Sets where the ralph branch is checked out while the loop runs.
+    pub fn with_checkpoint_backend(mut self, backend: CheckpointBackend) -> Self {
+        self.checkpoint_backend = backend;
+        self
+    }
+
+    /// Sets the directory patches are exported to; `None` keeps the per-change default.
+    pub fn with_patch_dir(mut self, dir: Option<PathBuf>) -> Self {
+        self.patch_dir = dir;
+        self
+    }
+
+    /// Sets the subject format of checkpoint commits.
+    pub fn with_commit_format(mut self, format: CommitFormat) -> Self {
+        self.commit_format = format;
+        self
+    }
+
+    /// Sets the author identity (`Name <email>`) of checkpoint commits.
+    pub fn with_commit_author(mut self, author: Option<String>) -> Self {
+        self.commit_author = author;
+        self
+    }
+
+    /// Sets the size in MiB above which untracked files are reported before a run.
+    pub fn with_untracked_size_limit(mut self, limit_mb: u64) -> Self {
+        self.untracked_size_limit_mb = limit_mb;
+        self
+    }
+
+    /// Sets the scanner run before each checkpoint commit.
+    pub fn with_scan_config(mut self, config: ScanConfig) -> Self {
+        self.scan_config = config;
+        self
+    }
+
+    /// Sets the globs of paths an attempt must not change.
+    pub fn with_protected_paths(mut self, globs: Vec<String>) -> Self {
+        self.protected_paths = globs;
+        self
+    }
+
+    /// Sets what to do when an attempt changes the plan beyond its own ticks.
+    pub fn with_integrity_action(mut self, action: IntegrityAction) -> Self {
+        self.integrity_action = action;
+        self
+    }
+
+    /// Sets the limits on the size of a story's changes.
+    pub fn with_diff_limits(mut self, limits: DiffLimits) -> Self {
+        self.diff_limits = limits;
+        self
+    }
+
+    /// Returns the directory the export-patch option writes to for a change.
+    fn patch_dir_for(&self, change_name: &str) -> PathBuf {
+        self.patch_dir
+            .clone()
+            .unwrap_or_else(|| checkpoint::default_patch_dir(change_name))
+    }
+
+    /// Runs the pre-flight checks, then starts the loop (resuming an
+    /// interrupted run unless `new_run`).
+    ///
+    /// If the checks report issues, the loop is not started; they are kept
+    /// in `preflight_issues` for the user to confirm or abort.
+    pub fn request_loop_start(&mut self, new_run: bool) {
+        let Some(ref name) = self.selected_change_name else {
+            return;
+        };
+
+        // Check with the backend the loop will run on
+        let timeout = std::time::Duration::from_secs(self.command_timeout);
+        let checkpoint = checkpoint::create_checkpoint(self.checkpoint_backend, name, timeout);
+        let issues = match block_on(checkpoint.preflight(self.untracked_size_limit_mb)) {
+            Some(Ok(issues)) => issues,
+            Some(Err(e)) => vec![PreflightIssue::check_failed(e)],
+            None => vec![PreflightIssue::check_failed("could not start the async runtime")],
+        };
+        if issues.is_empty() {
+            self.launch_loop(new_run);
+        } else {
+            self.preflight_issues = Some(issues);
+            self.preflight_new_run = new_run;
+        }
+    }
+
+    /// Starts the loop, discarding any interrupted run for the change if `new_run`.
+    fn launch_loop(&mut self, new_run: bool) {
+        if new_run {
+            self.existing_run = None;
+        }
+        self.start_loop();
+    }
+
+    /// Starts the loop despite pre-flight warnings. Does nothing while any
+    /// issue blocks the run.
+    pub fn confirm_preflight(&mut self) {
+        match self.preflight_issues.take() {
+            Some(issues) if PreflightIssue::any_blocking(&issues) => {
+                self.preflight_issues = Some(issues);
+            }
+            Some(_) => self.launch_loop(self.preflight_new_run),
+            None => {}
+        }
+    }
+
+    /// Discards the pre-flight issues without starting the loop.
+    pub fn abort_preflight(&mut self) {
+        self.preflight_issues = None;
+    }
+
     /// Starts the loop execution for the selected change.
+    ///
+    /// Resumes on the existing ralph branch if an interrupted run was found.
     pub fn start_loop(&mut self) {
-        use std::sync::atomic::{AtomicBool, Ordering};
         use std::sync::mpsc;
 
-        use crate::agent::ClaudeAgent;
+        use crate::agent;
         use crate::ralph_loop::Orchestrator;
 
         if let Some(ref name) = self.selected_change_name {
@@ -207,26 +437,43 @@ impl App {
             state.running = true;
             self.loop_state = state;
             self.story_events.clear();
+            self.story_verifications.clear();
+            self.story_scan_findings.clear();
+            self.story_signal_mismatches.clear();
+            self.story_plan_changes.clear();
+            self.story_diff_limits.clear();
+            self.story_reverts.clear();
             self.loop_selected_story = 0;
             self.loop_tab = LoopTab::default();
             self.loop_info_scroll = 0;
             self.loop_agent_scroll = 0;
             self.loop_agent_auto_scroll = true;
             self.loop_agent_max_scroll = 0;
-            self.max_retries_exceeded_story = None;
 
             // Create channel for events (std::sync::mpsc for TUI compatibility)
             let (tx, rx) = mpsc::channel();
             self.loop_event_rx = Some(rx);
 
-            // Create stop flag
-            let stop_flag = Arc::new(AtomicBool::new(false));
-            self.loop_stop_flag = Some(Arc::clone(&stop_flag));
+            // Create stop token
+            let stop_token = CancellationToken::new();
+            self.loop_stop_token = Some(stop_token.clone());
 
             // Spawn orchestrator in background thread with tokio runtime
             let change_name = name.clone();
+            let agent_config = self.agent_config.clone();
             let max_retries = self.max_retries;
             let command_timeout = self.command_timeout;
+            let verify_timeout = self.verify_timeout;
+            let failure_policy = self.failure_policy;
+            let checkpoint_backend = self.checkpoint_backend;
+            let patch_dir = self.patch_dir_for(name);
+            let commit_format = self.commit_format;
+            let commit_author = self.commit_author.clone();
+            let scan_config = self.scan_config.clone();
+            let protected_paths = self.protected_paths.clone();
+            let integrity_action = self.integrity_action;
+            let diff_limits = self.diff_limits;
+            let resume = self.existing_run.take().is_some();
             let handle = std::thread::spawn(move || {
                 // Create tokio runtime for async orchestrator
                 let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
@@ -246,22 +493,22 @@ impl App {
                     });
 
                     // Create and run orchestrator
-                    let agent = Box::new(ClaudeAgent::new());
+                    let agent = agent::create_agent(&agent_config);
                     let mut orchestrator =
                         Orchestrator::new(&change_name, agent, tokio_tx, max_retries)
-                            .with_command_timeout(command_timeout);
-
-                    // Set the stop flag on the orchestrator
-                    let orch_stop = orchestrator.stop_handle();
-                    tokio::spawn(async move {
-                        loop {
-                            if stop_flag.load(Ordering::Relaxed) {
-                                orch_stop.store(true, Ordering::Relaxed);
-                                break;
-                            }
-                            tokio::time::sleep(tokio::time::Duration::from_millis(100)).await;
-                        }
-                    });
+                            .with_command_timeout(command_timeout)
+                            .with_verify_timeout(verify_timeout)
+                            .with_failure_policy(failure_policy)
+                            .with_checkpoint_backend(checkpoint_backend)
+                            .with_patch_dir(patch_dir)
+                            .with_commit_format(commit_format)
+                            .with_commit_author(commit_author)
+                            .with_scan_config(scan_config)
+                            .with_protected_paths(protected_paths)
+                            .with_integrity_action(integrity_action)
+                            .with_diff_limits(diff_limits)
+                            .with_resume(resume)
+                            .with_stop_token(stop_token);
 
                     let _ = orchestrator.run().await;
                 });
@@ -278,20 +525,30 @@ impl App {
         self.result_scroll_offset = 0;
         self.result_tab = ResultTab::default();
         self.result_tasks_scroll = 0;
+        self.result_attempts_selected = 0;
+        self.result_attempt_diff = None;
+        self.result_attempts_scroll = 0;
+        self.result_checkpoints_selected = 0;
+        self.result_rollback_confirm = false;
+        self.result_rollback_error = None;
         self.screen = Screen::LoopResult;
     }
 
     /// Transitions to the completion screen with the given reason.
     pub fn show_completion_screen(&mut self, reason: CompletionReason, original_branch: String, ralph_branch: String) {
+        let patch_dir = self.patch_dir_for(&self.loop_state.change_name);
         self.completion_data = CompletionData {
             stories_completed: self.loop_state.completed_stories,
             stories_total: self.loop_state.total_stories,
             original_branch,
             ralph_branch,
+            patch_dir: patch_dir.display().to_string(),
+            options: self.checkpoint_backend.completion_options(),
             selected_option: 0,
             in_progress: false,
             progress_message: None,
             completion_reason: reason,
+            conflict: None,
         };
         self.screen = Screen::LoopCompletion;
     }
@@ -312,13 +569,46 @@ impl App {
             let option = self.completion_data.selected_completion_option();
 
             // Update progress message
-            self.completion_data.progress_message = Some(match option {
-                CompletionOption::Cleanup => format!("Returning to {}...", self.completion_data.original_branch),
-                CompletionOption::Keep => format!("Staying on {}...", self.completion_data.ralph_branch),
+            self.completion_data.progress_message = Some(match (option, self.checkpoint_backend) {
+                (CompletionOption::Cleanup, CheckpointBackend::InPlace) => {
+                    format!("Returning to {}...", self.completion_data.original_branch)
+                }
+                (CompletionOption::Cleanup, CheckpointBackend::Worktree) => format!(
+                    "Merging {} into {}...",
+                    self.completion_data.ralph_branch, self.completion_data.original_branch
+                ),
+                (CompletionOption::Keep, CheckpointBackend::InPlace) => {
+                    format!("Staying on {}...", self.completion_data.ralph_branch)
+                }
+                (CompletionOption::Keep, CheckpointBackend::Worktree) => {
+                    format!("Keeping the worktree of {}...", self.completion_data.ralph_branch)
+                }
+                (CompletionOption::Cleanup, CheckpointBackend::Snapshot) => {
+                    "Discarding snapshots...".to_string()
+                }
+                (CompletionOption::Keep, CheckpointBackend::Snapshot) => format!(
+                    "Keeping snapshots in {}...",
+                    checkpoint::default_snapshot_dir(
+                        &std::env::current_dir().unwrap_or_default(),
+                        &self.loop_state.change_name
+                    )
+                    .display()
+                ),
+                (CompletionOption::Squash, _) => {
+                    format!("Squashing changes into one commit on {}...", self.completion_data.original_branch)
+                }
+                (CompletionOption::PreserveHistory, _) => format!(
+                    "Landing checkpoint commits on {}...",
+                    self.completion_data.original_branch
+                ),
+                (CompletionOption::ExportPatch, _) => {
+                    format!("Exporting patches to {}...", self.completion_data.patch_dir)
+                }
             });
 
             // Set in_progress to show progress indicator
             self.completion_data.in_progress = true;
+            self.completion_data.conflict = None;
 
             // Send the choice - ignore error if receiver is dropped
             let _ = tx.send(option);
@@ -329,20 +619,40 @@ impl App {
         }
     }
 
+    /// Sends the option that ran into a conflict again.
+    pub fn retry_completion_choice(&mut self) -> bool {
+        match self.completion_data.conflict.as_ref().map(|c| c.option) {
+            Some(option) => {
+                self.completion_data.select(option);
+                self.send_completion_choice()
+            }
+            None => false,
+        }
+    }
+
     /// Builds a LoopResult from current state and git diff.
+    ///
+    /// Everything is read through the backend the loop ran on, from the
+    /// directory that holds the work once the loop finished.
     pub fn build_loop_result(&self) -> LoopResult {
+        let Some(ref name) = self.selected_change_name else {
+            return LoopResult::default();
+        };
+        let timeout = std::time::Duration::from_secs(self.command_timeout);
+        let checkpoint = checkpoint::create_checkpoint(self.checkpoint_backend, name, timeout);
+        let result_dir = block_on(checkpoint.result_dir())
+            .and_then(|result| result.ok())
+            .or_else(|| std::env::current_dir().ok())
+            .unwrap_or_default();
+
         // Get changed files from git diff
-        let changed_files = Self::get_changed_files();
+        let changed_files = Self::get_changed_files(&result_dir);
 
         // Re-parse tasks.md to get updated completion status
-        let stories = if let Some(ref name) = self.selected_change_name {
-            OpenSpecAdapter::new(name)
-                .ok()
-                .map(|adapter| adapter.stories().unwrap_or_default())
-                .unwrap_or_default()
-        } else {
-            Vec::new()
-        };
+        let stories = block_on(OpenSpecAdapter::new_async_in_dir(name, &result_dir, timeout))
+            .and_then(|result| result.ok())
+            .map(|adapter| adapter.stories().unwrap_or_default())
+            .unwrap_or_default();
 
         // Calculate completion statistics
         let stories_total = stories.len();
@@ -354,23 +664,36 @@ impl App {
             .filter(|t| t.done)
             .count();
 
+        // Failed attempts archived by the checkpoint layer
+        let attempts = block_on(checkpoint.list_attempts())
+            .and_then(|result| result.ok())
+            .unwrap_or_default();
+
+        // Story checkpoints that can be rolled back to
+        let checkpoints = block_on(checkpoint.list_checkpoints())
+            .and_then(|result| result.ok())
+            .unwrap_or_default();
+
         LoopResult {
-            change_name: self.selected_change_name.clone().unwrap_or_default(),
+            change_name: name.clone(),
             stories_completed,
             stories_total,
             tasks_completed,
             tasks_total,
             changed_files,
             stories,
+            attempts,
+            checkpoints,
         }
     }
 
-    /// Gets changed files from git diff.
-    fn get_changed_files() -> Vec<String> {
+    /// Gets changed files from git diff in `dir`.
+    fn get_changed_files(dir: &std::path::Path) -> Vec<String> {
         use std::process::Command;
 
         let output = Command::new("git")
             .args(["diff", "--name-status", "HEAD"])
+            .current_dir(dir)
             .output();
 
         match output {
@@ -384,64 +707,116 @@ impl App {
         }
     }
 
-    /// Gets the original branch name by checking what branch was active before ralph branch.
-    /// This is a heuristic - we check if we're on a ralph/ branch and try to determine
-    /// what branch was used before. Falls back to reading from git symbolic-ref.
-    fn get_original_branch() -> Option<String> {
-        use std::process::Command;
+    /// Looks for an interrupted run of the change on its ralph branch.
+    ///
+    /// Branches, refs and run state are shared by all worktrees, so the
+    /// in-place checkpoint answers these queries for either backend.
+    fn find_existing_run(change_name: &str) -> Option<ExistingRun> {
+        block_on(InPlaceCheckpoint::new(change_name).find_existing_run())?
+            .ok()
+            .flatten()
+    }
 
-        // Get current branch
-        let output = Command::new("git")
-            .args(["rev-parse", "--abbrev-ref", "HEAD"])
-            .output()
-            .ok()?;
+    /// Gets the original branch recorded by the checkpoint layer for a change.
+    fn get_original_branch(change_name: &str) -> Option<String> {
+        block_on(InPlaceCheckpoint::new(change_name).load_run_state())?
+            .ok()
+            .flatten()
+            .map(|state| state.original_branch)
+    }
 
-        if !output.status.success() {
-            return None;
-        }
+    /// Cycles through the tabs of the result screen.
+    pub fn switch_result_tab(&mut self) {
+        self.close_attempt_diff();
+        self.cancel_rollback();
+        self.result_tab = match self.result_tab {
+            ResultTab::Tasks => ResultTab::ChangedFiles,
+            ResultTab::ChangedFiles => ResultTab::Checkpoints,
+            ResultTab::Checkpoints => ResultTab::Attempts,
+            ResultTab::Attempts => ResultTab::Tasks,
+        };
+    }
 
-        let current = String::from_utf8_lossy(&output.stdout).trim().to_string();
-
-        // If we're on a ralph/ branch, the original branch info is stored in the checkpoint
-        // For now, return "main" as a fallback - the real original branch is stored in
-        // the Checkpoint struct which we'd need to persist somehow
-        if current.starts_with("ralph/") {
-            // Try to find the default branch (main or master)
-            let default_output = Command::new("git")
-                .args(["symbolic-ref", "refs/remotes/origin/HEAD", "--short"])
-                .output()
-                .ok()?;
-
-            if default_output.status.success() {
-                let default_ref = String::from_utf8_lossy(&default_output.stdout).trim().to_string();
-                // Extract branch name from "origin/main" -> "main"
-                return default_ref.strip_prefix("origin/").map(String::from);
-            }
+    /// Opens the diff of the selected attempt in the Attempts tab.
+    pub fn open_selected_attempt_diff(&mut self) {
+        if self.result_tab != ResultTab::Attempts {
+            return;
+        }
+        let Some(attempt) = self.loop_result.attempts.get(self.result_attempts_selected) else {
+            return;
+        };
+        let checkpoint = self.result_checkpoint();
+        let diff = block_on(checkpoint.attempt_diff(&attempt.ref_name))
+            .map(|result| result.unwrap_or_else(|e| format!("Failed to load diff: {}", e)))
+            .unwrap_or_default();
+        self.result_attempt_diff = Some(diff);
+        self.result_attempts_scroll = 0;
+    }
 
-            // Fallback to checking if main or master exists
-            let main_exists = Command::new("git")
-                .args(["rev-parse", "--verify", "main"])
-                .output()
-                .map(|o| o.status.success())
-                .unwrap_or(false);
+    /// Returns the checkpoint backend the shown result was built from.
+    fn result_checkpoint(&self) -> Box<dyn checkpoint::Checkpoint> {
+        let timeout = std::time::Duration::from_secs(self.command_timeout);
+        checkpoint::create_checkpoint(self.checkpoint_backend, &self.loop_result.change_name, timeout)
+    }
 
-            if main_exists {
-                return Some("main".to_string());
-            }
+    /// Closes an open attempt diff. Returns true if one was open.
+    pub fn close_attempt_diff(&mut self) -> bool {
+        self.result_attempts_scroll = 0;
+        self.result_attempt_diff.take().is_some()
+    }
 
-            return Some("master".to_string());
+    /// Asks for confirmation to roll back to the selected checkpoint.
+    pub fn request_rollback(&mut self) {
+        if self.result_tab == ResultTab::Checkpoints
+            && self.result_checkpoints_selected < self.loop_result.checkpoints.len()
+        {
+            self.result_rollback_confirm = true;
+            self.result_rollback_error = None;
         }
+    }
 
-        // Not on a ralph branch - return current
-        Some(current)
+    /// Cancels a pending rollback. Returns true if one was pending.
+    pub fn cancel_rollback(&mut self) -> bool {
+        std::mem::take(&mut self.result_rollback_confirm)
     }
 
-    /// Switches between Tasks and ChangedFiles tabs in the result screen.
-    pub fn switch_result_tab(&mut self) {
-        self.result_tab = match self.result_tab {
-            ResultTab::Tasks => ResultTab::ChangedFiles,
-            ResultTab::ChangedFiles => ResultTab::Tasks,
+    /// Resets the ralph branch to the selected checkpoint and re-runs the
+    /// loop from there, resuming the run.
+    ///
+    /// Pre-flight issues are shown on the preview screen as for any start.
+    pub fn confirm_rollback(&mut self) {
+        if !self.cancel_rollback() {
+            return;
+        }
+        let Some(checkpoint) = self
+            .loop_result
+            .checkpoints
+            .get(self.result_checkpoints_selected)
+            .cloned()
+        else {
+            return;
         };
+        let change_name = self.loop_result.change_name.clone();
+
+        match block_on(self.result_checkpoint().rollback_to(&checkpoint.commit)) {
+            Some(Ok(())) => {}
+            Some(Err(e)) => {
+                self.result_rollback_error = Some(e.to_string());
+                return;
+            }
+            None => return,
+        }
+
+        self.existing_run = Self::find_existing_run(&change_name);
+        self.selected_change_name = Some(change_name);
+        if let Err(e) = self.load_selected_change() {
+            self.result_rollback_error = Some(e.to_string());
+            return;
+        }
+        self.request_loop_start(false);
+        if self.preflight_issues.is_some() {
+            self.screen = Screen::ConversionPreview;
+        }
     }
 
     /// Scrolls up in the Tasks tab of the result screen.
@@ -461,6 +836,15 @@ impl App {
         match self.result_tab {
             ResultTab::Tasks => self.result_tasks_scroll = self.result_tasks_scroll.saturating_sub(1),
             ResultTab::ChangedFiles => self.result_scroll_offset = self.result_scroll_offset.saturating_sub(1),
+            ResultTab::Checkpoints => {
+                self.result_checkpoints_selected = self.result_checkpoints_selected.saturating_sub(1)
+            }
+            ResultTab::Attempts if self.result_attempt_diff.is_some() => {
+                self.result_attempts_scroll = self.result_attempts_scroll.saturating_sub(1)
+            }
+            ResultTab::Attempts => {
+                self.result_attempts_selected = self.result_attempts_selected.saturating_sub(1)
+            }
         }
     }
 
@@ -469,6 +853,17 @@ impl App {
         match self.result_tab {
             ResultTab::Tasks => self.result_tasks_scroll = self.result_tasks_scroll.saturating_add(1),
             ResultTab::ChangedFiles => self.result_scroll_offset = self.result_scroll_offset.saturating_add(1),
+            ResultTab::Checkpoints => {
+                let last = self.loop_result.checkpoints.len().saturating_sub(1);
+                self.result_checkpoints_selected = (self.result_checkpoints_selected + 1).min(last);
+            }
+            ResultTab::Attempts if self.result_attempt_diff.is_some() => {
+                self.result_attempts_scroll = self.result_attempts_scroll.saturating_add(1)
+            }
+            ResultTab::Attempts => {
+                let last = self.loop_result.attempts.len().saturating_sub(1);
+                self.result_attempts_selected = (self.result_attempts_selected + 1).min(last);
+            }
         }
     }
 
@@ -505,7 +900,10 @@ impl App {
     /// Selects a change by index and loads its data.
     pub fn select_change(&mut self, index: usize) -> Result<()> {
         if index < self.available_changes.len() {
-            self.selected_change_name = Some(self.available_changes[index].name.clone());
+            let name = self.available_changes[index].name.clone();
+            self.existing_run = Self::find_existing_run(&name);
+            self.preflight_issues = None;
+            self.selected_change_name = Some(name);
             self.load_selected_change()?;
             self.screen = Screen::ConversionPreview;
             self.scroll_offset = 0;
@@ -685,20 +1083,97 @@ impl App {
                         .or_default()
                         .push(event);
                 }
+                LoopEvent::Verification { story_id, result } => {
+                    // Store verification results for the Info tab
+                    self.story_verifications
+                        .entry(story_id)
+                        .or_default()
+                        .push(result);
+                }
+                LoopEvent::ScanFindings { story_id, findings, .. } => {
+                    // Store scan findings for the Info tab
+                    self.story_scan_findings
+                        .entry(story_id)
+                        .or_default()
+                        .extend(findings);
+                }
+                LoopEvent::SignalStateMismatch { story_id, unchecked_tasks } => {
+                    // Store why the COMPLETE signal was rejected for the Info tab
+                    self.story_signal_mismatches
+                        .entry(story_id)
+                        .or_default()
+                        .push(unchecked_tasks);
+                }
+                LoopEvent::PlanChanged { story_id, changes, action } => {
+                    // Store rejected or repaired plan changes for the Info tab
+                    self.story_plan_changes
+                        .entry(story_id)
+                        .or_default()
+                        .push((action, changes));
+                }
+                LoopEvent::DiffLimitExceeded { story_id, exceeded, action, .. } => {
+                    // Store the exceeded limits for the Info tab; approvals arrive separately
+                    self.story_diff_limits
+                        .entry(story_id)
+                        .or_default()
+                        .push((action, exceeded));
+                }
+                LoopEvent::AwaitingDiffApproval {
+                    story_id,
+                    stat,
+                    exceeded,
+                    approve_tx,
+                } => {
+                    // Shown over the loop screen until the user decides
+                    self.diff_approval = Some(DiffApproval { story_id, stat, exceeded });
+                    self.diff_approval_tx = Some(approve_tx);
+                }
+                LoopEvent::Reverted {
+                    story_id,
+                    restored,
+                    removed,
+                    archive_ref,
+                } => {
+                    // Store what the revert touched for the Info tab
+                    self.story_reverts
+                        .entry(story_id)
+                        .or_default()
+                        .push(RevertedAttempt {
+                            restored,
+                            removed,
+                            archive_ref,
+                        });
+                }
+                LoopEvent::PatchesExported { .. } => {
+                    // The target directory is shown on the completion screen
+                }
+                LoopEvent::CleanupConflict { option, branch, files } => {
+                    // Shown once the orchestrator asks for a choice again
+                    self.completion_data.conflict = Some(CleanupConflict { option, branch, files });
+                }
                 LoopEvent::Error { message: _ } => {
                     // Errors are logged but not stored in story_events
                 }
                 LoopEvent::MaxRetriesExceeded { story_id } => {
-                    // Store the story that exceeded max retries
-                    self.max_retries_exceeded_story = Some(story_id);
+                    // Record the story that exceeded max retries
+                    self.loop_state.failed_story_ids.push(story_id);
                 }
                 LoopEvent::AwaitingUserChoice { choice_tx } => {
                     // Store the sender for later use when user confirms selection
                     self.completion_choice_tx = Some(choice_tx);
 
+                    // Asked again after a conflict: offer keep or retry
+                    if self.screen == Screen::LoopCompletion {
+                        self.completion_data.in_progress = false;
+                        self.completion_data.progress_message = None;
+                        continue;
+                    }
+
                     // Determine completion reason based on state
-                    let reason = if let Some(story_id) = self.max_retries_exceeded_story.clone() {
-                        CompletionReason::MaxRetries { story_id }
+                    let reason = if !self.loop_state.failed_story_ids.is_empty() {
+                        CompletionReason::MaxRetries {
+                            story_ids: self.loop_state.failed_story_ids.clone(),
+                        }
                     } else if !self.loop_state.running {
                         // Loop was stopped by user
                         CompletionReason::UserStop
@@ -709,7 +1184,8 @@ impl App {
                     // Derive branch names
                     let change_name = self.selected_change_name.clone().unwrap_or_default();
                     let ralph_branch = format!("ralph/{}", change_name);
-                    let original_branch = Self::get_original_branch().unwrap_or_else(|| "main".to_string());
+                    let original_branch = Self::get_original_branch(&change_name)
+                        .unwrap_or_else(|| "unknown".to_string());
 
                     // Reset quit counter before transitioning
                     self.reset_quit_counter();
@@ -734,10 +1210,20 @@ impl App {
 
     /// Requests the loop to stop gracefully.
     pub fn request_loop_stop(&mut self) {
-        use std::sync::atomic::Ordering;
+        if let Some(ref token) = self.loop_stop_token {
+            token.cancel();
+        }
+
+        // A pending diff approval would keep the orchestrator waiting
+        self.answer_diff_approval(false);
+    }
 
-        if let Some(ref flag) = self.loop_stop_flag {
-            flag.store(true, Ordering::Relaxed);
+    /// Sends the user's decision on the pending diff approval to the
+    /// orchestrator: `true` commits the story, `false` fails the attempt.
+    pub fn answer_diff_approval(&mut self, accept: bool) {
+        self.diff_approval = None;
+        if let Some(tx) = self.diff_approval_tx.take() {
+            let _ = tx.send(accept);
         }
     }
 
@@ -955,11 +1441,17 @@ impl App {
 
         // Clear loop-related state
         self.loop_event_rx = None;
-        self.loop_stop_flag = None;
+        self.loop_stop_token = None;
         self.loop_state = LoopState::new("");
 
         // Clear story navigation and tab state
         self.story_events.clear();
+        self.story_verifications.clear();
+        self.story_scan_findings.clear();
+        self.story_signal_mismatches.clear();
+        self.story_plan_changes.clear();
+        self.story_diff_limits.clear();
+        self.story_reverts.clear();
         self.loop_selected_story = 0;
         self.loop_tab = LoopTab::default();
         self.loop_info_scroll = 0;
@@ -969,10 +1461,20 @@ impl App {
     }
 }
 
+/// Runs a checkpoint query from the synchronous TUI thread.
+///
+/// Returns `None` if a runtime could not be created.
+fn block_on<F: std::future::Future>(future: F) -> Option<F::Output> {
+    let rt = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .ok()?;
+    Some(rt.block_on(future))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::sync::atomic::Ordering;
     use std::sync::mpsc;
 
     #[test]
@@ -1059,6 +1561,250 @@ mod tests {
         assert!(!completed);
     }
 
+    #[test]
+    fn process_loop_events_stores_verification_results() {
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::Verification {
+            story_id: "1".to_string(),
+            result: VerificationResult {
+                command: "cargo test".to_string(),
+                passed: false,
+                output: "1 failed".to_string(),
+            },
+        })
+        .unwrap();
+
+        let completed = app.process_loop_events();
+
+        assert!(!completed);
+        let results = app.story_verifications.get("1").expect("results stored");
+        assert_eq!(results.len(), 1);
+        assert!(!results[0].passed);
+        assert_eq!(results[0].command, "cargo test");
+    }
+
+    #[test]
+    fn process_loop_events_stores_signal_mismatches() {
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::SignalStateMismatch {
+            story_id: "1".to_string(),
+            unchecked_tasks: vec!["1.2".to_string()],
+        })
+        .unwrap();
+
+        app.process_loop_events();
+
+        let mismatches = app.story_signal_mismatches.get("1").expect("mismatch stored");
+        assert_eq!(mismatches, &vec![vec!["1.2".to_string()]]);
+    }
+
+    #[test]
+    fn process_loop_events_stores_plan_changes() {
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::PlanChanged {
+            story_id: "1".to_string(),
+            changes: vec![PlanChange::TaskRemoved {
+                task_id: "2.1".to_string(),
+            }],
+            action: IntegrityAction::Repair,
+        })
+        .unwrap();
+
+        app.process_loop_events();
+
+        let changes = app.story_plan_changes.get("1").expect("plan changes stored");
+        assert_eq!(changes.len(), 1);
+        assert_eq!(changes[0].0, IntegrityAction::Repair);
+        assert_eq!(changes[0].1[0].describe(), "task 2.1 was removed");
+    }
+
+    #[test]
+    fn process_loop_events_stores_exceeded_diff_limits() {
+        use crate::ralph_loop::DiffStat;
+
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::DiffLimitExceeded {
+            story_id: "1".to_string(),
+            stat: DiffStat::default(),
+            exceeded: vec!["42 files changed (limit 10)".to_string()],
+            action: DiffLimitAction::Fail,
+        })
+        .unwrap();
+
+        app.process_loop_events();
+
+        let limits = app.story_diff_limits.get("1").expect("exceeded limits stored");
+        assert_eq!(
+            limits,
+            &vec![(DiffLimitAction::Fail, vec!["42 files changed (limit 10)".to_string()])]
+        );
+    }
+
+    #[test]
+    fn process_loop_events_stores_reverts() {
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::Reverted {
+            story_id: "1".to_string(),
+            restored: vec!["src/lib.rs".to_string()],
+            removed: vec!["scratch.txt".to_string()],
+            archive_ref: Some("refs/ralph/my-change/attempts/1-1".to_string()),
+        })
+        .unwrap();
+
+        app.process_loop_events();
+
+        let reverts = app.story_reverts.get("1").expect("revert stored");
+        assert_eq!(
+            reverts,
+            &vec![RevertedAttempt {
+                restored: vec!["src/lib.rs".to_string()],
+                removed: vec!["scratch.txt".to_string()],
+                archive_ref: Some("refs/ralph/my-change/attempts/1-1".to_string()),
+            }]
+        );
+    }
+
+    #[test]
+    fn diff_approval_is_shown_and_answered() {
+        use crate::ralph_loop::DiffStat;
+
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+        app.loop_state.running = true;
+
+        let (approve_tx, mut approve_rx) = oneshot::channel();
+        tx.send(LoopEvent::AwaitingDiffApproval {
+            story_id: "2".to_string(),
+            stat: DiffStat::default(),
+            exceeded: vec!["42 files changed (limit 10)".to_string()],
+            approve_tx,
+        })
+        .unwrap();
+
+        app.process_loop_events();
+        assert_eq!(app.diff_approval.as_ref().map(|a| a.story_id.as_str()), Some("2"));
+
+        app.answer_diff_approval(true);
+        assert!(app.diff_approval.is_none());
+        assert_eq!(approve_rx.try_recv(), Ok(true));
+    }
+
+    #[test]
+    fn stopping_the_loop_rejects_pending_diff_approval() {
+        let mut app = App::new();
+        let (approve_tx, mut approve_rx) = oneshot::channel();
+        app.diff_approval_tx = Some(approve_tx);
+
+        app.request_loop_stop();
+
+        assert_eq!(approve_rx.try_recv(), Ok(false));
+    }
+
+    #[test]
+    fn process_loop_events_stores_scan_findings() {
+        use crate::ralph_loop::scan::{FindingKind, ScanAction};
+
+        let mut app = App::new();
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+        app.loop_state.running = true;
+
+        tx.send(LoopEvent::ScanFindings {
+            story_id: "1".to_string(),
+            findings: vec![ScanFinding {
+                path: "big.bin".to_string(),
+                kind: FindingKind::TooLarge { size: 10 },
+            }],
+            action: ScanAction::Unstage,
+        })
+        .unwrap();
+
+        app.process_loop_events();
+
+        let findings = app.story_scan_findings.get("1").expect("findings stored");
+        assert_eq!(findings[0].path, "big.bin");
+    }
+
+    #[test]
+    fn process_loop_events_lists_all_failed_stories_on_completion() {
+        let mut app = App::new();
+        app.loop_state.running = true;
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        tx.send(LoopEvent::MaxRetriesExceeded {
+            story_id: "2".to_string(),
+        })
+        .unwrap();
+        tx.send(LoopEvent::MaxRetriesExceeded {
+            story_id: "4".to_string(),
+        })
+        .unwrap();
+        let (choice_tx, _choice_rx) = oneshot::channel();
+        tx.send(LoopEvent::AwaitingUserChoice { choice_tx }).unwrap();
+
+        app.process_loop_events();
+
+        assert_eq!(app.loop_state.failed_story_ids, vec!["2", "4"]);
+        assert_eq!(app.screen, Screen::LoopCompletion);
+        assert_eq!(
+            app.completion_data.completion_reason,
+            CompletionReason::MaxRetries {
+                story_ids: vec!["2".to_string(), "4".to_string()],
+            }
+        );
+    }
+
+    #[test]
+    fn process_loop_events_offers_retry_after_cleanup_conflict() {
+        let mut app = App::new();
+        app.loop_state.running = true;
+        let (tx, rx) = mpsc::channel();
+        app.loop_event_rx = Some(rx);
+
+        let (choice_tx, _first_rx) = oneshot::channel();
+        tx.send(LoopEvent::AwaitingUserChoice { choice_tx }).unwrap();
+        app.process_loop_events();
+        app.completion_data.select(CompletionOption::Squash);
+        assert!(app.send_completion_choice());
+
+        tx.send(LoopEvent::CleanupConflict {
+            option: CompletionOption::Squash,
+            branch: "main".to_string(),
+            files: vec!["a.txt".to_string()],
+        })
+        .unwrap();
+        let (choice_tx, mut retry_rx) = oneshot::channel();
+        tx.send(LoopEvent::AwaitingUserChoice { choice_tx }).unwrap();
+        app.process_loop_events();
+
+        assert_eq!(app.screen, Screen::LoopCompletion);
+        assert!(!app.completion_data.in_progress);
+        let conflict = app.completion_data.conflict.clone().unwrap();
+        assert_eq!(conflict.files, vec!["a.txt"]);
+
+        app.completion_data.select_keep();
+        assert!(app.retry_completion_choice());
+        assert_eq!(retry_rx.try_recv().unwrap(), CompletionOption::Squash);
+        assert!(app.completion_data.conflict.is_none());
+    }
+
     #[test]
     fn process_loop_events_returns_false_when_no_receiver() {
         let mut app = App::new();
@@ -1070,20 +1816,20 @@ mod tests {
     }
 
     #[test]
-    fn request_loop_stop_sets_flag() {
+    fn request_loop_stop_cancels_token() {
         let mut app = App::new();
-        let stop_flag = Arc::new(AtomicBool::new(false));
-        app.loop_stop_flag = Some(Arc::clone(&stop_flag));
+        let stop_token = CancellationToken::new();
+        app.loop_stop_token = Some(stop_token.clone());
 
         app.request_loop_stop();
 
-        assert!(stop_flag.load(Ordering::Relaxed));
+        assert!(stop_token.is_cancelled());
     }
 
     #[test]
-    fn request_loop_stop_handles_no_flag() {
+    fn request_loop_stop_handles_no_token() {
         let mut app = App::new();
-        app.loop_stop_flag = None;
+        app.loop_stop_token = None;
 
         app.request_loop_stop(); // Should not panic
     }
@@ -1093,12 +1839,16 @@ mod tests {
         let mut app = App::new();
         let (_, rx) = mpsc::channel::<LoopEvent>();
         app.loop_event_rx = Some(rx);
-        app.loop_stop_flag = Some(Arc::new(AtomicBool::new(false)));
+        app.loop_stop_token = Some(CancellationToken::new());
         app.loop_state = LoopState::new("test-change");
         app.loop_state.running = true;
 
         // Set up story navigation state
         app.story_events.insert("1".to_string(), vec![StreamEvent::Message("test".to_string())]);
+        app.story_scan_findings.insert("1".to_string(), Vec::new());
+        app.story_plan_changes.insert("1".to_string(), Vec::new());
+        app.story_diff_limits.insert("1".to_string(), Vec::new());
+        app.story_reverts.insert("1".to_string(), Vec::new());
         app.loop_selected_story = 2;
         app.loop_tab = LoopTab::Agent;
         app.loop_info_scroll = 5;
@@ -1110,11 +1860,15 @@ mod tests {
 
         // Verify original state is cleared
         assert!(app.loop_event_rx.is_none());
-        assert!(app.loop_stop_flag.is_none());
+        assert!(app.loop_stop_token.is_none());
         assert!(!app.loop_state.running);
 
         // Verify new state fields are cleared
         assert!(app.story_events.is_empty());
+        assert!(app.story_scan_findings.is_empty());
+        assert!(app.story_plan_changes.is_empty());
+        assert!(app.story_diff_limits.is_empty());
+        assert!(app.story_reverts.is_empty());
         assert_eq!(app.loop_selected_story, 0);
         assert_eq!(app.loop_tab, LoopTab::Info);
         assert_eq!(app.loop_info_scroll, 0);
@@ -1712,17 +2466,133 @@ mod tests {
     }
 
     #[test]
-    fn switch_result_tab_toggles_between_tasks_and_changed_files() {
+    fn switch_result_tab_cycles_through_all_tabs() {
         let mut app = App::new();
         assert_eq!(app.result_tab, ResultTab::Tasks);
 
         app.switch_result_tab();
         assert_eq!(app.result_tab, ResultTab::ChangedFiles);
 
+        app.switch_result_tab();
+        assert_eq!(app.result_tab, ResultTab::Checkpoints);
+
+        app.switch_result_tab();
+        assert_eq!(app.result_tab, ResultTab::Attempts);
+
         app.switch_result_tab();
         assert_eq!(app.result_tab, ResultTab::Tasks);
     }
 
+    fn attempt(story_id: &str, n: usize) -> crate::checkpoint::Attempt {
+        crate::checkpoint::Attempt {
+            ref_name: format!("refs/ralph/my-change/attempts/{}-{}", story_id, n),
+            story_id: story_id.to_string(),
+            attempt: n,
+            reason: "failed".to_string(),
+        }
+    }
+
+    #[test]
+    fn result_scroll_moves_attempt_selection_within_bounds() {
+        let mut app = App::new();
+        app.result_tab = ResultTab::Attempts;
+        app.loop_result.attempts = vec![attempt("1", 1), attempt("1", 2)];
+
+        app.result_scroll_down();
+        app.result_scroll_down();
+        assert_eq!(app.result_attempts_selected, 1);
+
+        app.result_scroll_up();
+        app.result_scroll_up();
+        assert_eq!(app.result_attempts_selected, 0);
+    }
+
+    #[test]
+    fn result_scroll_scrolls_open_attempt_diff() {
+        let mut app = App::new();
+        app.result_tab = ResultTab::Attempts;
+        app.loop_result.attempts = vec![attempt("1", 1), attempt("1", 2)];
+        app.result_attempt_diff = Some("diff".to_string());
+
+        app.result_scroll_down();
+
+        assert_eq!(app.result_attempts_scroll, 1);
+        assert_eq!(app.result_attempts_selected, 0);
+        assert!(app.close_attempt_diff());
+        assert!(!app.close_attempt_diff());
+        assert_eq!(app.result_attempts_scroll, 0);
+    }
+
+    fn story_checkpoint(story_id: &str) -> crate::checkpoint::StoryCheckpoint {
+        crate::checkpoint::StoryCheckpoint {
+            commit: format!("{:0>40}", story_id),
+            story_id: story_id.to_string(),
+            subject: format!("checkpoint: {}", story_id),
+            files_changed: 1,
+            insertions: 1,
+            deletions: 0,
+        }
+    }
+
+    #[test]
+    fn result_scroll_moves_checkpoint_selection_within_bounds() {
+        let mut app = App::new();
+        app.result_tab = ResultTab::Checkpoints;
+        app.loop_result.checkpoints = vec![story_checkpoint("1"), story_checkpoint("2")];
+
+        app.result_scroll_down();
+        app.result_scroll_down();
+        assert_eq!(app.result_checkpoints_selected, 1);
+
+        app.result_scroll_up();
+        app.result_scroll_up();
+        assert_eq!(app.result_checkpoints_selected, 0);
+    }
+
+    #[test]
+    fn request_rollback_needs_a_selected_checkpoint() {
+        let mut app = App::new();
+        app.request_rollback();
+        assert!(!app.result_rollback_confirm);
+
+        app.result_tab = ResultTab::Checkpoints;
+        app.request_rollback();
+        assert!(!app.result_rollback_confirm);
+
+        app.loop_result.checkpoints = vec![story_checkpoint("1")];
+        app.request_rollback();
+        assert!(app.result_rollback_confirm);
+
+        app.switch_result_tab();
+        assert!(!app.result_rollback_confirm);
+    }
+
+    #[test]
+    fn confirm_rollback_reports_failure_and_stays_on_result_screen() {
+        let mut app = App::new();
+        app.screen = Screen::LoopResult;
+        app.result_tab = ResultTab::Checkpoints;
+        app.loop_result.change_name = "no-such-change".to_string();
+        app.loop_result.checkpoints = vec![story_checkpoint("1")];
+
+        app.request_rollback();
+        app.confirm_rollback();
+
+        assert!(!app.result_rollback_confirm);
+        assert!(app.result_rollback_error.is_some());
+        assert_eq!(app.screen, Screen::LoopResult);
+    }
+
+    #[test]
+    fn open_selected_attempt_diff_ignored_outside_attempts_tab() {
+        let mut app = App::new();
+        app.loop_result.attempts = vec![attempt("1", 1)];
+
+        app.open_selected_attempt_diff();
+
+        assert!(app.result_attempt_diff.is_none());
+    }
+
     #[test]
     fn result_tasks_scroll_up_decreases_offset() {
         let mut app = App::new();
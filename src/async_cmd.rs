@@ -4,7 +4,8 @@
 //! tokio worker threads. Uses `tokio::task::spawn_blocking()` to run blocking
 //! `std::process::Command` calls on a dedicated thread pool.
 
-use std::process::{Command, Output};
+use std::path::{Path, PathBuf};
+use std::process::{Command, Output, Stdio};
 use std::time::Duration;
 
 use tokio::task::spawn_blocking;
@@ -107,48 +108,32 @@ pub async fn run_with_timeout(
     args: &[&str],
     timeout_duration: Duration,
 ) -> Result<Output> {
-    let program = program.to_string();
-    let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
-    let cmd_str = format!("{} {}", program, args.join(" "));
-
-    // Spawn blocking task for command execution
-    let handle = spawn_blocking(move || {
-        Command::new(&program)
-            .args(&args)
-            .output()
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    AsyncCmdError::NotFound(program.clone())
-                } else {
-                    AsyncCmdError::ExecutionFailed(e.to_string())
-                }
-            })
-    });
-
-    // Apply timeout
-    let result = timeout(timeout_duration, handle).await;
+    let output = spawn_output(program, args, None, timeout_duration).await?;
+    check_status(program, args, output)
+}
 
-    match result {
-        Ok(Ok(Ok(output))) => {
-            // Command executed, check exit status
-            if output.status.success() {
-                Ok(output)
-            } else {
-                let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
-                Err(AsyncCmdError::NonZeroExit {
-                    cmd: cmd_str,
-                    stderr,
-                }
-                .into())
-            }
-        }
-        Ok(Ok(Err(async_err))) => Err(async_err.into()),
-        Ok(Err(join_err)) => Err(Error::Command {
-            cmd: cmd_str,
-            stderr: format!("Task join error: {}", join_err),
-        }),
-        Err(_timeout_err) => Err(AsyncCmdError::Timeout.into()),
-    }
+/// Executes a command asynchronously in `dir` with a configurable timeout.
+///
+/// Like `run_with_timeout()`, but the command runs with `dir` as its
+/// working directory instead of the current process directory.
+///
+/// # Arguments
+/// * `program` - The program to execute
+/// * `args` - Arguments to pass to the program
+/// * `dir` - Working directory for the command
+/// * `timeout_duration` - Maximum time to wait for the command
+///
+/// # Returns
+/// * `Ok(Output)` - The command output on success
+/// * `Err(Error)` - On timeout, command not found, or execution failure
+pub async fn run_in_dir_with_timeout(
+    program: &str,
+    args: &[&str],
+    dir: &Path,
+    timeout_duration: Duration,
+) -> Result<Output> {
+    let output = spawn_output(program, args, Some(dir), timeout_duration).await?;
+    check_status(program, args, output)
 }
 
 /// Executes a command asynchronously and returns the stdout as a string.
@@ -187,6 +172,27 @@ pub async fn run_stdout_with_timeout(
     String::from_utf8(output.stdout).map_err(|e| Error::Parse(format!("Invalid UTF-8: {}", e)))
 }
 
+/// Executes a command asynchronously in `dir` with timeout and returns the stdout as a string.
+///
+/// # Arguments
+/// * `program` - The program to execute
+/// * `args` - Arguments to pass to the program
+/// * `dir` - Working directory for the command
+/// * `timeout_duration` - Maximum time to wait for the command
+///
+/// # Returns
+/// * `Ok(String)` - The stdout output on success
+/// * `Err(Error)` - On timeout, command not found, execution failure, or non-UTF8 output
+pub async fn run_stdout_in_dir_with_timeout(
+    program: &str,
+    args: &[&str],
+    dir: &Path,
+    timeout_duration: Duration,
+) -> Result<String> {
+    let output = run_in_dir_with_timeout(program, args, dir, timeout_duration).await?;
+    String::from_utf8(output.stdout).map_err(|e| Error::Parse(format!("Invalid UTF-8: {}", e)))
+}
+
 /// Executes a command asynchronously, returning the raw Output regardless of exit status.
 ///
 /// Unlike `run()`, this does not treat non-zero exit codes as errors.
@@ -218,24 +224,62 @@ pub async fn run_unchecked_with_timeout(
     program: &str,
     args: &[&str],
     timeout_duration: Duration,
+) -> Result<Output> {
+    spawn_output(program, args, None, timeout_duration).await
+}
+
+/// Executes a command asynchronously in `dir` with timeout, returning raw Output
+/// regardless of exit status.
+///
+/// # Arguments
+/// * `program` - The program to execute
+/// * `args` - Arguments to pass to the program
+/// * `dir` - Working directory for the command
+/// * `timeout_duration` - Maximum time to wait for the command
+///
+/// # Returns
+/// * `Ok(Output)` - The command output (success or failure)
+/// * `Err(Error)` - On timeout, command not found, or execution failure
+pub async fn run_unchecked_in_dir_with_timeout(
+    program: &str,
+    args: &[&str],
+    dir: &Path,
+    timeout_duration: Duration,
+) -> Result<Output> {
+    spawn_output(program, args, Some(dir), timeout_duration).await
+}
+
+/// Runs a command on the blocking thread pool and waits for its output.
+///
+/// The exit status is not checked. `dir` overrides the working directory.
+async fn spawn_output(
+    program: &str,
+    args: &[&str],
+    dir: Option<&Path>,
+    timeout_duration: Duration,
 ) -> Result<Output> {
     let program = program.to_string();
     let args: Vec<String> = args.iter().map(|s| s.to_string()).collect();
+    let dir: Option<PathBuf> = dir.map(Path::to_path_buf);
     let cmd_str = format!("{} {}", program, args.join(" "));
 
+    // Spawn blocking task for command execution
     let handle = spawn_blocking(move || {
-        Command::new(&program)
-            .args(&args)
-            .output()
-            .map_err(|e| {
-                if e.kind() == std::io::ErrorKind::NotFound {
-                    AsyncCmdError::NotFound(program.clone())
-                } else {
-                    AsyncCmdError::ExecutionFailed(e.to_string())
-                }
-            })
+        let mut cmd = Command::new(&program);
+        cmd.args(&args);
+        if let Some(dir) = &dir {
+            cmd.current_dir(dir);
+        }
+        cmd.output().map_err(|e| {
+            if e.kind() == std::io::ErrorKind::NotFound {
+                AsyncCmdError::NotFound(program.clone())
+            } else {
+                AsyncCmdError::ExecutionFailed(e.to_string())
+            }
+        })
     });
 
+    // Apply timeout
     let result = timeout(timeout_duration, handle).await;
 
     match result {
@@ -249,6 +293,60 @@ pub async fn run_unchecked_with_timeout(
     }
 }
 
+/// Kills a process group with SIGKILL when dropped.
+///
+/// Commands spawned with `process_group(0)` lead a group of their own, so
+/// killing the group also reaches the shells and tools they started, which
+/// `kill_on_drop` alone leaves running. Call `disarm` once the command has
+/// exited on its own.
+#[derive(Debug)]
+pub struct ProcessGroupGuard {
+    pgid: Option<u32>,
+}
+
+impl ProcessGroupGuard {
+    /// Guards the group led by the process with ID `pid`, if it has one.
+    pub fn new(pid: Option<u32>) -> Self {
+        Self { pgid: pid }
+    }
+
+    /// Kills the group now.
+    pub fn kill(&mut self) {
+        if let Some(pgid) = self.pgid.take() {
+            let _ = Command::new("kill")
+                .args(["-KILL", "--", &format!("-{}", pgid)])
+                .stdout(Stdio::null())
+                .stderr(Stdio::null())
+                .status();
+        }
+    }
+
+    /// Leaves the group alone when dropped.
+    pub fn disarm(&mut self) {
+        self.pgid = None;
+    }
+}
+
+impl Drop for ProcessGroupGuard {
+    fn drop(&mut self) {
+        self.kill();
+    }
+}
+
+/// Turns a non-zero exit status into a `NonZeroExit` error.
+fn check_status(program: &str, args: &[&str], output: Output) -> Result<Output> {
+    if output.status.success() {
+        Ok(output)
+    } else {
+        let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+        Err(AsyncCmdError::NonZeroExit {
+            cmd: format!("{} {}", program, args.join(" ")),
+            stderr,
+        }
+        .into())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -299,6 +397,29 @@ mod tests {
         assert!(!output.status.success());
     }
 
+    #[tokio::test]
+    async fn run_in_dir_uses_given_working_directory() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let stdout = run_stdout_in_dir_with_timeout("pwd", &[], dir.path(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert_eq!(
+            std::fs::canonicalize(stdout.trim()).unwrap(),
+            std::fs::canonicalize(dir.path()).unwrap()
+        );
+    }
+
+    #[tokio::test]
+    async fn run_in_dir_reports_failed_command() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let result = run_in_dir_with_timeout("false", &[], dir.path(), Duration::from_secs(5)).await;
+        assert!(result.is_err());
+        let output = run_unchecked_in_dir_with_timeout("false", &[], dir.path(), Duration::from_secs(5))
+            .await
+            .unwrap();
+        assert!(!output.status.success());
+    }
+
     #[tokio::test]
     async fn timeout_triggers_error() {
         // Use a very short timeout with a command that takes longer
@@ -0,0 +1,229 @@
+//! Checkpoint commit messages.
+//!
+//! Each checkpoint commit names the story, lists the tasks it completed and
+//! carries `Ralph-*` trailers with the attempt count and the agent's usage.
+//! The `Ralph-Story` trailer identifies checkpoint commits independent of
+//! the subject format.
+
+use crate::agent::Response;
+use crate::spec::Task;
+
+use super::CHECKPOINT_PREFIX;
+
+/// Trailer holding the story ID of a checkpoint commit.
+pub(super) const STORY_TRAILER: &str = "Ralph-Story";
+
+/// `git log` format yielding `<subject>\x1f<story trailer>` per commit, as
+/// read by `parse_checkpoint_story_ids`.
+pub(super) const CHECKPOINT_LOG_FORMAT: &str =
+    "--format=%s%x1f%(trailers:key=Ralph-Story,valueonly,separator=)";
+
+/// `git log` format yielding `<hash>\x1f<subject>\x1f<story trailer>` per
+/// commit, as read by `InPlaceCheckpoint::list_checkpoints`.
+pub(super) const CHECKPOINT_LIST_FORMAT: &str =
+    "--format=%H%x1f%s%x1f%(trailers:key=Ralph-Story,valueonly,separator=)";
+
+/// Subject format of checkpoint commits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CommitFormat {
+    /// `checkpoint: <story id> - <story title>`
+    #[default]
+    Plain,
+    /// Conventional Commits: `feat(<change>): <story title>`
+    Conventional,
+}
+
+/// Everything recorded in a checkpoint commit.
+#[derive(Debug, Clone, Default)]
+pub struct CheckpointCommit {
+    /// ID of the completed story.
+    pub story_id: String,
+    /// Title of the completed story.
+    pub story_title: String,
+    /// Tasks that were ticked off by this story.
+    pub completed_tasks: Vec<Task>,
+    /// Number of attempts the story took, including the successful one.
+    pub attempts: usize,
+    /// Final response of the successful agent run, if it reported one.
+    pub response: Option<Response>,
+    /// Subject format.
+    pub format: CommitFormat,
+    /// Author identity (`Name <email>`) instead of the configured git user.
+    pub author: Option<String>,
+    /// Paths (relative to the repository's top level) left out of the commit.
+    pub excluded_paths: Vec<String>,
+}
+
+/// Parses a commit author given as `Name <email>`, as `git commit --author`
+/// expects it, so a typo is rejected before the loop starts.
+pub fn parse_author(value: &str) -> Result<String, String> {
+    let author = value.trim();
+    let valid = author
+        .strip_suffix('>')
+        .and_then(|rest| rest.split_once('<'))
+        .is_some_and(|(name, email)| {
+            !name.trim().is_empty() && email.contains('@') && !email.contains(['<', '>'])
+        });
+    if valid {
+        Ok(author.to_string())
+    } else {
+        Err(format!("invalid commit author '{}' (expected \"Name <email>\")", value))
+    }
+}
+
+impl CheckpointCommit {
+    /// Creates a checkpoint commit for a story with nothing else recorded.
+    pub fn new(story_id: impl Into<String>) -> Self {
+        Self {
+            story_id: story_id.into(),
+            attempts: 1,
+            ..Default::default()
+        }
+    }
+
+    /// Builds the full commit message for the change.
+    pub fn message(&self, change_name: &str) -> String {
+        let mut message = self.subject(change_name);
+        message.push_str("\n\n");
+
+        if !self.completed_tasks.is_empty() {
+            message.push_str("Completed tasks:\n");
+            for task in &self.completed_tasks {
+                message.push_str(&format!("- {} {}\n", task.id, task.description));
+            }
+            message.push('\n');
+        }
+
+        message.push_str(&format!("{}: {}\n", STORY_TRAILER, self.story_id));
+        message.push_str(&format!("Ralph-Attempts: {}\n", self.attempts));
+        if let Some(response) = &self.response {
+            message.push_str(&format!("Ralph-Turns: {}\n", response.turns));
+            message.push_str(&format!("Ralph-Tokens: {}\n", response.tokens));
+            message.push_str(&format!("Ralph-Cost: {:.4}\n", response.cost));
+        }
+        message
+    }
+
+    /// Builds the subject line.
+    fn subject(&self, change_name: &str) -> String {
+        match (self.format, self.story_title.is_empty()) {
+            (CommitFormat::Plain, true) => format!("{}{}", CHECKPOINT_PREFIX, self.story_id),
+            (CommitFormat::Plain, false) => {
+                format!("{}{} - {}", CHECKPOINT_PREFIX, self.story_id, self.story_title)
+            }
+            (CommitFormat::Conventional, true) => {
+                format!("feat({}): complete story {}", change_name, self.story_id)
+            }
+            (CommitFormat::Conventional, false) => {
+                format!("feat({}): {}", change_name, self.story_title)
+            }
+        }
+    }
+}
+
+/// Extracts story IDs from a log in `CHECKPOINT_LOG_FORMAT`.
+///
+/// The `Ralph-Story` trailer wins; commits without one are recognized by a
+/// `checkpoint: <id>` subject, as written before trailers were added.
+pub(super) fn parse_checkpoint_story_ids(log: &str) -> Vec<String> {
+    log.lines()
+        .filter_map(|line| {
+            let (subject, trailer) = line.split_once('\x1f').unwrap_or((line, ""));
+            checkpoint_story_id(subject, trailer)
+        })
+        .collect()
+}
+
+/// Returns the story ID of a checkpoint commit, or `None` if the commit is
+/// not a checkpoint. The `Ralph-Story` trailer wins over the subject.
+pub(super) fn checkpoint_story_id(subject: &str, trailer: &str) -> Option<String> {
+    let trailer = trailer.trim();
+    if !trailer.is_empty() {
+        return Some(trailer.to_string());
+    }
+    subject
+        .trim()
+        .strip_prefix(CHECKPOINT_PREFIX)
+        .and_then(|rest| rest.split_whitespace().next())
+        .map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn task(id: &str, description: &str) -> Task {
+        Task {
+            id: id.to_string(),
+            description: description.to_string(),
+            done: true,
+        }
+    }
+
+    #[test]
+    fn parse_author_accepts_name_and_email() {
+        assert_eq!(parse_author("Ralph Bot <ralph@example.com>"), Ok("Ralph Bot <ralph@example.com>".to_string()));
+        assert!(parse_author("ralph@example.com").is_err());
+        assert!(parse_author("<ralph@example.com>").is_err());
+        assert!(parse_author("Ralph Bot <>").is_err());
+        assert!(parse_author("Ralph Bot <ralph@example.com").is_err());
+    }
+
+    #[test]
+    fn new_commit_keeps_bare_subject() {
+        let message = CheckpointCommit::new("1").message("my-change");
+        assert_eq!(message, "checkpoint: 1\n\nRalph-Story: 1\nRalph-Attempts: 1\n");
+    }
+
+    #[test]
+    fn message_lists_tasks_and_agent_trailers() {
+        let commit = CheckpointCommit {
+            story_title: "Add parser".to_string(),
+            completed_tasks: vec![task("1.1", "Create module"), task("1.2", "Add tests")],
+            attempts: 2,
+            response: Some(Response {
+                content: String::new(),
+                turns: 7,
+                tokens: 1200,
+                cost: 0.05,
+            }),
+            ..CheckpointCommit::new("1")
+        };
+
+        let message = commit.message("my-change");
+
+        assert!(message.starts_with("checkpoint: 1 - Add parser\n\n"));
+        assert!(message.contains("Completed tasks:\n- 1.1 Create module\n- 1.2 Add tests\n"));
+        assert!(message.ends_with(
+            "Ralph-Story: 1\nRalph-Attempts: 2\nRalph-Turns: 7\nRalph-Tokens: 1200\nRalph-Cost: 0.0500\n"
+        ));
+    }
+
+    #[test]
+    fn conventional_subject_uses_change_as_scope() {
+        let commit = CheckpointCommit {
+            story_title: "Add parser".to_string(),
+            format: CommitFormat::Conventional,
+            ..CheckpointCommit::new("1")
+        };
+        assert!(commit.message("my-change").starts_with("feat(my-change): Add parser\n"));
+
+        let untitled = CheckpointCommit {
+            format: CommitFormat::Conventional,
+            ..CheckpointCommit::new("2")
+        };
+        assert!(untitled.message("my-change").starts_with("feat(my-change): complete story 2\n"));
+    }
+
+    #[test]
+    fn parse_checkpoint_story_ids_prefers_trailer() {
+        let log = "initial state\x1f\nfeat(x): Add parser\x1f3\ncheckpoint: 4 - Title\x1f4\n";
+        assert_eq!(parse_checkpoint_story_ids(log), vec!["3", "4"]);
+    }
+
+    #[test]
+    fn parse_checkpoint_story_ids_falls_back_to_subject() {
+        let log = "initial state\ncheckpoint: 1\nmanual fix\ncheckpoint: 2\n";
+        assert_eq!(parse_checkpoint_story_ids(log), vec!["1", "2"]);
+    }
+}
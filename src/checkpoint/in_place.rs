@@ -0,0 +1,2153 @@
+//! In-place checkpoint backend.
+//!
+//! Switches the current checkout to the `ralph/{change_name}` branch for the
+//! duration of the run.
+
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::commit::{
+    checkpoint_story_id, parse_checkpoint_story_ids, CHECKPOINT_LIST_FORMAT, CHECKPOINT_LOG_FORMAT,
+};
+use super::{
+    squash_commit_message, Attempt, CheckpointCommit, Checkpoint, CompletionOption, DiffStat,
+    ExistingRun, PreflightIssue, RevertSummary, RunLock, RunState, StoryCheckpoint,
+};
+use crate::async_cmd;
+use crate::error::{Error, Result};
+
+/// Checkpoint manager using branch + commit for state preservation.
+///
+/// Creates a `ralph/{change_name}` branch at loop start and uses commits
+/// as checkpoints. On failure, reverts only the agent's footprint since the
+/// last checkpoint. The branch is checked out in place, in the current
+/// directory unless a working directory is given.
+///
+/// Run state and attempt refs are shared by every worktree of the
+/// repository, so the query methods work regardless of the backend used.
+#[derive(Debug, Clone)]
+pub struct InPlaceCheckpoint {
+    /// The name of the change being worked on.
+    change_name: String,
+    /// Optional working directory for git commands (defaults to the current directory).
+    work_dir: Option<PathBuf>,
+    /// Timeout for git commands.
+    timeout: Duration,
+    /// The original branch name before switching to ralph branch.
+    original_branch: Option<String>,
+    /// Untracked files present when the current attempt started.
+    attempt_baseline: Option<HashSet<String>>,
+}
+
+impl InPlaceCheckpoint {
+    /// Creates a new InPlaceCheckpoint with the default timeout.
+    pub fn new(change_name: impl Into<String>) -> Self {
+        Self::with_timeout(change_name, async_cmd::DEFAULT_TIMEOUT)
+    }
+
+    /// Creates a new InPlaceCheckpoint with a custom timeout.
+    pub fn with_timeout(change_name: impl Into<String>, timeout: Duration) -> Self {
+        Self {
+            change_name: change_name.into(),
+            work_dir: None,
+            timeout,
+            original_branch: None,
+            attempt_baseline: None,
+        }
+    }
+
+    /// Creates a new InPlaceCheckpoint that runs git in `work_dir`.
+    pub fn in_dir(change_name: impl Into<String>, work_dir: PathBuf, timeout: Duration) -> Self {
+        Self {
+            change_name: change_name.into(),
+            work_dir: Some(work_dir),
+            timeout,
+            original_branch: None,
+            attempt_baseline: None,
+        }
+    }
+
+    /// Creates a new InPlaceCheckpoint with a specific working directory.
+    /// Used primarily for testing.
+    #[cfg(test)]
+    pub fn with_work_dir(change_name: impl Into<String>, work_dir: PathBuf) -> Self {
+        Self::in_dir(change_name, work_dir, async_cmd::DEFAULT_TIMEOUT)
+    }
+
+    /// Returns the name of the ralph branch for this change.
+    pub(super) fn branch_name(&self) -> String {
+        format!("ralph/{}", self.change_name)
+    }
+
+    /// Returns the ref prefix under which failed attempts are archived.
+    fn attempts_ref_prefix(&self) -> String {
+        format!("refs/ralph/{}/attempts/", self.change_name)
+    }
+
+    /// Returns the git config key used to persist run state for this change.
+    fn config_key(&self, name: &str) -> String {
+        format!("ralph.{}.{}", self.change_name, name)
+    }
+
+    /// Looks for a previous run of this change on its ralph branch.
+    ///
+    /// Returns `None` if the branch does not exist or holds no checkpoint
+    /// commits, since there is nothing worth resuming in that case.
+    pub async fn find_existing_run(&self) -> Result<Option<ExistingRun>> {
+        let branch_name = self.branch_name();
+        if !self.branch_exists().await? {
+            return Ok(None);
+        }
+
+        let output = self
+            .run_git(&["log", "--reverse", CHECKPOINT_LOG_FORMAT, &branch_name])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git log {}", branch_name),
+                stderr,
+            });
+        }
+        let checkpoint_story_ids = parse_checkpoint_story_ids(&String::from_utf8_lossy(&output.stdout));
+        if checkpoint_story_ids.is_empty() {
+            return Ok(None);
+        }
+
+        Ok(Some(ExistingRun {
+            branch: branch_name,
+            state: self.load_run_state().await?,
+            checkpoint_story_ids,
+        }))
+    }
+
+    /// Returns true if the ralph branch exists.
+    async fn branch_exists(&self) -> Result<bool> {
+        let branch_ref = format!("refs/heads/{}", self.branch_name());
+        let output = self
+            .run_git(&["rev-parse", "--verify", "--quiet", &branch_ref])
+            .await?;
+        Ok(output.status.success())
+    }
+
+    /// Deletes all archived attempts for this change.
+    pub(super) async fn delete_attempts(&self) -> Result<()> {
+        let prefix = self.attempts_ref_prefix();
+        let stdout = self
+            .git_stdout(&["for-each-ref", "--format=%(refname)", &prefix])
+            .await?;
+        for ref_name in stdout.lines().filter(|l| !l.is_empty()) {
+            self.git_stdout(&["update-ref", "-d", ref_name]).await?;
+        }
+        Ok(())
+    }
+
+    /// Returns the branch the run started from.
+    ///
+    /// The original branch comes from memory, falling back to the persisted
+    /// `RunState` so completion also works after a restart.
+    pub(super) async fn original_branch_name(&self) -> Result<String> {
+        match &self.original_branch {
+            Some(branch) => Ok(branch.clone()),
+            None => self
+                .load_run_state()
+                .await?
+                .map(|state| state.original_branch)
+                .ok_or_else(|| Error::Command {
+                    cmd: "cleanup".to_string(),
+                    stderr: "No original branch stored - was init() called?".to_string(),
+                }),
+        }
+    }
+
+    /// Performs cleanup: checkout original branch, merge --squash, delete branch.
+    ///
+    /// If the original branch advanced during the run, the ralph branch is
+    /// rebased onto it first. On conflict everything is aborted, the ralph
+    /// branch stays checked out and `Error::MergeConflict` lists the files.
+    ///
+    /// Without a commit message the squashed changes are left unstaged
+    /// (`reset HEAD`); with one they are committed as a single commit.
+    async fn do_cleanup(&self, commit_message: Option<&str>) -> Result<()> {
+        let original_branch = &self.original_branch_name().await?;
+
+        let branch_name = self.branch_name();
+
+        if self.has_advanced(original_branch).await? {
+            self.rebase_onto(original_branch).await?;
+        }
+
+        // Checkout original branch
+        let output = self.run_git(&["checkout", original_branch]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git checkout {}", original_branch),
+                stderr,
+            });
+        }
+
+        // Merge --squash to bring all changes as staged
+        let output = self.run_git(&["merge", "--squash", &branch_name]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            let files = self.unmerged_files().await?;
+            // Undo the half-done merge and go back to where the run left off
+            let _ = self.run_git(&["reset", "-q", "--merge"]).await;
+            let _ = self.run_git(&["checkout", "-q", &branch_name]).await;
+            if !files.is_empty() {
+                return Err(Error::MergeConflict {
+                    branch: original_branch.clone(),
+                    files,
+                });
+            }
+            return Err(Error::Command {
+                cmd: format!("git merge --squash {}", branch_name),
+                stderr,
+            });
+        }
+
+        match commit_message {
+            // Commit the squashed changes, unless the run changed nothing
+            Some(message) => {
+                if self.has_staged_changes().await? {
+                    self.git_stdout(&["commit", "-q", "-m", message]).await?;
+                }
+            }
+            // Reset HEAD to make changes unstaged
+            None => {
+                let output = self.run_git(&["reset", "HEAD"]).await?;
+                if !output.status.success() {
+                    let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                    return Err(Error::Command {
+                        cmd: "git reset HEAD".to_string(),
+                        stderr,
+                    });
+                }
+            }
+        }
+
+        // Delete the ralph branch
+        let output = self.run_git(&["branch", "-D", &branch_name]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git branch -D {}", branch_name),
+                stderr,
+            });
+        }
+
+        // The run is finished; nothing is left to resume
+        self.remove_run_state().await
+    }
+
+    /// Lands the checkpoint commits on the original branch, keeping history.
+    ///
+    /// Rebases the ralph branch onto the original branch (a no-op replay if
+    /// it has not moved), fast-forwards the original branch to it and
+    /// deletes the ralph branch.
+    async fn do_preserve_history(&self) -> Result<()> {
+        let original_branch = self.original_branch_name().await?;
+        let branch_name = self.branch_name();
+
+        self.rebase_onto(&original_branch).await?;
+        self.git_stdout(&["checkout", "-q", &original_branch]).await?;
+        self.git_stdout(&["merge", "--ff-only", &branch_name]).await?;
+        self.git_stdout(&["branch", "-D", &branch_name]).await?;
+
+        // The run is finished; nothing is left to resume
+        self.remove_run_state().await
+    }
+
+    /// Rebases the checked out ralph branch onto `upstream`.
+    ///
+    /// Empty commits such as "initial state" are dropped and uncommitted
+    /// changes are stashed around the rebase. A failed rebase is aborted so
+    /// the branch is left as it was; conflicts are reported as
+    /// `Error::MergeConflict`.
+    pub(super) async fn rebase_onto(&self, upstream: &str) -> Result<()> {
+        if let Err(e) = self
+            .git_stdout(&["rebase", "--no-keep-empty", "--force-rebase", "--autostash", upstream])
+            .await
+        {
+            let files = self.unmerged_files().await.unwrap_or_default();
+            let _ = self.run_git(&["rebase", "--abort"]).await;
+            if !files.is_empty() {
+                return Err(Error::MergeConflict {
+                    branch: upstream.to_string(),
+                    files,
+                });
+            }
+            return Err(e);
+        }
+        Ok(())
+    }
+
+    /// Returns true if `original_branch` has commits the ralph branch lacks,
+    /// i.e. it moved on since the run started.
+    pub(super) async fn has_advanced(&self, original_branch: &str) -> Result<bool> {
+        let branch_name = self.branch_name();
+        let output = self
+            .run_git(&["merge-base", "--is-ancestor", original_branch, &branch_name])
+            .await?;
+        match output.status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(Error::Command {
+                cmd: format!("git merge-base --is-ancestor {} {}", original_branch, branch_name),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            }),
+        }
+    }
+
+    /// Lists files with unresolved conflicts in the index.
+    pub(super) async fn unmerged_files(&self) -> Result<Vec<String>> {
+        let output = self
+            .git_stdout(&["diff", "--name-only", "--diff-filter=U", "-z"])
+            .await?;
+        Ok(split_nul(&output))
+    }
+
+    /// Builds the squash commit message from the run's checkpoint commits.
+    pub(super) async fn squash_message(&self, original_branch: &str) -> Result<String> {
+        let range = format!("{}..{}", original_branch, self.branch_name());
+        let log = self
+            .git_stdout(&["log", "--reverse", CHECKPOINT_LOG_FORMAT, &range])
+            .await?;
+        Ok(squash_commit_message(
+            &self.change_name,
+            &parse_checkpoint_story_ids(&log),
+        ))
+    }
+
+    /// Returns true if the index differs from HEAD.
+    pub(super) async fn has_staged_changes(&self) -> Result<bool> {
+        let output = self.run_git(&["diff", "--cached", "--quiet"]).await?;
+        match output.status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(Error::Command {
+                cmd: "git diff --cached --quiet".to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            }),
+        }
+    }
+
+    /// Reads the run state recorded by `init`, if any.
+    ///
+    /// Returns `None` when no run was started or the state is incomplete.
+    pub async fn load_run_state(&self) -> Result<Option<RunState>> {
+        let Some(original_branch) = self.get_config("originalBranch").await? else {
+            return Ok(None);
+        };
+        let Some(base_commit) = self.get_config("baseCommit").await? else {
+            return Ok(None);
+        };
+        let Some(started_at) = self
+            .get_config("startedAt")
+            .await?
+            .and_then(|v| v.parse().ok())
+        else {
+            return Ok(None);
+        };
+
+        Ok(Some(RunState {
+            original_branch,
+            base_commit,
+            started_at,
+        }))
+    }
+
+    /// Records the run state in git config.
+    pub(super) async fn save_run_state(&self, state: &RunState) -> Result<()> {
+        self.set_config("originalBranch", &state.original_branch).await?;
+        self.set_config("baseCommit", &state.base_commit).await?;
+        self.set_config("startedAt", &state.started_at.to_string()).await
+    }
+
+    /// Removes the persisted run state once the run is finished.
+    ///
+    /// Ignores failures since the section may not exist for runs started
+    /// before it was persisted.
+    pub(super) async fn remove_run_state(&self) -> Result<()> {
+        let section = format!("ralph.{}", self.change_name);
+        let _ = self.run_git(&["config", "--remove-section", &section]).await?;
+        Ok(())
+    }
+
+    /// Returns the name of the currently checked out branch.
+    pub(super) async fn current_branch(&self) -> Result<String> {
+        let stdout = self.git_stdout(&["rev-parse", "--abbrev-ref", "HEAD"]).await?;
+        Ok(stdout.trim().to_string())
+    }
+
+    /// Returns the commit hash HEAD points to.
+    pub(super) async fn head_commit(&self) -> Result<String> {
+        let output = self.run_git(&["rev-parse", "HEAD"]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: "git rev-parse HEAD".to_string(),
+                stderr,
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+    }
+
+    /// Reads a `ralph.<change>.<name>` config value.
+    async fn get_config(&self, name: &str) -> Result<Option<String>> {
+        let key = self.config_key(name);
+        let output = self.run_git(&["config", "--get", &key]).await?;
+        if !output.status.success() {
+            return Ok(None);
+        }
+        let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        Ok(Some(value).filter(|v| !v.is_empty()))
+    }
+
+    /// Writes a `ralph.<change>.<name>` config value.
+    async fn set_config(&self, name: &str, value: &str) -> Result<()> {
+        let key = self.config_key(name);
+        let output = self.run_git(&["config", &key, value]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git config {} {}", key, value),
+                stderr,
+            });
+        }
+        Ok(())
+    }
+
+    /// Treats every untracked file as created by the run, so `revert` removes
+    /// them even without a `begin_attempt` snapshot. Only for checkouts the
+    /// user does not work in, like the worktree backend's.
+    pub(super) fn owning_untracked_files(mut self) -> Self {
+        self.attempt_baseline = Some(HashSet::new());
+        self
+    }
+
+    /// Lists untracked, non-ignored files relative to the repository root.
+    pub(super) async fn untracked_files(&self) -> Result<Vec<String>> {
+        let stdout = self
+            .git_stdout(&["ls-files", "--others", "--exclude-standard", "--full-name", "-z", ":/"])
+            .await?;
+        Ok(split_nul(&stdout))
+    }
+
+    /// Returns the top-level directory of the repository.
+    pub(super) async fn top_level(&self) -> Result<PathBuf> {
+        let dir = self.git_stdout(&["rev-parse", "--show-toplevel"]).await?;
+        Ok(PathBuf::from(dir.trim()))
+    }
+
+    /// Returns the git common dir, shared by all worktrees, which holds the
+    /// run lock.
+    pub(super) async fn lock_dir(&self) -> Result<PathBuf> {
+        let dir = self
+            .git_stdout(&["rev-parse", "--path-format=absolute", "--git-common-dir"])
+            .await?;
+        Ok(PathBuf::from(dir.trim()))
+    }
+
+    /// Runs a git command and returns its stdout, failing on a non-zero exit.
+    pub(super) async fn git_stdout(&self, args: &[&str]) -> Result<String> {
+        let output = self.run_git(args).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git {}", args.join(" ")),
+                stderr,
+            });
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    /// Helper to run a git command asynchronously.
+    ///
+    /// Runs in `work_dir` when set, otherwise in the current directory.
+    pub(super) async fn run_git(&self, args: &[&str]) -> Result<std::process::Output> {
+        match &self.work_dir {
+            Some(work_dir) => {
+                async_cmd::run_unchecked_in_dir_with_timeout("git", args, work_dir, self.timeout).await
+            }
+            None => async_cmd::run_unchecked_with_timeout("git", args, self.timeout).await,
+        }
+    }
+}
+
+#[async_trait]
+impl Checkpoint for InPlaceCheckpoint {
+    fn work_dir(&self) -> Result<PathBuf> {
+        match &self.work_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(std::env::current_dir()?),
+        }
+    }
+
+    /// Locks the git common dir, so worktrees of the repository share the lock.
+    async fn lock(&self) -> Result<RunLock> {
+        RunLock::acquire(&self.lock_dir().await?, &self.change_name)
+    }
+
+    async fn preflight(&self, untracked_size_limit_mb: u64) -> Result<Vec<PreflightIssue>> {
+        self.check_repository(untracked_size_limit_mb).await
+    }
+
+    async fn result_dir(&self) -> Result<PathBuf> {
+        self.work_dir()
+    }
+
+    /// Lists archived attempts for this change, oldest first.
+    async fn list_attempts(&self) -> Result<Vec<Attempt>> {
+        let prefix = self.attempts_ref_prefix();
+        let stdout = self
+            .git_stdout(&[
+                "for-each-ref",
+                "--sort=committerdate",
+                "--format=%(refname)%00%(contents:body)%1e",
+                &prefix,
+            ])
+            .await?;
+
+        Ok(stdout
+            .split('\x1e')
+            .filter_map(|record| {
+                let (ref_name, body) = record.trim_start_matches('\n').split_once('\0')?;
+                let (story_id, attempt) = ref_name.strip_prefix(&prefix)?.rsplit_once('-')?;
+                Some(Attempt {
+                    ref_name: ref_name.to_string(),
+                    story_id: story_id.to_string(),
+                    attempt: attempt.parse().ok()?,
+                    reason: body.trim().to_string(),
+                })
+            })
+            .collect())
+    }
+
+    /// Lists the checkpoint commits of the current run with their diffstats,
+    /// oldest first.
+    ///
+    /// Returns an empty list if the ralph branch does not exist.
+    async fn list_checkpoints(&self) -> Result<Vec<StoryCheckpoint>> {
+        let branch_name = self.branch_name();
+        if !self.branch_exists().await? {
+            return Ok(Vec::new());
+        }
+
+        let range = match self.load_run_state().await? {
+            Some(state) => format!("{}..{}", state.base_commit, branch_name),
+            None => branch_name,
+        };
+        let log = self
+            .git_stdout(&["log", "--reverse", CHECKPOINT_LIST_FORMAT, &range])
+            .await?;
+
+        let mut checkpoints = Vec::new();
+        for line in log.lines() {
+            let mut fields = line.splitn(3, '\x1f');
+            let (Some(commit), Some(subject)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let Some(story_id) = checkpoint_story_id(subject, fields.next().unwrap_or("")) else {
+                continue;
+            };
+            let stat = self
+                .git_stdout(&["show", "--shortstat", "--format=", commit])
+                .await?;
+            let (files_changed, insertions, deletions) = parse_shortstat(&stat);
+            checkpoints.push(StoryCheckpoint {
+                commit: commit.to_string(),
+                story_id,
+                subject: subject.to_string(),
+                files_changed,
+                insertions,
+                deletions,
+            });
+        }
+        Ok(checkpoints)
+    }
+
+    async fn attempt_diff(&self, ref_name: &str) -> Result<String> {
+        let parent = format!("{}^", ref_name);
+        self.git_stdout(&["diff", &parent, ref_name]).await
+    }
+
+    /// Resets the ralph branch to one of its checkpoint commits, dropping
+    /// every later commit.
+    ///
+    /// Only commits listed by `list_checkpoints` are accepted, so the branch
+    /// never moves below the run's base commit. If the branch is checked out
+    /// (here or in a worktree), that checkout is hard-reset so its files
+    /// match the checkpoint.
+    async fn rollback_to(&self, commit: &str) -> Result<()> {
+        let branch_name = self.branch_name();
+        let object = format!("{}^{{commit}}", commit);
+        let output = self.run_git(&["rev-parse", "--verify", "--quiet", &object]).await?;
+        let resolved = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        let checkpoints = self.list_checkpoints().await?;
+        if !output.status.success() || !checkpoints.iter().any(|c| c.commit == resolved) {
+            return Err(Error::Command {
+                cmd: format!("rollback to {}", commit),
+                stderr: format!("{} is not a checkpoint on {}", commit, branch_name),
+            });
+        }
+
+        let worktrees = self.git_stdout(&["worktree", "list", "--porcelain"]).await?;
+        match checkout_of_branch(&worktrees, &branch_name) {
+            Some(path) => {
+                Self::in_dir(self.change_name.clone(), path, self.timeout)
+                    .git_stdout(&["reset", "--hard", "-q", commit])
+                    .await?;
+            }
+            None => {
+                self.git_stdout(&["branch", "-f", &branch_name, commit]).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Initializes the checkpoint system by creating a ralph branch.
+    ///
+    /// Stores the current branch name (in memory, and in git config together
+    /// with the base commit as `RunState`), creates/switches to
+    /// `ralph/{change_name}`, and creates an "initial state" commit with
+    /// `--allow-empty`.
+    async fn init(&mut self) -> Result<()> {
+        // Get current branch name
+        let current_branch = self.current_branch().await?;
+        let branch_name = self.branch_name();
+
+        // A fresh run starts without attempts archived by earlier runs
+        self.delete_attempts().await?;
+
+        // Restarting from the ralph branch itself keeps the recorded run state
+        let state = match self.load_run_state().await? {
+            Some(persisted) if current_branch == branch_name => persisted,
+            _ => RunState::starting_now(current_branch, self.head_commit().await?),
+        };
+        self.save_run_state(&state).await?;
+        self.original_branch = Some(state.original_branch);
+
+        // Create/switch to ralph branch
+        let output = self.run_git(&["checkout", "-B", &branch_name]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git checkout -B {}", branch_name),
+                stderr,
+            });
+        }
+
+        // Stage all changes and create initial commit
+        let output = self.run_git(&["add", "-A"]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: "git add -A".to_string(),
+                stderr,
+            });
+        }
+
+        let output = self
+            .run_git(&["commit", "--allow-empty", "-m", "initial state"])
+            .await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: "git commit --allow-empty -m 'initial state'".to_string(),
+                stderr,
+            });
+        }
+
+        // The initial commit holds every untracked file, so anything
+        // untracked from here on was created by the run
+        self.attempt_baseline = Some(HashSet::new());
+
+        Ok(())
+    }
+
+    /// Resumes a previous run on the existing ralph branch.
+    ///
+    /// Restores the original branch from persisted state and switches to the
+    /// ralph branch without resetting it, so earlier checkpoint commits are
+    /// kept. If the ralph branch is already checked out, tracked files changed
+    /// by the interrupted attempt are restored; untracked files are kept, as
+    /// they may be the user's.
+    async fn resume(&mut self) -> Result<()> {
+        let branch_name = self.branch_name();
+        let state = self.load_run_state().await?.ok_or_else(|| Error::Command {
+            cmd: "resume".to_string(),
+            stderr: format!("No run state recorded for {}", branch_name),
+        })?;
+
+        let current_branch = self.current_branch().await?;
+
+        if current_branch == branch_name {
+            // The interrupted attempt never reached a checkpoint
+            self.revert().await?;
+        } else {
+            let output = self.run_git(&["checkout", &branch_name]).await?;
+            if !output.status.success() {
+                let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+                return Err(Error::Command {
+                    cmd: format!("git checkout {}", branch_name),
+                    stderr,
+                });
+            }
+        }
+
+        self.original_branch = Some(state.original_branch);
+        Ok(())
+    }
+
+    /// Creates a checkpoint commit after a story completes successfully.
+    ///
+    /// Stages all changes except the excluded paths and commits them with
+    /// the message built by `CheckpointCommit::message`, as its author if
+    /// one is set. Excluded paths stay in the working tree, unstaged.
+    async fn commit_checkpoint(&self, commit: &CheckpointCommit) -> Result<()> {
+        // Stage all changes
+        let output = self.run_git(&["add", "-A"]).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: "git add -A".to_string(),
+                stderr,
+            });
+        }
+        if !commit.excluded_paths.is_empty() {
+            let pathspecs: Vec<String> = commit.excluded_paths.iter().map(|p| format!(":(top){}", p)).collect();
+            let mut args = vec!["reset", "-q", "--"];
+            args.extend(pathspecs.iter().map(String::as_str));
+            self.git_stdout(&args).await?;
+        }
+
+        // Create checkpoint commit
+        let message = commit.message(&self.change_name);
+        let author = commit.author.as_ref().map(|author| format!("--author={}", author));
+        let mut args = vec!["commit", "--allow-empty", "-m", &message];
+        if let Some(author) = &author {
+            args.push(author);
+        }
+        let output = self.run_git(&args).await?;
+        if !output.status.success() {
+            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
+            return Err(Error::Command {
+                cmd: format!("git commit (story {})", commit.story_id),
+                stderr,
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records the untracked files present before an agent attempt starts.
+    ///
+    /// `revert` never removes files from this snapshot, so files that existed
+    /// before the attempt (e.g. created by the user) survive a failed attempt.
+    async fn begin_attempt(&mut self) -> Result<()> {
+        let untracked = self.untracked_files().await?;
+        self.attempt_baseline = Some(untracked.into_iter().collect());
+        Ok(())
+    }
+
+    /// Stages everything to list the changed files, then resets the index
+    /// to HEAD again.
+    async fn pending_files(&self) -> Result<Vec<String>> {
+        self.git_stdout(&["add", "-A"]).await?;
+        let staged = self
+            .git_stdout(&["diff", "--cached", "--name-only", "-z"])
+            .await;
+        // Leave the index as the agent left it relative to HEAD
+        self.git_stdout(&["reset", "-q", "HEAD"]).await?;
+        Ok(split_nul(&staged?))
+    }
+
+    async fn top_level_dir(&self) -> Result<PathBuf> {
+        self.top_level().await
+    }
+
+    /// Stages everything to measure the changes, then resets the index to
+    /// HEAD again.
+    async fn pending_diffstat(&self) -> Result<DiffStat> {
+        self.git_stdout(&["add", "-A"]).await?;
+        let stat = self.git_stdout(&["diff", "--cached", "--shortstat"]).await;
+        // Leave the index as the agent left it relative to HEAD
+        self.git_stdout(&["reset", "-q", "HEAD"]).await?;
+        let (files_changed, insertions, deletions) = parse_shortstat(&stat?);
+        Ok(DiffStat {
+            files_changed,
+            insertions,
+            deletions,
+        })
+    }
+
+    /// Archives the working tree of a failed attempt before it is reverted.
+    ///
+    /// Commits everything (including untracked files) on top of the last
+    /// checkpoint without moving HEAD, and points
+    /// `refs/ralph/<change>/attempts/<story>-<n>` at it. The failure reason is
+    /// kept in the commit message. Returns the ref name.
+    async fn archive_attempt(&self, story_id: &str, attempt: usize, reason: &str) -> Result<String> {
+        let ref_name = format!(
+            "{}{}-{}",
+            self.attempts_ref_prefix(),
+            sanitize_ref_component(story_id),
+            attempt
+        );
+        let message = format!("attempt {} for story {}\n\n{}", attempt, story_id, reason);
+
+        self.git_stdout(&["add", "-A"]).await?;
+        let tree = self.git_stdout(&["write-tree"]).await?;
+        // Leave the index as the agent left it relative to HEAD
+        self.git_stdout(&["reset", "-q", "HEAD"]).await?;
+
+        let commit = self
+            .git_stdout(&["commit-tree", tree.trim(), "-p", "HEAD", "-m", &message])
+            .await?;
+        self.git_stdout(&["update-ref", &ref_name, commit.trim()]).await?;
+
+        Ok(ref_name)
+    }
+
+    /// Reverts the agent's footprint since the last checkpoint.
+    ///
+    /// Tracked files that differ from the checkpoint commit are restored, and
+    /// untracked files created since the attempt started are removed. Unlike
+    /// `git reset --hard` plus `git clean -fd`, untracked files that predate
+    /// the attempt and ignored files are left alone. Without a snapshot from
+    /// `init` or `begin_attempt` (e.g. when `resume` discards an interrupted
+    /// attempt), no untracked file is removed, since the agent's files cannot
+    /// be told apart from the user's.
+    async fn revert(&self) -> Result<RevertSummary> {
+        // Unstage everything so the index matches the checkpoint commit;
+        // files the agent staged as new become untracked
+        self.git_stdout(&["reset", "-q", "HEAD"]).await?;
+
+        // Restore tracked files modified or deleted since the checkpoint
+        let restored = split_nul(&self.git_stdout(&["diff", "--name-only", "--no-renames", "-z"]).await?);
+        if !restored.is_empty() {
+            let pathspecs: Vec<String> = restored.iter().map(|p| format!(":(top){}", p)).collect();
+            let mut args = vec!["checkout", "--"];
+            args.extend(pathspecs.iter().map(String::as_str));
+            self.git_stdout(&args).await?;
+        }
+
+        // Remove untracked files the attempt created
+        let removed: Vec<String> = self
+            .untracked_files()
+            .await?
+            .into_iter()
+            .filter(|path| {
+                self.attempt_baseline
+                    .as_ref()
+                    .is_some_and(|baseline| !baseline.contains(path))
+            })
+            .collect();
+        if !removed.is_empty() {
+            let toplevel = self.top_level().await?;
+            for path in &removed {
+                remove_file_and_empty_parents(&toplevel, path)?;
+            }
+        }
+
+        Ok(RevertSummary { restored, removed })
+    }
+
+    /// Handles completion based on the user's choice.
+    ///
+    /// - `Cleanup`: Returns to original branch with uncommitted changes
+    /// - `Keep`: Stays on ralph branch with checkpoint commits
+    /// - `Squash`: Returns to original branch with one commit of all changes
+    /// - `PreserveHistory`: Rebases and fast-forwards the original branch
+    /// - `ExportPatch`: Stays on ralph branch (the series is written separately)
+    async fn cleanup(&self, option: CompletionOption) -> Result<()> {
+        match option {
+            CompletionOption::Cleanup => self.do_cleanup(None).await,
+            CompletionOption::Squash => {
+                let message = self.squash_message(&self.original_branch_name().await?).await?;
+                self.do_cleanup(Some(&message)).await
+            }
+            CompletionOption::PreserveHistory => self.do_preserve_history().await,
+            // No-op for keep; export_patches writes the series
+            CompletionOption::Keep | CompletionOption::ExportPatch => Ok(()),
+        }
+    }
+
+    /// Exports `<original branch>..ralph/<change>` with `git format-patch`.
+    ///
+    /// Patches of empty commits (an initial state without uncommitted changes)
+    /// are dropped, since `git am` refuses to apply them.
+    async fn export_patches(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let original_branch = self.original_branch_name().await?;
+        let range = format!("{}..{}", original_branch, self.branch_name());
+        // Relative directories are relative to where ralphtool was started
+        let dir = std::path::absolute(dir)?;
+        let dir = dir.to_string_lossy();
+        let stdout = self
+            .git_stdout(&["format-patch", "-o", &dir, &range])
+            .await?;
+
+        let mut patches = Vec::new();
+        for patch in stdout.lines().filter(|l| !l.is_empty()).map(PathBuf::from) {
+            if std::fs::read_to_string(&patch)?.contains("\ndiff --git ") {
+                patches.push(patch);
+            } else {
+                std::fs::remove_file(&patch)?;
+            }
+        }
+        Ok(patches)
+    }
+}
+
+/// Replaces characters that are awkward in ref names with `_`.
+pub(super) fn sanitize_ref_component(value: &str) -> String {
+    value
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '_' { c } else { '_' })
+        .collect()
+}
+
+/// Splits NUL-separated git output into paths.
+fn split_nul(output: &str) -> Vec<String> {
+    output
+        .split('\0')
+        .filter(|p| !p.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `git show --shortstat` output into (files, insertions, deletions).
+fn parse_shortstat(output: &str) -> (usize, usize, usize) {
+    let mut stat = (0, 0, 0);
+    for part in output.trim().split(", ") {
+        let Some((count, label)) = part.split_once(' ') else {
+            continue;
+        };
+        let Ok(count) = count.parse() else {
+            continue;
+        };
+        if label.starts_with("file") {
+            stat.0 = count;
+        } else if label.starts_with("insertion") {
+            stat.1 = count;
+        } else if label.starts_with("deletion") {
+            stat.2 = count;
+        }
+    }
+    stat
+}
+
+/// Returns the checkout path of `branch` from `git worktree list --porcelain`
+/// output, if it is checked out anywhere.
+fn checkout_of_branch(worktrees: &str, branch: &str) -> Option<PathBuf> {
+    let branch_ref = format!("branch refs/heads/{}", branch);
+    worktrees.split("\n\n").find_map(|block| {
+        let mut lines = block.lines();
+        let path = lines.next()?.strip_prefix("worktree ")?;
+        lines
+            .any(|line| line == branch_ref)
+            .then(|| PathBuf::from(path))
+    })
+}
+
+/// Removes a file and any parent directories left empty, up to `root`.
+pub(super) fn remove_file_and_empty_parents(root: &Path, relative: &str) -> Result<()> {
+    let path = root.join(relative);
+    match std::fs::remove_file(&path) {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+
+    let mut dir = path.parent();
+    while let Some(current) = dir {
+        if current == root || std::fs::remove_dir(current).is_err() {
+            break;
+        }
+        dir = current.parent();
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::test_support::setup_temp_repo_with_checkpoint;
+    use crate::checkpoint::CommitFormat;
+    use std::fs;
+    use std::process::Command;
+
+    #[test]
+    fn checkpoint_with_timeout_sets_change_name() {
+        let checkpoint = InPlaceCheckpoint::with_timeout("test-change", async_cmd::DEFAULT_TIMEOUT);
+        assert_eq!(checkpoint.change_name, "test-change");
+    }
+
+    #[test]
+    fn branch_name_format() {
+        let checkpoint = InPlaceCheckpoint::with_timeout("my-feature", async_cmd::DEFAULT_TIMEOUT);
+        assert_eq!(checkpoint.branch_name(), "ralph/my-feature");
+    }
+
+    /// Gets the repo path from a checkpoint for file operations.
+    /// Returns a cloned PathBuf to avoid borrowing issues.
+    fn repo_path(checkpoint: &InPlaceCheckpoint) -> PathBuf {
+        checkpoint.work_dir.as_ref().expect("Checkpoint should have work_dir for tests").clone()
+    }
+
+    /// Helper to get current branch name
+    fn get_current_branch(repo_path: impl AsRef<std::path::Path>) -> String {
+        let output = Command::new("git")
+            .args(["rev-parse", "--abbrev-ref", "HEAD"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to get current branch");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    /// Helper to get commit count
+    fn get_commit_count(repo_path: impl AsRef<std::path::Path>) -> usize {
+        let output = Command::new("git")
+            .args(["rev-list", "--count", "HEAD"])
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to count commits");
+        String::from_utf8_lossy(&output.stdout)
+            .trim()
+            .parse()
+            .unwrap_or(0)
+    }
+
+    // ==================== init() tests ====================
+
+    #[tokio::test]
+    async fn init_stores_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        // Get original branch name before init
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Verify original branch was stored internally
+        assert_eq!(
+            checkpoint.original_branch.as_deref(),
+            Some(original_branch.as_str())
+        );
+    }
+
+    #[tokio::test]
+    async fn init_persists_run_state() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+        let output = Command::new("git")
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to get HEAD");
+        let base_commit = String::from_utf8_lossy(&output.stdout).trim().to_string();
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // A fresh instance (e.g. after a restart) reads the same state
+        let reloaded = InPlaceCheckpoint::with_work_dir("my-change", path);
+        let state = reloaded
+            .load_run_state()
+            .await
+            .expect("config lookup should succeed")
+            .expect("run state should be persisted");
+        assert_eq!(state.original_branch, original_branch);
+        assert_eq!(state.base_commit, base_commit);
+        assert!(state.started_at > 0);
+    }
+
+    #[tokio::test]
+    async fn cleanup_after_restart_uses_persisted_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        let restarted = InPlaceCheckpoint::with_work_dir("my-change", path.clone());
+        restarted
+            .cleanup(CompletionOption::Cleanup)
+            .await
+            .expect("cleanup should succeed");
+
+        assert_eq!(get_current_branch(&path), original_branch);
+    }
+
+    #[tokio::test]
+    async fn init_creates_ralph_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Verify we're on the ralph branch
+        let current_branch = get_current_branch(&path);
+        assert_eq!(current_branch, "ralph/my-change");
+    }
+
+    #[tokio::test]
+    async fn init_creates_initial_commit() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        let commits_before = get_commit_count(&path);
+        checkpoint.init().await.expect("init should succeed");
+        let commits_after = get_commit_count(&path);
+
+        // Should have created one new commit
+        assert_eq!(commits_after, commits_before + 1);
+
+        // Verify commit message
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%s"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to get commit message");
+        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert_eq!(message, "initial state");
+    }
+
+    #[tokio::test]
+    async fn init_includes_uncommitted_changes() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        // Create uncommitted changes before init
+        let new_file = path.join("uncommitted.txt");
+        fs::write(&new_file, "uncommitted content").expect("Failed to write file");
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Verify file is committed in initial state
+        let output = Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to get git status");
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert!(status.is_empty(), "Working directory should be clean after init");
+    }
+
+    #[tokio::test]
+    async fn init_force_creates_branch_if_exists() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        // First init
+        checkpoint.init().await.expect("first init should succeed");
+
+        // Go back to original branch manually
+        Command::new("git")
+            .args(["checkout", "-"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to checkout");
+
+        // Modify the checkpoint
+        checkpoint.original_branch = None;
+
+        // Second init should work (force recreates branch)
+        checkpoint.init().await.expect("second init should succeed");
+
+        let current_branch = get_current_branch(&path);
+        assert_eq!(current_branch, "ralph/my-change");
+    }
+
+    // ==================== commit_checkpoint() tests ====================
+
+    #[tokio::test]
+    async fn commit_checkpoint_creates_commit_with_story_id() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Make changes
+        let test_file = path.join("feature.txt");
+        fs::write(&test_file, "feature code").expect("Failed to write file");
+
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit_checkpoint should succeed");
+
+        // Verify commit message
+        let output = Command::new("git")
+            .args(["log", "-1", "--format=%s"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to get commit message");
+        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert_eq!(message, "checkpoint: story-1");
+    }
+
+    #[tokio::test]
+    async fn commit_checkpoint_includes_all_changes() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Create tracked and untracked files
+        let tracked_file = path.join("tracked.txt");
+        fs::write(&tracked_file, "tracked content").expect("Failed to write file");
+        let untracked_file = path.join("untracked.txt");
+        fs::write(&untracked_file, "untracked content").expect("Failed to write file");
+
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit_checkpoint should succeed");
+
+        // Verify working directory is clean
+        let output = Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(path)
+            .output()
+            .expect("Failed to get git status");
+        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert!(status.is_empty(), "Working directory should be clean after checkpoint");
+    }
+
+    // ==================== revert() tests ====================
+
+    #[tokio::test]
+    async fn revert_discards_uncommitted_changes() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Make changes after init
+        let agent_file = path.join("agent_created.txt");
+        fs::write(&agent_file, "agent output").expect("Failed to write file");
+
+        // Modify existing file
+        let initial_file = path.join("initial.txt");
+        fs::write(&initial_file, "modified content").expect("Failed to write file");
+
+        checkpoint.revert().await.expect("revert should succeed");
+
+        // Verify agent file is gone
+        assert!(!agent_file.exists(), "Agent-created file should be removed");
+
+        // Verify existing file is restored
+        let content = fs::read_to_string(&initial_file).expect("Failed to read file");
+        assert_eq!(content, "initial content");
+    }
+
+    #[tokio::test]
+    async fn revert_preserves_committed_changes() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Story 1 completes successfully
+        let story1_file = path.join("story1.txt");
+        fs::write(&story1_file, "story 1 code").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        // Story 2 starts and fails
+        let story2_file = path.join("story2.txt");
+        fs::write(&story2_file, "story 2 failed attempt").expect("Failed to write file");
+
+        checkpoint.revert().await.expect("revert should succeed");
+
+        // Story 1 file should still exist (committed)
+        assert!(story1_file.exists(), "Story 1 file should remain after revert");
+
+        // Story 2 file should be gone (uncommitted)
+        assert!(!story2_file.exists(), "Story 2 file should be removed after revert");
+    }
+
+    #[tokio::test]
+    async fn revert_spares_untracked_files_from_before_the_attempt() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // User file created before the attempt starts
+        let user_file = path.join("notes.txt");
+        fs::write(&user_file, "my notes").expect("Failed to write file");
+
+        checkpoint.begin_attempt().await.expect("snapshot should succeed");
+
+        let agent_file = path.join("agent_created.txt");
+        fs::write(&agent_file, "agent output").expect("Failed to write file");
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert!(user_file.exists(), "Pre-existing untracked file should be kept");
+        assert!(!agent_file.exists(), "Agent-created file should be removed");
+        assert_eq!(summary.removed, vec!["agent_created.txt"]);
+    }
+
+    #[tokio::test]
+    async fn revert_spares_ignored_files() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        fs::write(path.join(".gitignore"), "target/\n").expect("Failed to write file");
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.begin_attempt().await.expect("snapshot should succeed");
+
+        fs::create_dir_all(path.join("target")).expect("Failed to create dir");
+        let build_output = path.join("target/build.log");
+        fs::write(&build_output, "build").expect("Failed to write file");
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert!(build_output.exists(), "Ignored file should be kept");
+        assert!(summary.removed.is_empty());
+    }
+
+    #[tokio::test]
+    async fn revert_reports_restored_and_removed_paths() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.begin_attempt().await.expect("snapshot should succeed");
+
+        // Modify, delete, stage a new file, and create a nested directory
+        fs::write(path.join("initial.txt"), "changed").expect("Failed to write file");
+        fs::create_dir_all(path.join("src/new")).expect("Failed to create dir");
+        fs::write(path.join("src/new/mod.rs"), "mod").expect("Failed to write file");
+        fs::write(path.join("staged.txt"), "staged").expect("Failed to write file");
+        Command::new("git")
+            .args(["add", "staged.txt"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to git add");
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert_eq!(summary.restored, vec!["initial.txt"]);
+        let mut removed = summary.removed.clone();
+        removed.sort();
+        assert_eq!(removed, vec!["src/new/mod.rs", "staged.txt"]);
+
+        let content = fs::read_to_string(path.join("initial.txt")).expect("Failed to read file");
+        assert_eq!(content, "initial content");
+        assert!(!path.join("src").exists(), "Empty directories should be pruned");
+        assert!(!path.join("staged.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn revert_restores_deleted_tracked_files() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.begin_attempt().await.expect("snapshot should succeed");
+
+        let initial_file = path.join("initial.txt");
+        fs::remove_file(&initial_file).expect("Failed to delete file");
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert!(initial_file.exists(), "Deleted tracked file should be restored");
+        assert_eq!(summary.restored, vec!["initial.txt"]);
+    }
+
+    // ==================== attempt archive tests ====================
+
+    #[test]
+    fn sanitize_ref_component_replaces_unsafe_characters() {
+        assert_eq!(sanitize_ref_component("1"), "1");
+        assert_eq!(sanitize_ref_component("story 2/x-y"), "story_2_x_y");
+    }
+
+    #[tokio::test]
+    async fn archive_attempt_keeps_failed_work_in_a_ref() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        let commits_before = get_commit_count(&path);
+
+        fs::write(path.join("attempt.txt"), "failed work").expect("Failed to write file");
+        fs::write(path.join("initial.txt"), "changed").expect("Failed to write file");
+
+        let ref_name = checkpoint
+            .archive_attempt("1", 2, "tests failed")
+            .await
+            .expect("archive should succeed");
+        assert_eq!(ref_name, "refs/ralph/my-change/attempts/1-2");
+
+        // HEAD did not move and the work is still there to be reverted
+        assert_eq!(get_commit_count(&path), commits_before);
+        assert!(path.join("attempt.txt").exists());
+
+        checkpoint.revert().await.expect("revert should succeed");
+        assert!(!path.join("attempt.txt").exists());
+
+        let attempts = checkpoint.list_attempts().await.expect("list should succeed");
+        assert_eq!(attempts.len(), 1);
+        assert_eq!(attempts[0].ref_name, ref_name);
+        assert_eq!(attempts[0].story_id, "1");
+        assert_eq!(attempts[0].attempt, 2);
+        assert_eq!(attempts[0].reason, "tests failed");
+
+        let diff = checkpoint.attempt_diff(&ref_name).await.expect("diff should succeed");
+        assert!(diff.contains("attempt.txt"));
+        assert!(diff.contains("+failed work"));
+        assert!(diff.contains("+changed"));
+    }
+
+    #[tokio::test]
+    async fn fresh_init_clears_archived_attempts() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint
+            .archive_attempt("1", 1, "failed")
+            .await
+            .expect("archive should succeed");
+
+        Command::new("git")
+            .args(["checkout", &original_branch])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to checkout");
+        checkpoint.init().await.expect("second init should succeed");
+
+        let attempts = checkpoint.list_attempts().await.expect("list should succeed");
+        assert!(attempts.is_empty());
+    }
+
+    // ==================== resume tests ====================
+
+    #[tokio::test]
+    async fn find_existing_run_returns_none_without_branch() {
+        let (_temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+
+        let existing = checkpoint.find_existing_run().await.expect("lookup should succeed");
+        assert!(existing.is_none());
+    }
+
+    #[tokio::test]
+    async fn find_existing_run_returns_none_without_checkpoints() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+
+        checkpoint.init().await.expect("init should succeed");
+
+        let existing = checkpoint.find_existing_run().await.expect("lookup should succeed");
+        assert!(existing.is_none());
+    }
+
+    #[tokio::test]
+    async fn find_existing_run_lists_checkpoints_and_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story2.txt"), "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+
+        let existing = checkpoint
+            .find_existing_run()
+            .await
+            .expect("lookup should succeed")
+            .expect("run should be found");
+        assert_eq!(existing.branch, "ralph/my-change");
+        let state = existing.state.expect("run state should be persisted");
+        assert_eq!(state.original_branch, original_branch);
+        assert_eq!(existing.checkpoint_story_ids, vec!["1", "2"]);
+    }
+
+    #[tokio::test]
+    async fn conventional_checkpoints_are_found_by_trailer() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        let commit = CheckpointCommit {
+            story_title: "Add parser".to_string(),
+            format: CommitFormat::Conventional,
+            author: Some("Ralph Bot <ralph@example.com>".to_string()),
+            ..CheckpointCommit::new("1")
+        };
+        checkpoint.commit_checkpoint(&commit).await.expect("commit should succeed");
+
+        let log = git_output(&path, &["log", "-1", "--format=%s|%an <%ae>|%cn"]);
+        assert_eq!(log, "feat(my-change): Add parser|Ralph Bot <ralph@example.com>|Test User");
+        let existing = checkpoint.find_existing_run().await.unwrap().unwrap();
+        assert_eq!(existing.checkpoint_story_ids, vec!["1"]);
+    }
+
+    #[tokio::test]
+    async fn pending_files_lists_changes_without_staging() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("new.txt"), "new").expect("Failed to write file");
+        fs::write(path.join("README.md"), "changed").expect("Failed to write file");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("new.txt"), "changed").expect("Failed to write file");
+        fs::write(path.join("other.txt"), "other").expect("Failed to write file");
+        fs::remove_file(path.join("story1.txt")).expect("Failed to remove file");
+
+        let mut pending = checkpoint.pending_files().await.unwrap();
+        pending.sort();
+
+        assert_eq!(pending, vec!["new.txt", "other.txt", "story1.txt"]);
+        assert_eq!(git_output(&path, &["diff", "--cached", "--name-only"]), "");
+    }
+
+    #[tokio::test]
+    async fn pending_files_are_relative_to_the_top_level_from_a_subdirectory() {
+        let (temp_dir, _) = setup_temp_repo_with_checkpoint("my-change");
+        let repo = temp_dir.path();
+        let subdir = repo.join("app");
+        fs::create_dir(&subdir).expect("Failed to create subdir");
+        let mut checkpoint = InPlaceCheckpoint::with_work_dir("my-change", subdir.clone());
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(subdir.join("main.rs"), "fn main() {}").expect("Failed to write file");
+        fs::write(repo.join(".env"), "SECRET=1").expect("Failed to write file");
+
+        let mut pending = checkpoint.pending_files().await.unwrap();
+        pending.sort();
+        assert_eq!(pending, vec![".env", "app/main.rs"]);
+        assert_eq!(
+            checkpoint.top_level_dir().await.unwrap().canonicalize().unwrap(),
+            repo.canonicalize().unwrap()
+        );
+
+        let commit = CheckpointCommit {
+            excluded_paths: vec![".env".to_string()],
+            ..CheckpointCommit::new("1")
+        };
+        checkpoint.commit_checkpoint(&commit).await.expect("commit should succeed");
+        assert_eq!(git_output(repo, &["show", "--name-only", "--format=", "HEAD"]), "app/main.rs");
+    }
+
+    #[tokio::test]
+    async fn pending_diffstat_measures_changes_without_staging() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("initial.txt"), "changed\nand more\n").expect("Failed to write file");
+        fs::write(path.join("new.txt"), "one\ntwo\nthree\n").expect("Failed to write file");
+
+        let stat = checkpoint.pending_diffstat().await.unwrap();
+
+        assert_eq!(stat.files_changed, 2);
+        assert_eq!(stat.insertions, 5);
+        assert_eq!(stat.deletions, 1);
+        assert_eq!(git_output(&path, &["diff", "--cached", "--name-only"]), "");
+    }
+
+    #[tokio::test]
+    async fn commit_checkpoint_leaves_excluded_paths_unstaged() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        fs::write(path.join(".env"), "SECRET=1").expect("Failed to write file");
+        let commit = CheckpointCommit {
+            excluded_paths: vec![".env".to_string()],
+            ..CheckpointCommit::new("1")
+        };
+        checkpoint.commit_checkpoint(&commit).await.expect("commit should succeed");
+
+        let committed = git_output(&path, &["show", "--name-only", "--format=", "HEAD"]);
+        assert_eq!(committed, "story1.txt");
+        assert_eq!(git_output(&path, &["status", "--porcelain"]), "?? .env");
+    }
+
+    #[tokio::test]
+    async fn list_checkpoints_reports_diffstats_per_story() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        assert!(checkpoint.list_checkpoints().await.unwrap().is_empty());
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "one\ntwo\n").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story1.txt"), "one\n").expect("Failed to write file");
+        fs::write(path.join("story2.txt"), "three\n").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+
+        let checkpoints = checkpoint.list_checkpoints().await.unwrap();
+        let stats: Vec<_> = checkpoints
+            .iter()
+            .map(|c| (c.story_id.as_str(), c.files_changed, c.insertions, c.deletions))
+            .collect();
+        assert_eq!(stats, vec![("1", 1, 2, 0), ("2", 2, 1, 1)]);
+        assert_eq!(checkpoints[1].subject, "checkpoint: 2");
+    }
+
+    #[tokio::test]
+    async fn rollback_to_resets_checked_out_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story2.txt"), "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+
+        let checkpoints = checkpoint.list_checkpoints().await.unwrap();
+        checkpoint.rollback_to(&checkpoints[0].commit).await.expect("rollback should succeed");
+
+        assert_eq!(git_output(&path, &["rev-parse", "HEAD"]), checkpoints[0].commit);
+        assert!(path.join("story1.txt").exists());
+        assert!(!path.join("story2.txt").exists());
+        let existing = checkpoint.find_existing_run().await.unwrap().unwrap();
+        assert_eq!(existing.checkpoint_story_ids, vec!["1"]);
+    }
+
+    #[tokio::test]
+    async fn rollback_to_moves_branch_that_is_not_checked_out() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story2.txt"), "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+        git_output(&path, &["checkout", "-q", &original_branch]);
+
+        let checkpoints = checkpoint.list_checkpoints().await.unwrap();
+        checkpoint.rollback_to(&checkpoints[0].commit).await.expect("rollback should succeed");
+
+        assert_eq!(get_current_branch(&path), original_branch);
+        assert_eq!(
+            git_output(&path, &["rev-parse", "ralph/my-change"]),
+            checkpoints[0].commit
+        );
+    }
+
+    #[tokio::test]
+    async fn rollback_to_rejects_commit_outside_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        let head = git_output(&path, &["rev-parse", "HEAD"]);
+        git_output(&path, &["checkout", "-q", "-b", "other", "HEAD~1"]);
+        fs::write(path.join("other.txt"), "other").expect("Failed to write file");
+        git_output(&path, &["add", "other.txt"]);
+        git_output(&path, &["commit", "-q", "-m", "other"]);
+        let other = git_output(&path, &["rev-parse", "HEAD"]);
+
+        assert!(checkpoint.rollback_to(&other).await.is_err());
+        assert_eq!(git_output(&path, &["rev-parse", "ralph/my-change"]), head);
+    }
+
+    #[tokio::test]
+    async fn rollback_to_rejects_commit_below_the_run_base() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let base = git_output(&path, &["rev-parse", "HEAD"]);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        let head = git_output(&path, &["rev-parse", "HEAD"]);
+
+        // The base commit is an ancestor of the branch but not a checkpoint
+        assert!(checkpoint.rollback_to(&base).await.is_err());
+        assert_eq!(git_output(&path, &["rev-parse", "ralph/my-change"]), head);
+    }
+
+    #[test]
+    fn parse_shortstat_reads_all_counts() {
+        assert_eq!(
+            parse_shortstat(" 3 files changed, 10 insertions(+), 2 deletions(-)\n"),
+            (3, 10, 2)
+        );
+        assert_eq!(parse_shortstat(" 1 file changed, 1 deletion(-)\n"), (1, 0, 1));
+        assert_eq!(parse_shortstat(""), (0, 0, 0));
+    }
+
+    #[test]
+    fn checkout_of_branch_finds_worktree_path() {
+        let worktrees = "worktree /repo\nHEAD abc\nbranch refs/heads/main\n\n\
+                         worktree /tmp/wt\nHEAD def\nbranch refs/heads/ralph/x\n";
+        assert_eq!(
+            checkout_of_branch(worktrees, "ralph/x"),
+            Some(PathBuf::from("/tmp/wt"))
+        );
+        assert_eq!(checkout_of_branch(worktrees, "ralph/y"), None);
+    }
+
+    #[tokio::test]
+    async fn resume_keeps_checkpoints_and_restores_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        let story1_file = path.join("story1.txt");
+        fs::write(&story1_file, "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        let commits_before = get_commit_count(&path);
+
+        // Simulate a crash: go back to the original branch, lose in-memory state
+        Command::new("git")
+            .args(["checkout", &original_branch])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to checkout");
+        let mut resumed = InPlaceCheckpoint::with_work_dir("my-change", path.clone());
+
+        resumed.resume().await.expect("resume should succeed");
+
+        assert_eq!(get_current_branch(&path), "ralph/my-change");
+        assert_eq!(get_commit_count(&path), commits_before);
+        assert!(story1_file.exists(), "Checkpointed work should be kept");
+        assert_eq!(resumed.original_branch, Some(original_branch));
+    }
+
+    #[tokio::test]
+    async fn resume_on_ralph_branch_discards_interrupted_attempt() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        let initial_file = path.join("initial.txt");
+        fs::write(&initial_file, "half done").expect("Failed to write file");
+
+        let mut resumed = InPlaceCheckpoint::with_work_dir("my-change", path.clone());
+        resumed.resume().await.expect("resume should succeed");
+
+        assert_eq!(get_current_branch(&path), "ralph/my-change");
+        assert_eq!(
+            fs::read_to_string(&initial_file).unwrap(),
+            "initial content",
+            "Interrupted attempt should be discarded"
+        );
+    }
+
+    #[tokio::test]
+    async fn resume_on_ralph_branch_keeps_untracked_user_files() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        let user_file = path.join("notes.txt");
+        fs::write(&user_file, "my notes").expect("Failed to write file");
+
+        let mut resumed = InPlaceCheckpoint::with_work_dir("my-change", path.clone());
+        resumed.resume().await.expect("resume should succeed");
+
+        assert!(user_file.exists(), "Untracked user file should survive resume");
+    }
+
+    #[tokio::test]
+    async fn init_from_ralph_branch_keeps_persisted_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("first init should succeed");
+
+        // Starting over while still on the ralph branch
+        let mut restarted = InPlaceCheckpoint::with_work_dir("my-change", path.clone());
+        restarted.init().await.expect("second init should succeed");
+
+        assert_eq!(restarted.original_branch, Some(original_branch));
+    }
+
+    #[tokio::test]
+    async fn resume_fails_without_persisted_state() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+
+        assert!(checkpoint.resume().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cleanup_removes_persisted_state() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        checkpoint
+            .cleanup(CompletionOption::Cleanup)
+            .await
+            .expect("cleanup should succeed");
+
+        let persisted = checkpoint
+            .load_run_state()
+            .await
+            .expect("config lookup should succeed");
+        assert!(persisted.is_none());
+    }
+
+    // ==================== cleanup() tests ====================
+
+    #[tokio::test]
+    async fn cleanup_with_cleanup_option_returns_to_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        let original_branch = get_current_branch(&path);
+        checkpoint.init().await.expect("init should succeed");
+
+        // Make and commit changes
+        let test_file = path.join("feature.txt");
+        fs::write(&test_file, "feature code").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
+
+        // Verify we're back on original branch
+        let current_branch = get_current_branch(&path);
+        assert_eq!(current_branch, original_branch);
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_cleanup_option_brings_uncommitted_changes() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Make and commit changes
+        let test_file = path.join("feature.txt");
+        fs::write(&test_file, "feature code").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
+
+        // Verify changes are present but uncommitted
+        assert!(test_file.exists(), "Feature file should exist");
+
+        let output = Command::new("git")
+            .args(["status", "--porcelain"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to get git status");
+        let status = String::from_utf8_lossy(&output.stdout);
+        assert!(!status.is_empty(), "Should have uncommitted changes after cleanup");
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_cleanup_option_deletes_ralph_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
+
+        // Verify ralph branch is deleted
+        let output = Command::new("git")
+            .args(["branch", "--list", "ralph/my-change"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to list branches");
+        let branches = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        assert!(branches.is_empty(), "Ralph branch should be deleted after cleanup");
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_keep_option_stays_on_ralph_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
+
+        // Verify we're still on ralph branch
+        let current_branch = get_current_branch(&path);
+        assert_eq!(current_branch, "ralph/my-change");
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_keep_option_preserves_commits() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Make multiple commits
+        let file1 = path.join("story1.txt");
+        fs::write(&file1, "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        let file2 = path.join("story2.txt");
+        fs::write(&file2, "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-2")).await.expect("commit should succeed");
+
+        let commits_before = get_commit_count(&path);
+        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
+        let commits_after = get_commit_count(&path);
+
+        // Commits should be preserved
+        assert_eq!(commits_after, commits_before);
+    }
+
+    /// Helper to run git in the repo and return trimmed stdout
+    fn git_output(repo_path: impl AsRef<std::path::Path>, args: &[&str]) -> String {
+        let output = Command::new("git")
+            .args(args)
+            .current_dir(repo_path)
+            .output()
+            .expect("Failed to run git");
+        String::from_utf8_lossy(&output.stdout).trim().to_string()
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_squash_option_creates_single_commit() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+        let commits_before = get_commit_count(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story2.txt"), "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+
+        checkpoint.cleanup(CompletionOption::Squash).await.expect("squash should succeed");
+
+        assert_eq!(get_current_branch(&path), original_branch);
+        assert_eq!(get_commit_count(&path), commits_before + 1);
+        assert_eq!(git_output(&path, &["status", "--porcelain"]), "");
+        let message = git_output(&path, &["log", "-1", "--format=%B"]);
+        assert!(message.starts_with("Implement my-change"));
+        assert!(message.contains("- Story 1\n- Story 2"));
+        assert!(git_output(&path, &["branch", "--list", "ralph/my-change"]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleanup_with_preserve_history_option_lands_checkpoint_commits() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        fs::write(path.join("story2.txt"), "story 2").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.expect("commit should succeed");
+
+        checkpoint
+            .cleanup(CompletionOption::PreserveHistory)
+            .await
+            .expect("preserve history should succeed");
+
+        assert_eq!(get_current_branch(&path), original_branch);
+        let log = git_output(&path, &["log", "--format=%s"]);
+        assert_eq!(log, "checkpoint: 2\ncheckpoint: 1\nInitial commit");
+        assert!(git_output(&path, &["branch", "--list", "ralph/my-change"]).is_empty());
+        assert!(checkpoint.load_run_state().await.unwrap().is_none());
+    }
+
+    /// Commits `content` to `file` on `branch`, then checks `back_to` out again.
+    fn commit_on_branch(repo_path: &std::path::Path, branch: &str, back_to: &str, file: &str, content: &str) {
+        git_output(repo_path, &["checkout", "-q", branch]);
+        fs::write(repo_path.join(file), content).expect("Failed to write file");
+        git_output(repo_path, &["add", "."]);
+        git_output(repo_path, &["commit", "-q", "-m", "Upstream work"]);
+        git_output(repo_path, &["checkout", "-q", back_to]);
+    }
+
+    #[tokio::test]
+    async fn cleanup_rebases_onto_advanced_original_branch() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("feature.txt"), "feature code").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        commit_on_branch(&path, &original_branch, "ralph/my-change", "upstream.txt", "upstream");
+
+        checkpoint.cleanup(CompletionOption::Squash).await.expect("squash should succeed");
+
+        assert_eq!(get_current_branch(&path), original_branch);
+        let log = git_output(&path, &["log", "--format=%s"]);
+        assert_eq!(log, "Implement my-change\nUpstream work\nInitial commit");
+        assert!(path.join("upstream.txt").exists());
+        assert!(path.join("feature.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn cleanup_conflict_aborts_and_lists_files() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+        let original_branch = get_current_branch(&path);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("initial.txt"), "ralph version").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+        commit_on_branch(&path, &original_branch, "ralph/my-change", "initial.txt", "upstream version");
+        let ralph_head = git_output(&path, &["rev-parse", "HEAD"]);
+
+        let err = checkpoint.cleanup(CompletionOption::Cleanup).await.unwrap_err();
+
+        match err {
+            Error::MergeConflict { branch, files } => {
+                assert_eq!(branch, original_branch);
+                assert_eq!(files, vec!["initial.txt"]);
+            }
+            other => panic!("expected a merge conflict, got {other}"),
+        }
+        // Back where the run left off, ready for keep or retry
+        assert_eq!(get_current_branch(&path), "ralph/my-change");
+        assert_eq!(git_output(&path, &["rev-parse", "HEAD"]), ralph_head);
+        assert_eq!(git_output(&path, &["status", "--porcelain"]), "");
+        assert!(checkpoint.load_run_state().await.unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn export_patches_writes_one_patch_per_checkpoint() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
+        let path = repo_path(&checkpoint);
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(path.join("story1.txt"), "story 1").expect("Failed to write file");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.expect("commit should succeed");
+
+        let patch_dir = temp_dir.path().join("patches");
+        let patches = checkpoint.export_patches(&patch_dir).await.expect("export should succeed");
+
+        // The empty initial state commit is not exported
+        assert_eq!(patches.len(), 1);
+        assert_eq!(fs::read_dir(&patch_dir).unwrap().count(), 1);
+        assert!(patches[0].starts_with(&patch_dir));
+        let patch = fs::read_to_string(&patches[0]).unwrap();
+        assert!(patch.contains("checkpoint: 1"));
+        assert!(patch.contains("story1.txt"));
+
+        // Exporting leaves the branch in place, like keep
+        checkpoint.cleanup(CompletionOption::ExportPatch).await.expect("cleanup should succeed");
+        assert_eq!(get_current_branch(&path), "ralph/my-change");
+    }
+
+    // ==================== Integration tests ====================
+
+    #[tokio::test]
+    async fn integration_full_checkpoint_cycle_with_retries() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("test-feature");
+        let path = repo_path(&checkpoint).clone();
+
+        // Initialize checkpoint system
+        checkpoint.init().await.expect("init should succeed");
+
+        // Story 1: Agent fails twice, then succeeds
+        let story1_file = path.join("story1.txt");
+
+        // Attempt 1: fails
+        fs::write(&story1_file, "attempt 1 - wrong approach").expect("write failed");
+        checkpoint.revert().await.expect("revert 1 should succeed");
+        assert!(!story1_file.exists(), "Failed attempt should be reverted");
+
+        // Attempt 2: fails
+        fs::write(&story1_file, "attempt 2 - still wrong").expect("write failed");
+        checkpoint.revert().await.expect("revert 2 should succeed");
+        assert!(!story1_file.exists(), "Failed attempt should be reverted");
+
+        // Attempt 3: succeeds
+        fs::write(&story1_file, "attempt 3 - success!").expect("write failed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        // Story 2: Succeeds on first try
+        let story2_file = path.join("story2.txt");
+        fs::write(&story2_file, "story 2 success").expect("write failed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-2")).await.expect("commit should succeed");
+
+        // Cleanup with cleanup option
+        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
+
+        // Verify both story files exist as uncommitted changes
+        assert!(story1_file.exists(), "Story 1 file should exist");
+        assert!(story2_file.exists(), "Story 2 file should exist");
+
+        // Verify we're on original branch
+        let branch = get_current_branch(&path);
+        assert_ne!(branch, "ralph/test-feature", "Should be back on original branch");
+    }
+
+    #[tokio::test]
+    async fn integration_max_retries_with_partial_work() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("partial-work");
+        let path = repo_path(&checkpoint).clone();
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Story 1 completes
+        let story1_file = path.join("story1.txt");
+        fs::write(&story1_file, "story 1 complete").expect("write failed");
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("story-1")).await.expect("commit should succeed");
+
+        // Story 2 fails repeatedly (max retries exceeded)
+        for i in 1..=3 {
+            let story2_file = path.join("story2.txt");
+            fs::write(&story2_file, format!("attempt {}", i)).expect("write failed");
+            checkpoint.revert().await.expect("revert should succeed");
+        }
+
+        // User chooses to cleanup - partial work (story 1) should be preserved
+        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
+
+        // Story 1 work should exist as uncommitted changes
+        assert!(story1_file.exists(), "Story 1 work should be preserved");
+        let content = fs::read_to_string(&story1_file).expect("read failed");
+        assert_eq!(content, "story 1 complete");
+    }
+
+    #[tokio::test]
+    async fn integration_keep_option_preserves_history() {
+        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("keep-history");
+        let path = repo_path(&checkpoint).clone();
+
+        checkpoint.init().await.expect("init should succeed");
+
+        // Complete multiple stories
+        for i in 1..=3 {
+            let file = path.join(format!("story{}.txt", i));
+            fs::write(&file, format!("story {} content", i)).expect("write failed");
+            checkpoint.commit_checkpoint(&CheckpointCommit::new(format!("story-{}", i))).await.expect("commit should succeed");
+        }
+
+        // User chooses to keep
+        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
+
+        // Verify commit history is intact
+        let output = Command::new("git")
+            .args(["log", "--oneline"])
+            .current_dir(&path)
+            .output()
+            .expect("Failed to get log");
+        let log = String::from_utf8_lossy(&output.stdout);
+
+        assert!(log.contains("checkpoint: story-1"), "Story 1 commit should exist");
+        assert!(log.contains("checkpoint: story-2"), "Story 2 commit should exist");
+        assert!(log.contains("checkpoint: story-3"), "Story 3 commit should exist");
+        assert!(log.contains("initial state"), "Initial commit should exist");
+    }
+}
@@ -0,0 +1,242 @@
+//! Single-instance lock per repository.
+//!
+//! Two instances in the same repository would fight over the `ralph/<change>`
+//! branches, and the second one's `git checkout -B` corrupts the first run.
+//! While the loop runs, an advisory lock file in the git common dir (shared
+//! by all worktrees) records the PID, change name and start time of the
+//! instance holding it. A lock whose PID is no longer alive is stale and is
+//! taken over.
+
+use std::fs;
+use std::io::ErrorKind;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{Error, Result};
+
+/// Name of the lock file inside the locked directory.
+pub const LOCK_FILE: &str = "ralphtool.lock";
+
+/// Contents of a lock file: who holds the lock.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockInfo {
+    /// Process ID of the instance holding the lock.
+    pub pid: u32,
+    /// Change the instance is running.
+    pub change_name: String,
+    /// Unix timestamp (seconds) when the lock was taken.
+    pub started_at: u64,
+}
+
+impl LockInfo {
+    /// Parses `key=value` lines; returns None if a field is missing.
+    fn parse(contents: &str) -> Option<Self> {
+        let field = |key: &str| {
+            contents
+                .lines()
+                .find_map(|line| line.strip_prefix(key)?.strip_prefix('='))
+                .map(str::trim)
+        };
+        Some(Self {
+            pid: field("pid")?.parse().ok()?,
+            change_name: field("change")?.to_string(),
+            started_at: field("started_at")?.parse().ok()?,
+        })
+    }
+
+    /// Formats the lock file contents.
+    fn render(&self) -> String {
+        format!(
+            "pid={}\nchange={}\nstarted_at={}\n",
+            self.pid, self.change_name, self.started_at
+        )
+    }
+
+    /// Returns true if the holding process is still alive.
+    fn is_alive(&self) -> bool {
+        self.pid == std::process::id() || pid_alive(self.pid)
+    }
+}
+
+/// An acquired lock; the lock file is removed when it is dropped.
+#[derive(Debug)]
+pub struct RunLock {
+    /// Path of the lock file.
+    path: PathBuf,
+}
+
+impl RunLock {
+    /// Takes the lock in `dir` for a run of `change_name`.
+    ///
+    /// Fails with `Error::AlreadyRunning` if a live instance holds it. A
+    /// stale lock (dead PID or unreadable contents) is replaced.
+    pub fn acquire(dir: &Path, change_name: &str) -> Result<Self> {
+        let path = dir.join(LOCK_FILE);
+        let info = LockInfo {
+            pid: std::process::id(),
+            change_name: change_name.to_string(),
+            started_at: SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        };
+
+        // Write the contents to a private file first and link it into
+        // place, so the lock file never exists half-written
+        let staging = dir.join(format!("{}.{}", LOCK_FILE, info.pid));
+        fs::write(&staging, info.render())?;
+        let linked = Self::link(&staging, &path, dir);
+        let _ = fs::remove_file(&staging);
+        if linked? {
+            return Ok(Self { path });
+        }
+
+        Err(Self::holder(dir)
+            .map(|holder| Error::AlreadyRunning {
+                change: holder.change_name,
+                pid: holder.pid,
+            })
+            .unwrap_or_else(|| Error::Command {
+                cmd: "lock".to_string(),
+                stderr: format!("Could not take the lock at {}", path.display()),
+            }))
+    }
+
+    /// Links `staging` to the lock file at `path`, replacing a stale lock.
+    ///
+    /// Returns false if the lock could not be taken. A second try follows
+    /// removing a stale lock; losing that race to another instance counts
+    /// as not taken.
+    fn link(staging: &Path, path: &Path, dir: &Path) -> Result<bool> {
+        for _ in 0..2 {
+            match fs::hard_link(staging, path) {
+                Ok(()) => return Ok(true),
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if Self::holder(dir).is_some() {
+                        return Ok(false);
+                    }
+                    match fs::remove_file(path) {
+                        Err(e) if e.kind() != ErrorKind::NotFound => return Err(e.into()),
+                        _ => {}
+                    }
+                }
+                Err(e) => return Err(e.into()),
+            }
+        }
+        Ok(false)
+    }
+
+    /// Returns the live instance holding the lock in `dir`, if any.
+    ///
+    /// Stale and unreadable lock files count as not held.
+    pub fn holder(dir: &Path) -> Option<LockInfo> {
+        let contents = fs::read_to_string(dir.join(LOCK_FILE)).ok()?;
+        LockInfo::parse(&contents).filter(LockInfo::is_alive)
+    }
+}
+
+impl Drop for RunLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+/// Returns true if a process with `pid` exists.
+///
+/// Uses `kill -0`, which checks for the process without signalling it.
+fn pid_alive(pid: u32) -> bool {
+    Command::new("kill")
+        .args(["-0", &pid.to_string()])
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .status()
+        .map(|status| status.success())
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    /// A PID that is not running: beyond the default Linux and macOS maximum.
+    const DEAD_PID: u32 = 99_999_999;
+
+    #[test]
+    fn lock_info_round_trips() {
+        let info = LockInfo {
+            pid: 42,
+            change_name: "add-auth".to_string(),
+            started_at: 1_700_000_000,
+        };
+        assert_eq!(LockInfo::parse(&info.render()), Some(info));
+        assert_eq!(LockInfo::parse("pid=42\n"), None);
+    }
+
+    #[test]
+    fn acquire_writes_and_drop_removes_the_lock() {
+        let dir = TempDir::new().unwrap();
+
+        let lock = RunLock::acquire(dir.path(), "add-auth").unwrap();
+        let holder = RunLock::holder(dir.path()).expect("lock is held");
+        assert_eq!(holder.pid, std::process::id());
+        assert_eq!(holder.change_name, "add-auth");
+
+        drop(lock);
+        assert!(!dir.path().join(LOCK_FILE).exists());
+    }
+
+    #[test]
+    fn live_lock_names_the_running_change() {
+        let dir = TempDir::new().unwrap();
+        // The parent process (cargo's test harness) is alive
+        let info = LockInfo {
+            pid: std::os::unix::process::parent_id(),
+            change_name: "other-change".to_string(),
+            started_at: 0,
+        };
+        fs::write(dir.path().join(LOCK_FILE), info.render()).unwrap();
+
+        match RunLock::acquire(dir.path(), "add-auth") {
+            Err(Error::AlreadyRunning { change, pid }) => {
+                assert_eq!(change, "other-change");
+                assert_eq!(pid, info.pid);
+            }
+            other => panic!("expected AlreadyRunning, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn lock_file_is_complete_when_it_appears() {
+        let dir = TempDir::new().unwrap();
+
+        let _lock = RunLock::acquire(dir.path(), "add-auth").unwrap();
+
+        // Only the linked lock file is left behind
+        let names: Vec<_> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(names, vec![LOCK_FILE]);
+        let contents = fs::read_to_string(dir.path().join(LOCK_FILE)).unwrap();
+        assert!(LockInfo::parse(&contents).is_some());
+    }
+
+    #[test]
+    fn stale_lock_is_taken_over() {
+        let dir = TempDir::new().unwrap();
+        let info = LockInfo {
+            pid: DEAD_PID,
+            change_name: "crashed".to_string(),
+            started_at: 0,
+        };
+        fs::write(dir.path().join(LOCK_FILE), info.render()).unwrap();
+        assert_eq!(RunLock::holder(dir.path()), None);
+
+        let _lock = RunLock::acquire(dir.path(), "add-auth").unwrap();
+
+        let holder = RunLock::holder(dir.path()).expect("lock is held");
+        assert_eq!(holder.change_name, "add-auth");
+    }
+}
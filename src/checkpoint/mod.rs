@@ -7,289 +7,335 @@
 //! instead of git stash. This avoids the "save and clean" behavior of stash
 //! that was causing issues with completed story changes being lost.
 //!
+//! The `Checkpoint` trait has three backends:
+//! - `InPlaceCheckpoint` switches the current checkout to the ralph branch
+//! - `WorktreeCheckpoint` checks the ralph branch out in a separate git
+//!   worktree, leaving the user's checkout alone while the loop runs
+//! - `SnapshotCheckpoint` copies the project tree into snapshot directories,
+//!   for projects that are not git repositories
+//!
+//! Before a run starts, `Checkpoint::preflight` reports repository states
+//! the backend cannot handle safely (see `preflight`). While it runs,
+//! a `RunLock` keeps other instances out of the repository (see `lock`).
+//!
 //! All operations are async-safe, using `async_cmd` to avoid blocking tokio
 //! worker threads.
 
-use std::path::PathBuf;
-use std::process::Command;
-use std::time::Duration;
+mod commit;
+mod in_place;
+mod lock;
+mod preflight;
+mod snapshot;
+#[cfg(test)]
+mod test_support;
+mod worktree;
+
+pub use commit::{parse_author, CheckpointCommit, CommitFormat};
+pub use in_place::InPlaceCheckpoint;
+pub use lock::RunLock;
+pub use preflight::{PreflightIssue, Severity, DEFAULT_UNTRACKED_SIZE_LIMIT_MB};
+#[cfg(test)]
+pub use preflight::PreflightCheck;
+pub use snapshot::{default_snapshot_dir, SnapshotCheckpoint};
+pub use worktree::WorktreeCheckpoint;
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use async_trait::async_trait;
+
+use crate::error::Result;
+
+/// Subject prefix of the commit created after each completed story.
+const CHECKPOINT_PREFIX: &str = "checkpoint: ";
+
+/// Where the ralph branch is checked out while the loop runs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum CheckpointBackend {
+    /// Switch the current checkout to the ralph branch.
+    #[default]
+    InPlace,
+    /// Check the ralph branch out in a separate git worktree.
+    Worktree,
+    /// Copy the project tree into snapshot directories; needs no git.
+    Snapshot,
+}
 
-use crate::async_cmd;
-use crate::error::{Error, Result};
+impl CheckpointBackend {
+    /// Returns the backend to use in `dir`: `Snapshot` if `dir` is not
+    /// inside a git repository, otherwise the backend itself.
+    pub fn resolve(self, dir: &Path) -> Self {
+        if dir.ancestors().any(|d| d.join(".git").exists()) {
+            self
+        } else {
+            CheckpointBackend::Snapshot
+        }
+    }
+
+    /// Returns the completion options the backend can carry out, in the
+    /// order they are offered. Without git there are no commits to squash
+    /// or land.
+    pub fn completion_options(self) -> &'static [CompletionOption] {
+        match self {
+            CheckpointBackend::InPlace | CheckpointBackend::Worktree => &CompletionOption::ALL,
+            CheckpointBackend::Snapshot => &[
+                CompletionOption::Cleanup,
+                CompletionOption::Keep,
+                CompletionOption::ExportPatch,
+            ],
+        }
+    }
+}
 
 /// Option for handling completion when the loop finishes.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
 pub enum CompletionOption {
-    /// Cleanup: return to original branch with uncommitted changes.
+    /// Cleanup: bring the changes onto the original branch as uncommitted changes.
     Cleanup,
-    /// Keep: stay on the ralph branch with checkpoint commits.
+    /// Keep: leave the ralph branch (and its checkout) with checkpoint commits.
     Keep,
+    /// Squash: commit all changes onto the original branch as a single commit.
+    Squash,
+    /// Preserve history: rebase the checkpoint commits onto the original
+    /// branch and fast-forward it.
+    PreserveHistory,
+    /// Export patch: write the checkpoint commits as a `git format-patch`
+    /// series and keep the ralph branch.
+    ExportPatch,
 }
 
-/// Checkpoint manager using branch + commit for state preservation.
-///
-/// Creates a `ralph/{change_name}` branch at loop start and uses commits
-/// as checkpoints. On failure, uses `git reset --hard HEAD` to restore.
-#[derive(Debug, Clone)]
-pub struct Checkpoint {
-    /// The name of the change being worked on.
-    change_name: String,
-    /// Optional working directory for git commands (for testing).
-    work_dir: Option<PathBuf>,
-    /// Timeout for git commands.
-    timeout: Duration,
-    /// The original branch name before switching to ralph branch.
-    original_branch: Option<String>,
+impl CompletionOption {
+    /// All options, in the order they are offered on the completion screen.
+    pub const ALL: [CompletionOption; 5] = [
+        CompletionOption::Cleanup,
+        CompletionOption::Keep,
+        CompletionOption::Squash,
+        CompletionOption::PreserveHistory,
+        CompletionOption::ExportPatch,
+    ];
 }
 
-impl Checkpoint {
-    /// Creates a new Checkpoint with the default timeout.
-    #[allow(dead_code)] // May be used externally or in future integrations
-    pub fn new(change_name: impl Into<String>) -> Self {
-        Self::with_timeout(change_name, async_cmd::DEFAULT_TIMEOUT)
-    }
+/// Durable metadata about a run, stored in git config under `ralph.<change>.*`.
+///
+/// Written by `init` so the original branch survives restarts and can be
+/// read by both the checkpoint layer and the TUI.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RunState {
+    /// Branch that was checked out when the run started.
+    pub original_branch: String,
+    /// Commit the original branch pointed to when the run started.
+    pub base_commit: String,
+    /// Unix timestamp (seconds) when the run started.
+    pub started_at: u64,
+}
 
-    /// Creates a new Checkpoint with a custom timeout.
-    pub fn with_timeout(change_name: impl Into<String>, timeout: Duration) -> Self {
+impl RunState {
+    /// Creates the state for a run starting now.
+    fn starting_now(original_branch: String, base_commit: String) -> Self {
+        let started_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
         Self {
-            change_name: change_name.into(),
-            work_dir: None,
-            timeout,
-            original_branch: None,
+            original_branch,
+            base_commit,
+            started_at,
         }
     }
+}
 
-    /// Creates a new Checkpoint with a specific working directory.
-    /// Used primarily for testing.
-    #[cfg(test)]
-    pub fn with_work_dir(change_name: impl Into<String>, work_dir: PathBuf) -> Self {
-        Self {
-            change_name: change_name.into(),
-            work_dir: Some(work_dir),
-            timeout: async_cmd::DEFAULT_TIMEOUT,
-            original_branch: None,
-        }
-    }
+/// Paths touched by `Checkpoint::revert`, relative to the repository root.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevertSummary {
+    /// Tracked files restored to their checkpoint content.
+    pub restored: Vec<String>,
+    /// Untracked files created by the attempt and removed.
+    pub removed: Vec<String>,
+}
 
-    /// Returns the name of the ralph branch for this change.
-    fn branch_name(&self) -> String {
-        format!("ralph/{}", self.change_name)
-    }
+/// Size of the changes since the last checkpoint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffStat {
+    /// Number of files added, modified or deleted.
+    pub files_changed: usize,
+    /// Lines added.
+    pub insertions: usize,
+    /// Lines removed.
+    pub deletions: usize,
+}
 
-    /// Initializes the checkpoint system by creating a ralph branch.
-    ///
-    /// Stores the current branch name, creates/switches to `ralph/{change_name}`,
-    /// and creates an "initial state" commit with `--allow-empty`.
-    pub async fn init(&mut self) -> Result<()> {
-        // Get current branch name
-        let output = self.run_git(&["rev-parse", "--abbrev-ref", "HEAD"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git rev-parse --abbrev-ref HEAD".to_string(),
-                stderr,
-            });
-        }
-        let current_branch = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        self.original_branch = Some(current_branch);
-
-        // Create/switch to ralph branch
-        let branch_name = self.branch_name();
-        let output = self.run_git(&["checkout", "-B", &branch_name]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: format!("git checkout -B {}", branch_name),
-                stderr,
-            });
-        }
+/// A failed attempt archived under `refs/ralph/<change>/attempts/`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Attempt {
+    /// Full ref name holding the archived attempt.
+    pub ref_name: String,
+    /// ID of the story the attempt was for.
+    pub story_id: String,
+    /// Attempt number for the story (1-indexed).
+    pub attempt: usize,
+    /// Why the attempt failed.
+    pub reason: String,
+}
 
-        // Stage all changes and create initial commit
-        let output = self.run_git(&["add", "-A"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git add -A".to_string(),
-                stderr,
-            });
-        }
+/// A checkpoint commit on the ralph branch, as listed on the result screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoryCheckpoint {
+    /// Full hash of the checkpoint commit.
+    pub commit: String,
+    /// ID of the story the checkpoint completed.
+    pub story_id: String,
+    /// Subject line of the commit.
+    pub subject: String,
+    /// Number of files the checkpoint changed.
+    pub files_changed: usize,
+    /// Lines added by the checkpoint.
+    pub insertions: usize,
+    /// Lines removed by the checkpoint.
+    pub deletions: usize,
+}
 
-        let output = self
-            .run_git(&["commit", "--allow-empty", "-m", "initial state"])
-            .await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git commit --allow-empty -m 'initial state'".to_string(),
-                stderr,
-            });
-        }
+/// A previous run found on an existing `ralph/{change_name}` branch.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExistingRun {
+    /// Name of the ralph branch holding the run.
+    pub branch: String,
+    /// Run metadata persisted when the run was started, if any.
+    pub state: Option<RunState>,
+    /// Story IDs with checkpoint commits on the branch, oldest first.
+    pub checkpoint_story_ids: Vec<String>,
+}
 
-        Ok(())
-    }
 
-    /// Creates a checkpoint commit after a story completes successfully.
+/// Checkpoint manager for a run on the `ralph/{change_name}` branch.
+///
+/// Backends decide where the branch is checked out; the orchestrator runs the
+/// agent, verification commands and spec reads in `work_dir()`.
+#[async_trait]
+pub trait Checkpoint: Send + Sync {
+    /// Returns the directory holding the ralph branch checkout.
     ///
-    /// Stages all changes and creates a commit with message "checkpoint: {story_id}".
-    pub async fn commit_checkpoint(&self, story_id: &str) -> Result<()> {
-        // Stage all changes
-        let output = self.run_git(&["add", "-A"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git add -A".to_string(),
-                stderr,
-            });
-        }
+    /// Only meaningful after `init` or `resume` succeeded.
+    fn work_dir(&self) -> Result<PathBuf>;
 
-        // Create checkpoint commit
-        let message = format!("checkpoint: {}", story_id);
-        let output = self.run_git(&["commit", "--allow-empty", "-m", &message]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: format!("git commit -m '{}'", message),
-                stderr,
-            });
-        }
+    /// Takes the single-instance lock for the repository; the lock is held
+    /// until the returned guard is dropped.
+    ///
+    /// Fails with `Error::AlreadyRunning` while another instance runs.
+    async fn lock(&self) -> Result<RunLock>;
 
-        Ok(())
-    }
+    /// Reports states the backend cannot safely start a run in, before `init`.
+    ///
+    /// Untracked files larger than `untracked_size_limit_mb` MiB are reported
+    /// where `init` would commit them.
+    async fn preflight(&self, untracked_size_limit_mb: u64) -> Result<Vec<PreflightIssue>>;
 
-    /// Reverts to the last checkpoint by resetting to HEAD.
+    /// Returns the directory holding the run's work once the loop finished:
+    /// the kept checkout of the ralph branch, or the project the work landed in.
     ///
-    /// Uses `git reset --hard HEAD` to discard tracked changes and
-    /// `git clean -fd` to remove untracked files and directories.
-    pub async fn revert(&self) -> Result<()> {
-        // Reset tracked files
-        let output = self.run_git(&["reset", "--hard", "HEAD"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git reset --hard HEAD".to_string(),
-                stderr,
-            });
-        }
+    /// Unlike `work_dir`, this works without `init` or `resume`.
+    async fn result_dir(&self) -> Result<PathBuf>;
 
-        // Clean untracked files and directories
-        let output = self.run_git(&["clean", "-fd"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git clean -fd".to_string(),
-                stderr,
-            });
-        }
+    /// Lists the failed attempts archived during the run, oldest first.
+    async fn list_attempts(&self) -> Result<Vec<Attempt>>;
 
-        Ok(())
-    }
+    /// Lists the story checkpoints of the run with their diffstats, oldest first.
+    async fn list_checkpoints(&self) -> Result<Vec<StoryCheckpoint>>;
 
-    /// Handles completion based on the user's choice.
-    ///
-    /// - `Cleanup`: Returns to original branch with uncommitted changes
-    /// - `Keep`: Stays on ralph branch with checkpoint commits
-    pub async fn cleanup(&self, option: CompletionOption) -> Result<()> {
-        match option {
-            CompletionOption::Cleanup => self.do_cleanup().await,
-            CompletionOption::Keep => Ok(()), // No-op for keep
-        }
-    }
+    /// Returns the diff of an attempt listed by `list_attempts` against the
+    /// checkpoint it started from.
+    async fn attempt_diff(&self, ref_name: &str) -> Result<String>;
 
-    /// Performs cleanup: checkout original branch, merge --squash, reset HEAD, delete branch.
-    async fn do_cleanup(&self) -> Result<()> {
-        let original_branch = self.original_branch.as_ref().ok_or_else(|| Error::Command {
-            cmd: "cleanup".to_string(),
-            stderr: "No original branch stored - was init() called?".to_string(),
-        })?;
-
-        let branch_name = self.branch_name();
-
-        // Checkout original branch
-        let output = self.run_git(&["checkout", original_branch]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: format!("git checkout {}", original_branch),
-                stderr,
-            });
-        }
+    /// Moves the run back to a checkpoint listed by `list_checkpoints`,
+    /// dropping every later one, so a resumed run continues from there.
+    async fn rollback_to(&self, commit: &str) -> Result<()>;
 
-        // Merge --squash to bring all changes as staged
-        let output = self.run_git(&["merge", "--squash", &branch_name]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: format!("git merge --squash {}", branch_name),
-                stderr,
-            });
-        }
+    /// Starts a fresh run, (re)creating the ralph branch from the current branch.
+    async fn init(&mut self) -> Result<()>;
 
-        // Reset HEAD to make changes unstaged
-        let output = self.run_git(&["reset", "HEAD"]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: "git reset HEAD".to_string(),
-                stderr,
-            });
-        }
+    /// Resumes a previous run on the existing ralph branch, keeping its checkpoints.
+    async fn resume(&mut self) -> Result<()>;
 
-        // Delete the ralph branch
-        let output = self.run_git(&["branch", "-D", &branch_name]).await?;
-        if !output.status.success() {
-            let stderr = String::from_utf8_lossy(&output.stderr).to_string();
-            return Err(Error::Command {
-                cmd: format!("git branch -D {}", branch_name),
-                stderr,
-            });
-        }
+    /// Records the untracked files present before an agent attempt starts.
+    async fn begin_attempt(&mut self) -> Result<()>;
 
-        Ok(())
-    }
+    /// Lists the files a checkpoint commit would add, modify or delete,
+    /// relative to `top_level_dir()`, without committing anything.
+    async fn pending_files(&self) -> Result<Vec<String>>;
 
-    /// Creates a Command with the appropriate working directory set.
-    /// Used for sync fallback in tests with work_dir.
-    fn git_command(&self) -> Command {
-        let mut cmd = Command::new("git");
-        if let Some(ref work_dir) = self.work_dir {
-            cmd.current_dir(work_dir);
-        }
-        cmd
-    }
+    /// Returns the directory the paths of `pending_files` and excluded paths
+    /// are relative to. For git backends this is the repository's top level,
+    /// which lies above `work_dir()` when ralphtool runs in a subdirectory.
+    async fn top_level_dir(&self) -> Result<PathBuf>;
+
+    /// Measures the changes a checkpoint commit would record, without
+    /// committing anything.
+    async fn pending_diffstat(&self) -> Result<DiffStat>;
+
+    /// Creates a checkpoint commit after a story completes successfully.
+    async fn commit_checkpoint(&self, commit: &CheckpointCommit) -> Result<()>;
 
-    /// Helper to run a git command asynchronously.
+    /// Archives the working tree of a failed attempt and returns the ref name.
+    async fn archive_attempt(&self, story_id: &str, attempt: usize, reason: &str) -> Result<String>;
+
+    /// Reverts the agent's footprint since the last checkpoint.
+    async fn revert(&self) -> Result<RevertSummary>;
+
+    /// Handles completion based on the user's choice.
     ///
-    /// For testing (when work_dir is set), falls back to sync execution.
-    /// In production (work_dir is None), uses async_cmd for non-blocking execution.
-    async fn run_git(&self, args: &[&str]) -> Result<std::process::Output> {
-        if self.work_dir.is_some() {
-            // Fall back to sync for testing with work_dir
-            let output = self.git_command().args(args).output()?;
-            Ok(output)
-        } else {
-            // Use async command execution
-            async_cmd::run_unchecked_with_timeout("git", args, self.timeout).await
+    /// `ExportPatch` keeps the branch like `Keep`; the series itself is
+    /// written by `export_patches`.
+    async fn cleanup(&self, option: CompletionOption) -> Result<()>;
+
+    /// Writes the run's commits as a `git format-patch` series into `dir`
+    /// and returns the patch files, in order.
+    async fn export_patches(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+}
+
+/// Returns the default directory patches are exported to for a change.
+///
+/// Follows the same `/tmp/ralphtool/` convention as the learnings file.
+pub fn default_patch_dir(change_name: &str) -> PathBuf {
+    PathBuf::from("/tmp/ralphtool").join(format!("{}-patches", change_name))
+}
+
+/// Builds the message of the single commit created by `CompletionOption::Squash`.
+fn squash_commit_message(change_name: &str, story_ids: &[String]) -> String {
+    let mut message = format!("Implement {}\n\n", change_name);
+    if story_ids.is_empty() {
+        message.push_str("Squashed from the Ralph loop run; no story reached a checkpoint.\n");
+    } else {
+        message.push_str("Squashed from the Ralph loop run. Completed stories:\n\n");
+        for id in story_ids {
+            message.push_str(&format!("- Story {}\n", id));
         }
     }
+    message
+}
+
+/// Creates the checkpoint manager for the given backend.
+///
+/// Falls back to the snapshot backend when the current directory is not in
+/// a git repository (see `CheckpointBackend::resolve`).
+pub fn create_checkpoint(
+    backend: CheckpointBackend,
+    change_name: &str,
+    timeout: Duration,
+) -> Box<dyn Checkpoint> {
+    let backend = match std::env::current_dir() {
+        Ok(dir) => backend.resolve(&dir),
+        Err(_) => backend,
+    };
+    match backend {
+        CheckpointBackend::InPlace => Box::new(InPlaceCheckpoint::with_timeout(change_name, timeout)),
+        CheckpointBackend::Worktree => Box::new(WorktreeCheckpoint::with_timeout(change_name, timeout)),
+        CheckpointBackend::Snapshot => Box::new(SnapshotCheckpoint::with_timeout(change_name, timeout)),
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::fs;
-    use tempfile::TempDir;
-
-    #[test]
-    fn checkpoint_with_timeout_sets_change_name() {
-        let checkpoint = Checkpoint::with_timeout("test-change", async_cmd::DEFAULT_TIMEOUT);
-        assert_eq!(checkpoint.change_name, "test-change");
-    }
-
-    #[test]
-    fn branch_name_format() {
-        let checkpoint = Checkpoint::with_timeout("my-feature", async_cmd::DEFAULT_TIMEOUT);
-        assert_eq!(checkpoint.branch_name(), "ralph/my-feature");
-    }
+    use clap::ValueEnum;
 
     #[test]
     fn completion_option_enum() {
@@ -299,487 +345,60 @@ mod tests {
         assert_eq!(CompletionOption::Keep, CompletionOption::Keep);
     }
 
-    /// Creates a temporary git repository for testing.
-    /// Returns (TempDir, Checkpoint) - TempDir must stay in scope to keep the directory.
-    fn setup_temp_repo_with_checkpoint(change_name: &str) -> (TempDir, Checkpoint) {
-        let temp_dir = TempDir::new().expect("Failed to create temp dir");
-        let repo_path = temp_dir.path().to_path_buf();
-
-        // Initialize git repo
-        Command::new("git")
-            .args(["init"])
-            .current_dir(&repo_path)
-            .output()
-            .expect("Failed to init git repo");
-
-        // Configure git user (required for commits)
-        Command::new("git")
-            .args(["config", "user.email", "test@test.com"])
-            .current_dir(&repo_path)
-            .output()
-            .expect("Failed to configure git email");
-
-        Command::new("git")
-            .args(["config", "user.name", "Test User"])
-            .current_dir(&repo_path)
-            .output()
-            .expect("Failed to configure git name");
-
-        // Create an initial commit (required for branch operations)
-        let test_file = repo_path.join("initial.txt");
-        fs::write(&test_file, "initial content").expect("Failed to write initial file");
-
-        Command::new("git")
-            .args(["add", "."])
-            .current_dir(&repo_path)
-            .output()
-            .expect("Failed to git add");
-
-        Command::new("git")
-            .args(["commit", "-m", "Initial commit"])
-            .current_dir(&repo_path)
-            .output()
-            .expect("Failed to create initial commit");
-
-        let checkpoint = Checkpoint::with_work_dir(change_name, repo_path);
-        (temp_dir, checkpoint)
-    }
-
-    /// Gets the repo path from a checkpoint for file operations.
-    /// Returns a cloned PathBuf to avoid borrowing issues.
-    fn repo_path(checkpoint: &Checkpoint) -> PathBuf {
-        checkpoint.work_dir.as_ref().expect("Checkpoint should have work_dir for tests").clone()
-    }
-
-    /// Helper to get current branch name
-    fn get_current_branch(repo_path: impl AsRef<std::path::Path>) -> String {
-        let output = Command::new("git")
-            .args(["rev-parse", "--abbrev-ref", "HEAD"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to get current branch");
-        String::from_utf8_lossy(&output.stdout).trim().to_string()
-    }
-
-    /// Helper to get commit count
-    fn get_commit_count(repo_path: impl AsRef<std::path::Path>) -> usize {
-        let output = Command::new("git")
-            .args(["rev-list", "--count", "HEAD"])
-            .current_dir(repo_path)
-            .output()
-            .expect("Failed to count commits");
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .parse()
-            .unwrap_or(0)
-    }
-
-    // ==================== init() tests ====================
-
-    #[tokio::test]
-    async fn init_stores_original_branch() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        // Get original branch name before init
-        let original_branch = get_current_branch(&path);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Verify original branch was stored internally
+    #[test]
+    fn completion_option_cli_names() {
+        let names: Vec<String> = CompletionOption::ALL
+            .iter()
+            .map(|o| o.to_possible_value().unwrap().get_name().to_string())
+            .collect();
         assert_eq!(
-            checkpoint.original_branch.as_deref(),
-            Some(original_branch.as_str())
+            names,
+            vec!["cleanup", "keep", "squash", "preserve-history", "export-patch"]
         );
     }
 
-    #[tokio::test]
-    async fn init_creates_ralph_branch() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Verify we're on the ralph branch
-        let current_branch = get_current_branch(&path);
-        assert_eq!(current_branch, "ralph/my-change");
-    }
-
-    #[tokio::test]
-    async fn init_creates_initial_commit() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        let commits_before = get_commit_count(&path);
-        checkpoint.init().await.expect("init should succeed");
-        let commits_after = get_commit_count(&path);
-
-        // Should have created one new commit
-        assert_eq!(commits_after, commits_before + 1);
-
-        // Verify commit message
-        let output = Command::new("git")
-            .args(["log", "-1", "--format=%s"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to get commit message");
-        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        assert_eq!(message, "initial state");
-    }
-
-    #[tokio::test]
-    async fn init_includes_uncommitted_changes() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        // Create uncommitted changes before init
-        let new_file = path.join("uncommitted.txt");
-        fs::write(&new_file, "uncommitted content").expect("Failed to write file");
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Verify file is committed in initial state
-        let output = Command::new("git")
-            .args(["status", "--porcelain"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to get git status");
-        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        assert!(status.is_empty(), "Working directory should be clean after init");
-    }
-
-    #[tokio::test]
-    async fn init_force_creates_branch_if_exists() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        // First init
-        checkpoint.init().await.expect("first init should succeed");
-
-        // Go back to original branch manually
-        Command::new("git")
-            .args(["checkout", "-"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to checkout");
-
-        // Modify the checkpoint
-        checkpoint.original_branch = None;
-
-        // Second init should work (force recreates branch)
-        checkpoint.init().await.expect("second init should succeed");
-
-        let current_branch = get_current_branch(&path);
-        assert_eq!(current_branch, "ralph/my-change");
-    }
-
-    // ==================== commit_checkpoint() tests ====================
-
-    #[tokio::test]
-    async fn commit_checkpoint_creates_commit_with_story_id() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Make changes
-        let test_file = path.join("feature.txt");
-        fs::write(&test_file, "feature code").expect("Failed to write file");
-
-        checkpoint.commit_checkpoint("story-1").await.expect("commit_checkpoint should succeed");
-
-        // Verify commit message
-        let output = Command::new("git")
-            .args(["log", "-1", "--format=%s"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to get commit message");
-        let message = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        assert_eq!(message, "checkpoint: story-1");
-    }
-
-    #[tokio::test]
-    async fn commit_checkpoint_includes_all_changes() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Create tracked and untracked files
-        let tracked_file = path.join("tracked.txt");
-        fs::write(&tracked_file, "tracked content").expect("Failed to write file");
-        let untracked_file = path.join("untracked.txt");
-        fs::write(&untracked_file, "untracked content").expect("Failed to write file");
-
-        checkpoint.commit_checkpoint("story-1").await.expect("commit_checkpoint should succeed");
-
-        // Verify working directory is clean
-        let output = Command::new("git")
-            .args(["status", "--porcelain"])
-            .current_dir(path)
-            .output()
-            .expect("Failed to get git status");
-        let status = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        assert!(status.is_empty(), "Working directory should be clean after checkpoint");
-    }
-
-    // ==================== revert() tests ====================
-
-    #[tokio::test]
-    async fn revert_discards_uncommitted_changes() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Make changes after init
-        let agent_file = path.join("agent_created.txt");
-        fs::write(&agent_file, "agent output").expect("Failed to write file");
-
-        // Modify existing file
-        let initial_file = path.join("initial.txt");
-        fs::write(&initial_file, "modified content").expect("Failed to write file");
-
-        checkpoint.revert().await.expect("revert should succeed");
-
-        // Verify agent file is gone
-        assert!(!agent_file.exists(), "Agent-created file should be removed");
-
-        // Verify existing file is restored
-        let content = fs::read_to_string(&initial_file).expect("Failed to read file");
-        assert_eq!(content, "initial content");
-    }
-
-    #[tokio::test]
-    async fn revert_preserves_committed_changes() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Story 1 completes successfully
-        let story1_file = path.join("story1.txt");
-        fs::write(&story1_file, "story 1 code").expect("Failed to write file");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        // Story 2 starts and fails
-        let story2_file = path.join("story2.txt");
-        fs::write(&story2_file, "story 2 failed attempt").expect("Failed to write file");
-
-        checkpoint.revert().await.expect("revert should succeed");
-
-        // Story 1 file should still exist (committed)
-        assert!(story1_file.exists(), "Story 1 file should remain after revert");
-
-        // Story 2 file should be gone (uncommitted)
-        assert!(!story2_file.exists(), "Story 2 file should be removed after revert");
-    }
-
-    // ==================== cleanup() tests ====================
-
-    #[tokio::test]
-    async fn cleanup_with_cleanup_option_returns_to_original_branch() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        let original_branch = get_current_branch(&path);
-        checkpoint.init().await.expect("init should succeed");
-
-        // Make and commit changes
-        let test_file = path.join("feature.txt");
-        fs::write(&test_file, "feature code").expect("Failed to write file");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
-
-        // Verify we're back on original branch
-        let current_branch = get_current_branch(&path);
-        assert_eq!(current_branch, original_branch);
-    }
-
-    #[tokio::test]
-    async fn cleanup_with_cleanup_option_brings_uncommitted_changes() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Make and commit changes
-        let test_file = path.join("feature.txt");
-        fs::write(&test_file, "feature code").expect("Failed to write file");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
-
-        // Verify changes are present but uncommitted
-        assert!(test_file.exists(), "Feature file should exist");
-
-        let output = Command::new("git")
-            .args(["status", "--porcelain"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to get git status");
-        let status = String::from_utf8_lossy(&output.stdout);
-        assert!(!status.is_empty(), "Should have uncommitted changes after cleanup");
-    }
-
-    #[tokio::test]
-    async fn cleanup_with_cleanup_option_deletes_ralph_branch() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
-
-        // Verify ralph branch is deleted
-        let output = Command::new("git")
-            .args(["branch", "--list", "ralph/my-change"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to list branches");
-        let branches = String::from_utf8_lossy(&output.stdout).trim().to_string();
-        assert!(branches.is_empty(), "Ralph branch should be deleted after cleanup");
+    #[test]
+    fn default_patch_dir_follows_tmp_convention() {
+        assert_eq!(
+            default_patch_dir("my-change"),
+            PathBuf::from("/tmp/ralphtool/my-change-patches")
+        );
     }
 
-    #[tokio::test]
-    async fn cleanup_with_keep_option_stays_on_ralph_branch() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
-
-        // Verify we're still on ralph branch
-        let current_branch = get_current_branch(&path);
-        assert_eq!(current_branch, "ralph/my-change");
+    #[test]
+    fn squash_commit_message_lists_stories() {
+        let message = squash_commit_message("my-change", &["1".to_string(), "2".to_string()]);
+        assert!(message.starts_with("Implement my-change\n\n"));
+        assert!(message.contains("- Story 1\n- Story 2"));
     }
 
-    #[tokio::test]
-    async fn cleanup_with_keep_option_preserves_commits() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("my-change");
-        let path = repo_path(&checkpoint);
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Make multiple commits
-        let file1 = path.join("story1.txt");
-        fs::write(&file1, "story 1").expect("Failed to write file");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        let file2 = path.join("story2.txt");
-        fs::write(&file2, "story 2").expect("Failed to write file");
-        checkpoint.commit_checkpoint("story-2").await.expect("commit should succeed");
-
-        let commits_before = get_commit_count(&path);
-        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
-        let commits_after = get_commit_count(&path);
-
-        // Commits should be preserved
-        assert_eq!(commits_after, commits_before);
+    #[test]
+    fn squash_commit_message_without_stories() {
+        let message = squash_commit_message("my-change", &[]);
+        assert!(message.contains("no story reached a checkpoint"));
     }
 
-    // ==================== Integration tests ====================
-
-    #[tokio::test]
-    async fn integration_full_checkpoint_cycle_with_retries() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("test-feature");
-        let path = repo_path(&checkpoint).clone();
-
-        // Initialize checkpoint system
-        checkpoint.init().await.expect("init should succeed");
-
-        // Story 1: Agent fails twice, then succeeds
-        let story1_file = path.join("story1.txt");
-
-        // Attempt 1: fails
-        fs::write(&story1_file, "attempt 1 - wrong approach").expect("write failed");
-        checkpoint.revert().await.expect("revert 1 should succeed");
-        assert!(!story1_file.exists(), "Failed attempt should be reverted");
-
-        // Attempt 2: fails
-        fs::write(&story1_file, "attempt 2 - still wrong").expect("write failed");
-        checkpoint.revert().await.expect("revert 2 should succeed");
-        assert!(!story1_file.exists(), "Failed attempt should be reverted");
-
-        // Attempt 3: succeeds
-        fs::write(&story1_file, "attempt 3 - success!").expect("write failed");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        // Story 2: Succeeds on first try
-        let story2_file = path.join("story2.txt");
-        fs::write(&story2_file, "story 2 success").expect("write failed");
-        checkpoint.commit_checkpoint("story-2").await.expect("commit should succeed");
-
-        // Cleanup with cleanup option
-        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
-
-        // Verify both story files exist as uncommitted changes
-        assert!(story1_file.exists(), "Story 1 file should exist");
-        assert!(story2_file.exists(), "Story 2 file should exist");
-
-        // Verify we're on original branch
-        let branch = get_current_branch(&path);
-        assert_ne!(branch, "ralph/test-feature", "Should be back on original branch");
+    #[test]
+    fn checkpoint_backend_defaults_to_in_place() {
+        assert_eq!(CheckpointBackend::default(), CheckpointBackend::InPlace);
     }
 
-    #[tokio::test]
-    async fn integration_max_retries_with_partial_work() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("partial-work");
-        let path = repo_path(&checkpoint).clone();
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Story 1 completes
-        let story1_file = path.join("story1.txt");
-        fs::write(&story1_file, "story 1 complete").expect("write failed");
-        checkpoint.commit_checkpoint("story-1").await.expect("commit should succeed");
-
-        // Story 2 fails repeatedly (max retries exceeded)
-        for i in 1..=3 {
-            let story2_file = path.join("story2.txt");
-            fs::write(&story2_file, format!("attempt {}", i)).expect("write failed");
-            checkpoint.revert().await.expect("revert should succeed");
-        }
-
-        // User chooses to cleanup - partial work (story 1) should be preserved
-        checkpoint.cleanup(CompletionOption::Cleanup).await.expect("cleanup should succeed");
-
-        // Story 1 work should exist as uncommitted changes
-        assert!(story1_file.exists(), "Story 1 work should be preserved");
-        let content = fs::read_to_string(&story1_file).expect("read failed");
-        assert_eq!(content, "story 1 complete");
+    #[test]
+    fn checkpoint_backend_cli_names() {
+        assert_eq!(CheckpointBackend::from_str("in-place", false), Ok(CheckpointBackend::InPlace));
+        assert_eq!(CheckpointBackend::from_str("worktree", false), Ok(CheckpointBackend::Worktree));
+        assert_eq!(CheckpointBackend::from_str("snapshot", false), Ok(CheckpointBackend::Snapshot));
     }
 
-    #[tokio::test]
-    async fn integration_keep_option_preserves_history() {
-        let (_temp_dir, mut checkpoint) = setup_temp_repo_with_checkpoint("keep-history");
-        let path = repo_path(&checkpoint).clone();
-
-        checkpoint.init().await.expect("init should succeed");
-
-        // Complete multiple stories
-        for i in 1..=3 {
-            let file = path.join(format!("story{}.txt", i));
-            fs::write(&file, format!("story {} content", i)).expect("write failed");
-            checkpoint.commit_checkpoint(&format!("story-{}", i)).await.expect("commit should succeed");
-        }
-
-        // User chooses to keep
-        checkpoint.cleanup(CompletionOption::Keep).await.expect("keep should succeed");
-
-        // Verify commit history is intact
-        let output = Command::new("git")
-            .args(["log", "--oneline"])
-            .current_dir(&path)
-            .output()
-            .expect("Failed to get log");
-        let log = String::from_utf8_lossy(&output.stdout);
-
-        assert!(log.contains("checkpoint: story-1"), "Story 1 commit should exist");
-        assert!(log.contains("checkpoint: story-2"), "Story 2 commit should exist");
-        assert!(log.contains("checkpoint: story-3"), "Story 3 commit should exist");
-        assert!(log.contains("initial state"), "Initial commit should exist");
+    #[test]
+    fn checkpoint_backend_resolves_to_snapshot_outside_git() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let nested = temp_dir.path().join("a/b");
+        std::fs::create_dir_all(&nested).unwrap();
+        assert_eq!(CheckpointBackend::Worktree.resolve(&nested), CheckpointBackend::Snapshot);
+
+        std::fs::create_dir(temp_dir.path().join(".git")).unwrap();
+        assert_eq!(CheckpointBackend::Worktree.resolve(&nested), CheckpointBackend::Worktree);
+        assert_eq!(CheckpointBackend::Snapshot.resolve(&nested), CheckpointBackend::Snapshot);
     }
 }
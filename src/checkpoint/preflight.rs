@@ -0,0 +1,361 @@
+//! Pre-flight repository checks.
+//!
+//! `init` switches branches and stages everything with `git add -A`, which
+//! goes wrong in a repository that is in the middle of another operation.
+//! The checks here run before the loop starts and report such states as
+//! issues: errors block the run, warnings need the user's confirmation.
+
+use std::path::Path;
+
+use super::{InPlaceCheckpoint, RunLock};
+use crate::error::{Error, Result};
+
+/// Default size in MiB above which untracked files are reported.
+pub const DEFAULT_UNTRACKED_SIZE_LIMIT_MB: u64 = 10;
+
+/// Marker files (relative to the git dir) of operations that are not finished.
+const UNFINISHED_OPERATIONS: [(&str, &str); 5] = [
+    ("MERGE_HEAD", "merge"),
+    ("rebase-merge", "rebase"),
+    ("rebase-apply", "rebase"),
+    ("CHERRY_PICK_HEAD", "cherry-pick"),
+    ("REVERT_HEAD", "revert"),
+];
+
+/// How serious a pre-flight issue is.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Severity {
+    /// The run can start once the user confirms.
+    Warning,
+    /// The run cannot start until the issue is fixed.
+    Error,
+}
+
+/// The check that reported an issue.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PreflightCheck {
+    /// HEAD is not on a branch, so there is no branch to return to.
+    DetachedHead,
+    /// A merge, rebase, cherry-pick or revert is in progress.
+    UnfinishedOperation,
+    /// A submodule has a new commit or uncommitted changes.
+    SubmoduleChanges,
+    /// An untracked file is above the size limit and would be committed.
+    LargeUntrackedFile,
+    /// Git has no author or committer identity, so commits would fail.
+    MissingIdentity,
+    /// Another instance holds the repository's run lock.
+    AnotherInstance,
+    /// The change's files have changes a checkout of the last commit lacks.
+    UncommittedChange,
+    /// The checks themselves could not run.
+    CheckFailed,
+}
+
+impl PreflightCheck {
+    /// Returns the severity of issues reported by this check.
+    pub fn severity(self) -> Severity {
+        match self {
+            PreflightCheck::DetachedHead
+            | PreflightCheck::UnfinishedOperation
+            | PreflightCheck::MissingIdentity
+            | PreflightCheck::AnotherInstance
+            | PreflightCheck::UncommittedChange
+            | PreflightCheck::CheckFailed => Severity::Error,
+            PreflightCheck::SubmoduleChanges | PreflightCheck::LargeUntrackedFile => {
+                Severity::Warning
+            }
+        }
+    }
+
+    /// Returns a machine-readable name for this check.
+    pub fn code(self) -> &'static str {
+        match self {
+            PreflightCheck::DetachedHead => "detached_head",
+            PreflightCheck::UnfinishedOperation => "unfinished_operation",
+            PreflightCheck::SubmoduleChanges => "submodule_changes",
+            PreflightCheck::LargeUntrackedFile => "large_untracked_file",
+            PreflightCheck::MissingIdentity => "missing_identity",
+            PreflightCheck::AnotherInstance => "another_instance",
+            PreflightCheck::UncommittedChange => "uncommitted_change",
+            PreflightCheck::CheckFailed => "check_failed",
+        }
+    }
+}
+
+/// A problem found by the pre-flight checks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PreflightIssue {
+    /// The check that found the problem.
+    pub check: PreflightCheck,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl PreflightIssue {
+    pub(super) fn new(check: PreflightCheck, message: impl Into<String>) -> Self {
+        Self {
+            check,
+            message: message.into(),
+        }
+    }
+
+    /// A blocking issue for checks that could not run, so the failure is
+    /// shown instead of passing as a clean repository.
+    pub fn check_failed(error: impl std::fmt::Display) -> Self {
+        Self::new(
+            PreflightCheck::CheckFailed,
+            format!("Pre-flight checks failed: {}", error),
+        )
+    }
+
+    /// Returns the severity of this issue.
+    pub fn severity(&self) -> Severity {
+        self.check.severity()
+    }
+
+    /// Returns true if any of the issues blocks the run.
+    pub fn any_blocking(issues: &[PreflightIssue]) -> bool {
+        issues.iter().any(|i| i.severity() == Severity::Error)
+    }
+}
+
+impl InPlaceCheckpoint {
+    /// Checks that the repository is in a state the loop can safely start in.
+    ///
+    /// Untracked files larger than `untracked_size_limit_mb` MiB are reported,
+    /// since `init` would commit them to the ralph branch.
+    pub(super) async fn check_repository(&self, untracked_size_limit_mb: u64) -> Result<Vec<PreflightIssue>> {
+        let mut issues = Vec::new();
+
+        if let Some(holder) = RunLock::holder(&self.lock_dir().await?) {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::AnotherInstance,
+                format!(
+                    "Another ralphtool instance (pid {}) is running change '{}' in this repository",
+                    holder.pid, holder.change_name
+                ),
+            ));
+        }
+
+        if self.is_detached().await? {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::DetachedHead,
+                "HEAD is detached; check out a branch to return to after the run",
+            ));
+        }
+
+        for operation in self.unfinished_operations().await? {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::UnfinishedOperation,
+                format!("A {} is in progress; finish or abort it first", operation),
+            ));
+        }
+
+        for path in self.changed_submodules().await? {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::SubmoduleChanges,
+                format!("Submodule {} has changes that would be committed", path),
+            ));
+        }
+
+        let limit = untracked_size_limit_mb.saturating_mul(1024 * 1024);
+        for (path, size) in self.large_untracked_files(limit).await? {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::LargeUntrackedFile,
+                format!(
+                    "Untracked file {} is {:.1} MiB and would be committed",
+                    path,
+                    size as f64 / (1024.0 * 1024.0)
+                ),
+            ));
+        }
+
+        for ident in ["GIT_AUTHOR_IDENT", "GIT_COMMITTER_IDENT"] {
+            let output = self.run_git(&["var", ident]).await?;
+            if !output.status.success() {
+                issues.push(PreflightIssue::new(
+                    PreflightCheck::MissingIdentity,
+                    "No git identity configured; set user.name and user.email",
+                ));
+                break;
+            }
+        }
+
+        Ok(issues)
+    }
+
+    /// Returns true if HEAD does not point to a branch.
+    async fn is_detached(&self) -> Result<bool> {
+        let output = self.run_git(&["symbolic-ref", "-q", "HEAD"]).await?;
+        match output.status.code() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            _ => Err(Error::Command {
+                cmd: "git symbolic-ref -q HEAD".to_string(),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            }),
+        }
+    }
+
+    /// Returns the names of operations whose marker files exist in the git dir.
+    async fn unfinished_operations(&self) -> Result<Vec<&'static str>> {
+        let git_dir = self
+            .git_stdout(&["rev-parse", "--path-format=absolute", "--git-dir"])
+            .await?;
+        let git_dir = Path::new(git_dir.trim());
+
+        let mut operations: Vec<&'static str> = Vec::new();
+        for (marker, operation) in UNFINISHED_OPERATIONS {
+            if git_dir.join(marker).exists() && !operations.contains(&operation) {
+                operations.push(operation);
+            }
+        }
+        Ok(operations)
+    }
+
+    /// Lists submodules with a new commit, modified or untracked content.
+    async fn changed_submodules(&self) -> Result<Vec<String>> {
+        let status = self
+            .git_stdout(&["status", "--porcelain=v2", "--ignore-submodules=none"])
+            .await?;
+        Ok(status.lines().filter_map(parse_changed_submodule).collect())
+    }
+
+    /// Lists untracked files larger than `limit` bytes with their size.
+    async fn large_untracked_files(&self, limit: u64) -> Result<Vec<(String, u64)>> {
+        let root = self
+            .git_stdout(&["rev-parse", "--show-toplevel"])
+            .await?;
+        let root = Path::new(root.trim());
+
+        let mut large = Vec::new();
+        for path in self.untracked_files().await? {
+            // Files can vanish between listing and reading
+            if let Ok(metadata) = std::fs::metadata(root.join(&path)) {
+                if metadata.len() > limit {
+                    large.push((path, metadata.len()));
+                }
+            }
+        }
+        Ok(large)
+    }
+}
+
+/// Returns the path of a changed submodule from a `git status --porcelain=v2`
+/// line, if the line describes one.
+///
+/// Ordinary changed entries look like `1 XY <sub> ...` with `<sub>` being
+/// `N...` for regular files and `S<c><m><u>` for submodules.
+fn parse_changed_submodule(line: &str) -> Option<String> {
+    let mut fields = line.splitn(9, ' ');
+    if fields.next()? != "1" {
+        return None;
+    }
+    let sub = fields.nth(1)?;
+    if !sub.starts_with('S') || sub == "S..." {
+        return None;
+    }
+    fields.nth(5).map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::test_support::{git, setup_temp_repo_with_checkpoint};
+    use std::fs;
+    use std::process::Command;
+
+    fn checks(issues: &[PreflightIssue]) -> Vec<PreflightCheck> {
+        issues.iter().map(|i| i.check).collect()
+    }
+
+    #[tokio::test]
+    async fn clean_repository_has_no_issues() {
+        let (_temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("test-change");
+        let issues = checkpoint.check_repository(DEFAULT_UNTRACKED_SIZE_LIMIT_MB).await.unwrap();
+        assert!(issues.is_empty(), "unexpected issues: {:?}", issues);
+    }
+
+    #[tokio::test]
+    async fn detached_head_blocks_the_run() {
+        let (temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("test-change");
+        git(temp_dir.path(), &["checkout", "-q", "--detach"]);
+
+        let issues = checkpoint.check_repository(DEFAULT_UNTRACKED_SIZE_LIMIT_MB).await.unwrap();
+
+        assert_eq!(checks(&issues), vec![PreflightCheck::DetachedHead]);
+        assert!(PreflightIssue::any_blocking(&issues));
+    }
+
+    #[tokio::test]
+    async fn unfinished_merge_blocks_the_run() {
+        let (temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("test-change");
+        let repo = temp_dir.path();
+        git(repo, &["checkout", "-q", "-b", "other"]);
+        fs::write(repo.join("initial.txt"), "other").unwrap();
+        git(repo, &["commit", "-q", "-am", "Other"]);
+        git(repo, &["checkout", "-q", "main"]);
+        fs::write(repo.join("initial.txt"), "main").unwrap();
+        git(repo, &["commit", "-q", "-am", "Main"]);
+        // Conflicts, leaving the merge unfinished
+        let _ = Command::new("git")
+            .args(["merge", "other"])
+            .current_dir(repo)
+            .output();
+
+        let issues = checkpoint.check_repository(DEFAULT_UNTRACKED_SIZE_LIMIT_MB).await.unwrap();
+
+        assert_eq!(checks(&issues), vec![PreflightCheck::UnfinishedOperation]);
+        assert!(issues[0].message.contains("merge"));
+    }
+
+    #[tokio::test]
+    async fn large_untracked_file_is_a_warning() {
+        let (temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("test-change");
+        fs::create_dir(temp_dir.path().join("target")).unwrap();
+        fs::write(temp_dir.path().join("target/app.bin"), vec![0u8; 2 * 1024 * 1024]).unwrap();
+        fs::write(temp_dir.path().join("small.txt"), "small").unwrap();
+
+        let issues = checkpoint.check_repository(1).await.unwrap();
+
+        assert_eq!(checks(&issues), vec![PreflightCheck::LargeUntrackedFile]);
+        assert!(issues[0].message.contains("target/app.bin"));
+        assert!(issues[0].message.contains("2.0 MiB"));
+        assert!(!PreflightIssue::any_blocking(&issues));
+    }
+
+    #[tokio::test]
+    async fn running_instance_blocks_the_run() {
+        let (temp_dir, checkpoint) = setup_temp_repo_with_checkpoint("test-change");
+        let lock = RunLock::acquire(&temp_dir.path().join(".git"), "other-change").unwrap();
+
+        let issues = checkpoint.check_repository(DEFAULT_UNTRACKED_SIZE_LIMIT_MB).await.unwrap();
+
+        assert_eq!(checks(&issues), vec![PreflightCheck::AnotherInstance]);
+        assert!(issues[0].message.contains("'other-change'"));
+
+        drop(lock);
+        let issues = checkpoint.check_repository(DEFAULT_UNTRACKED_SIZE_LIMIT_MB).await.unwrap();
+        assert!(issues.is_empty(), "unexpected issues: {:?}", issues);
+    }
+
+    #[test]
+    fn parse_changed_submodule_reads_submodule_entries() {
+        let modified = "1 .M SC.. 160000 160000 160000 abc abc vendor/lib";
+        assert_eq!(parse_changed_submodule(modified), Some("vendor/lib".to_string()));
+
+        let file = "1 .M N... 100644 100644 100644 abc abc src/main.rs";
+        assert_eq!(parse_changed_submodule(file), None);
+
+        let untracked = "? notes.txt";
+        assert_eq!(parse_changed_submodule(untracked), None);
+    }
+
+    #[test]
+    fn severity_matches_check() {
+        assert_eq!(PreflightCheck::MissingIdentity.severity(), Severity::Error);
+        assert_eq!(PreflightCheck::SubmoduleChanges.severity(), Severity::Warning);
+        assert_eq!(PreflightCheck::CheckFailed.severity(), Severity::Error);
+    }
+}
@@ -0,0 +1,1035 @@
+//! Snapshot checkpoint backend for projects that are not git repositories.
+//!
+//! Copies the project tree into a numbered snapshot directory at `init` and
+//! after each completed story, and restores the latest snapshot on revert.
+//! Files unchanged since the previous snapshot are hardlinked to it instead
+//! of copied; snapshots are never modified, so the links are safe. Files are
+//! always copied (never linked) between the project and a snapshot.
+//!
+//! Paths matching the ignore list (`DEFAULT_SNAPSHOT_IGNORE` plus the lines
+//! of a `.ralphignore` file in the project root) are neither snapshotted nor
+//! touched on revert. Symlinks and empty directories are left alone.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::commit::{checkpoint_story_id, STORY_TRAILER};
+use super::in_place::{remove_file_and_empty_parents, sanitize_ref_component};
+use super::preflight::PreflightCheck;
+use super::{
+    Attempt, Checkpoint, CheckpointCommit, CompletionOption, DiffStat, PreflightIssue, RevertSummary,
+    RunLock, StoryCheckpoint,
+};
+use crate::async_cmd;
+use crate::error::{Error, Result};
+
+/// Paths never snapshotted: build output, dependencies and VCS metadata.
+pub const DEFAULT_SNAPSHOT_IGNORE: &[&str] = &[
+    ".git",
+    "target",
+    "node_modules",
+    ".venv",
+    "__pycache__",
+];
+
+/// File in the project root listing extra paths to ignore, one per line.
+const IGNORE_FILE: &str = ".ralphignore";
+
+/// File in each snapshot holding the checkpoint commit message.
+const MESSAGE_FILE: &str = "MESSAGE";
+
+/// File in each archived attempt holding why the attempt failed.
+const REASON_FILE: &str = "REASON";
+
+/// File in each archived attempt naming the snapshot it started from.
+const BASE_FILE: &str = "BASE";
+
+/// Returns the default directory snapshots of a change in `project` are
+/// stored in: `<state dir>/snapshots/<project path>/<change>`.
+///
+/// Keying by the project path keeps projects with the same change name
+/// apart. The state dir (`$XDG_STATE_HOME/ralphtool`, else
+/// `~/.local/state/ralphtool`) survives a reboot, so a run can be resumed.
+pub fn default_snapshot_dir(project: &Path, change_name: &str) -> PathBuf {
+    state_dir()
+        .join("snapshots")
+        .join(sanitize_ref_component(&project.to_string_lossy()))
+        .join(change_name)
+}
+
+/// Returns the persistent per-user state directory of ralphtool.
+fn state_dir() -> PathBuf {
+    let non_empty = |name| std::env::var_os(name).filter(|value| !value.is_empty()).map(PathBuf::from);
+    non_empty("XDG_STATE_HOME")
+        .or_else(|| non_empty("HOME").map(|home| home.join(".local").join("state")))
+        .unwrap_or_else(std::env::temp_dir)
+        .join("ralphtool")
+}
+
+/// Checkpoint manager that snapshots the project tree on disk.
+///
+/// Snapshots live in `<snapshot dir>/checkpoints/<NNNN>/tree`, numbered from
+/// `0000` (the initial state). Failed attempts are archived to
+/// `<snapshot dir>/attempts/<story>-<n>/tree`.
+#[derive(Debug, Clone)]
+pub struct SnapshotCheckpoint {
+    /// The name of the change being worked on.
+    change_name: String,
+    /// Project directory; the current directory when unset.
+    project_dir: Option<PathBuf>,
+    /// Directory holding the snapshots.
+    snapshot_dir: PathBuf,
+    /// Timeout for the `diff` commands run by `export_patches`.
+    timeout: Duration,
+}
+
+impl SnapshotCheckpoint {
+    /// Without a git dir, the run lock lives in the project's directory
+    /// next to the snapshot directories of its changes, so all changes of
+    /// a project share it.
+    fn lock_dir(&self) -> PathBuf {
+        self.snapshot_dir.parent().unwrap_or(&self.snapshot_dir).to_path_buf()
+    }
+
+    /// Creates a new SnapshotCheckpoint with a custom timeout.
+    pub fn with_timeout(change_name: impl Into<String>, timeout: Duration) -> Self {
+        let change_name = change_name.into();
+        let project = std::env::current_dir().unwrap_or_default();
+        Self {
+            snapshot_dir: default_snapshot_dir(&project, &change_name),
+            change_name,
+            project_dir: None,
+            timeout,
+        }
+    }
+
+    /// Creates a new SnapshotCheckpoint for `project_dir`, storing snapshots
+    /// in `snapshot_dir`. Used primarily for testing.
+    #[cfg(test)]
+    pub fn with_dirs(change_name: impl Into<String>, project_dir: PathBuf, snapshot_dir: PathBuf) -> Self {
+        Self {
+            change_name: change_name.into(),
+            project_dir: Some(project_dir),
+            snapshot_dir,
+            timeout: async_cmd::DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Returns the directory holding the numbered snapshots.
+    fn checkpoints_dir(&self) -> PathBuf {
+        self.snapshot_dir.join("checkpoints")
+    }
+
+    /// Returns the numbered snapshot directories, oldest first.
+    fn snapshots(&self) -> Result<Vec<PathBuf>> {
+        let entries = match fs::read_dir(self.checkpoints_dir()) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let numbered = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.len() == 4 && name.bytes().all(|b| b.is_ascii_digit()));
+            if numbered {
+                snapshots.push(path);
+            }
+        }
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    /// Returns the latest snapshot, failing if there is none.
+    fn latest_snapshot(&self) -> Result<PathBuf> {
+        self.snapshots()?.pop().ok_or_else(|| Error::Command {
+            cmd: "snapshot".to_string(),
+            stderr: format!("No snapshots recorded for {}", self.change_name),
+        })
+    }
+
+    /// Returns the ignore list: the defaults plus the project's `.ralphignore`.
+    fn ignore_patterns(&self, project: &Path) -> Vec<String> {
+        let mut patterns: Vec<String> = DEFAULT_SNAPSHOT_IGNORE.iter().map(|p| p.to_string()).collect();
+        if let Ok(contents) = fs::read_to_string(project.join(IGNORE_FILE)) {
+            patterns.extend(
+                contents
+                    .lines()
+                    .map(|line| line.trim().trim_matches('/'))
+                    .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                    .map(String::from),
+            );
+        }
+        patterns
+    }
+
+    /// Lists the project files to snapshot, relative to the project root.
+    fn project_files(&self, project: &Path) -> Result<BTreeSet<String>> {
+        let patterns = self.ignore_patterns(project);
+        let mut files = BTreeSet::new();
+        collect_files(project, project, &patterns, &self.snapshot_dir, &mut files)?;
+        Ok(files)
+    }
+
+    /// Lists the files that differ between the project and the latest snapshot.
+    fn changed_files(&self) -> Result<Vec<String>> {
+        let project = self.work_dir()?;
+        let tree = self.latest_snapshot()?.join("tree");
+        let mut files = self.project_files(&project)?;
+        collect_files(&tree, &tree, &[], &self.snapshot_dir, &mut files)?;
+        Ok(files
+            .into_iter()
+            .filter(|relative| !same_contents(&tree.join(relative), &project.join(relative)))
+            .collect())
+    }
+
+    /// Copies the project into a new snapshot with the given message.
+    ///
+    /// Excluded paths keep their content from the previous snapshot, if any.
+    fn take_snapshot(&self, message: &str, excluded: &[String]) -> Result<()> {
+        let project = self.work_dir()?;
+        let previous = self.snapshots()?.pop();
+        let number = previous
+            .as_deref()
+            .and_then(|path| path.file_name()?.to_str()?.parse::<usize>().ok())
+            .map_or(0, |n| n + 1);
+
+        // Build under a temporary name so a crash never leaves a partial snapshot
+        let final_dir = self.checkpoints_dir().join(format!("{:04}", number));
+        let staging = self.checkpoints_dir().join(format!("{:04}.tmp", number));
+        remove_dir_if_exists(&staging)?;
+        let tree = staging.join("tree");
+        let mut files = self.project_files(&project)?;
+        for relative in excluded {
+            files.remove(relative);
+        }
+        if let Some(prev) = &previous {
+            let prev_tree = prev.join("tree");
+            for relative in excluded {
+                if prev_tree.join(relative).is_file() {
+                    let dest = tree.join(relative);
+                    if let Some(parent) = dest.parent() {
+                        fs::create_dir_all(parent)?;
+                    }
+                    fs::hard_link(prev_tree.join(relative), &dest)
+                        .or_else(|_| fs::copy(prev_tree.join(relative), &dest).map(|_| ()))?;
+                }
+            }
+        }
+        for relative in files {
+            let source = project.join(&relative);
+            let dest = tree.join(&relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            let unchanged = previous
+                .as_deref()
+                .map(|prev| prev.join("tree").join(&relative))
+                .filter(|prev| same_contents(prev, &source));
+            match unchanged {
+                Some(prev) if fs::hard_link(&prev, &dest).is_ok() => {}
+                _ => {
+                    fs::copy(&source, &dest)?;
+                }
+            }
+        }
+        fs::create_dir_all(&tree)?;
+        fs::write(staging.join(MESSAGE_FILE), message)?;
+        fs::rename(&staging, &final_dir)?;
+        Ok(())
+    }
+
+    /// Restores the project to the latest snapshot.
+    fn restore_latest(&self) -> Result<RevertSummary> {
+        let project = self.work_dir()?;
+        let tree = self.latest_snapshot()?.join("tree");
+        let mut snapshot_files = BTreeSet::new();
+        collect_files(&tree, &tree, &[], &self.snapshot_dir, &mut snapshot_files)?;
+
+        let mut summary = RevertSummary::default();
+        for relative in self.project_files(&project)? {
+            if !snapshot_files.contains(&relative) {
+                remove_file_and_empty_parents(&project, &relative)?;
+                summary.removed.push(relative);
+            }
+        }
+        for relative in snapshot_files {
+            let source = tree.join(&relative);
+            let dest = project.join(&relative);
+            if same_contents(&source, &dest) {
+                continue;
+            }
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            // Replace rather than overwrite, in case dest is a link
+            let _ = fs::remove_file(&dest);
+            fs::copy(&source, &dest)?;
+            summary.restored.push(relative);
+        }
+        Ok(summary)
+    }
+
+    /// Lists the archived attempts, oldest first.
+    fn attempts(&self) -> Result<Vec<Attempt>> {
+        let entries = match fs::read_dir(self.snapshot_dir.join("attempts")) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut attempts = Vec::new();
+        for entry in entries {
+            let dir = entry?.path();
+            let Some((story_id, attempt)) = dir
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.rsplit_once('-'))
+                .and_then(|(story_id, n)| Some((story_id.to_string(), n.parse::<usize>().ok()?)))
+            else {
+                continue;
+            };
+            let reason_file = dir.join(REASON_FILE);
+            let archived_at = fs::metadata(&reason_file).and_then(|m| m.modified()).ok();
+            attempts.push((
+                archived_at,
+                Attempt {
+                    ref_name: dir.to_string_lossy().to_string(),
+                    story_id,
+                    attempt,
+                    reason: fs::read_to_string(&reason_file).unwrap_or_default().trim().to_string(),
+                },
+            ));
+        }
+        attempts.sort_by_key(|(archived_at, _)| *archived_at);
+        Ok(attempts.into_iter().map(|(_, attempt)| attempt).collect())
+    }
+
+    /// Lists the story snapshots with their diffstats against the snapshot
+    /// before them, oldest first. The snapshot number stands in for the commit.
+    fn story_checkpoints(&self) -> Result<Vec<StoryCheckpoint>> {
+        let mut checkpoints = Vec::new();
+        for pair in self.snapshots()?.windows(2) {
+            let message = fs::read_to_string(pair[1].join(MESSAGE_FILE)).unwrap_or_default();
+            let subject = message.lines().next().unwrap_or("");
+            let trailer = message
+                .lines()
+                .find_map(|line| line.strip_prefix(STORY_TRAILER)?.strip_prefix(':'))
+                .unwrap_or("");
+            let Some(story_id) = checkpoint_story_id(subject, trailer) else {
+                continue;
+            };
+
+            let (old, new) = (pair[0].join("tree"), pair[1].join("tree"));
+            let mut files = BTreeSet::new();
+            collect_files(&old, &old, &[], &self.snapshot_dir, &mut files)?;
+            collect_files(&new, &new, &[], &self.snapshot_dir, &mut files)?;
+            let mut stat = DiffStat::default();
+            for relative in files {
+                if same_contents(&old.join(&relative), &new.join(&relative)) {
+                    continue;
+                }
+                let (insertions, deletions) = line_changes(&old.join(&relative), &new.join(&relative));
+                stat.files_changed += 1;
+                stat.insertions += insertions;
+                stat.deletions += deletions;
+            }
+
+            checkpoints.push(StoryCheckpoint {
+                commit: pair[1].file_name().unwrap_or_default().to_string_lossy().to_string(),
+                story_id,
+                subject: subject.to_string(),
+                files_changed: stat.files_changed,
+                insertions: stat.insertions,
+                deletions: stat.deletions,
+            });
+        }
+        Ok(checkpoints)
+    }
+
+    /// Drops the snapshots after `number` and restores the project to it.
+    fn rollback(&self, number: &str) -> Result<()> {
+        if !self.story_checkpoints()?.iter().any(|c| c.commit == number) {
+            return Err(Error::Command {
+                cmd: format!("rollback to {}", number),
+                stderr: format!("{} is not a snapshot of {}", number, self.change_name),
+            });
+        }
+        for snapshot in self.snapshots()? {
+            if snapshot.file_name().is_some_and(|name| name.to_string_lossy().as_ref() > number) {
+                remove_dir_if_exists(&snapshot)?;
+            }
+        }
+        self.restore_latest().map(|_| ())
+    }
+
+    /// Copies the project into an attempt archive and returns its path.
+    fn archive(&self, story_id: &str, attempt: usize, reason: &str) -> Result<String> {
+        let project = self.work_dir()?;
+        let dir = self
+            .snapshot_dir
+            .join("attempts")
+            .join(format!("{}-{}", sanitize_ref_component(story_id), attempt));
+        remove_dir_if_exists(&dir)?;
+        let tree = dir.join("tree");
+        fs::create_dir_all(&tree)?;
+        for relative in self.project_files(&project)? {
+            let dest = tree.join(&relative);
+            if let Some(parent) = dest.parent() {
+                fs::create_dir_all(parent)?;
+            }
+            fs::copy(project.join(&relative), &dest)?;
+        }
+        fs::write(dir.join(REASON_FILE), reason)?;
+        if let Some(base) = self.snapshots()?.pop().as_deref().and_then(Path::file_name) {
+            fs::write(dir.join(BASE_FILE), base.to_string_lossy().as_bytes())?;
+        }
+        Ok(dir.to_string_lossy().to_string())
+    }
+}
+
+#[async_trait]
+impl Checkpoint for SnapshotCheckpoint {
+    fn work_dir(&self) -> Result<PathBuf> {
+        match &self.project_dir {
+            Some(dir) => Ok(dir.clone()),
+            None => Ok(std::env::current_dir()?),
+        }
+    }
+
+    async fn lock(&self) -> Result<RunLock> {
+        let dir = self.lock_dir();
+        fs::create_dir_all(&dir)?;
+        RunLock::acquire(&dir, &self.change_name)
+    }
+
+    /// Without git there is no repository state to check; only another
+    /// instance running in the project blocks the run.
+    async fn preflight(&self, _untracked_size_limit_mb: u64) -> Result<Vec<PreflightIssue>> {
+        Ok(RunLock::holder(&self.lock_dir())
+            .map(|holder| {
+                PreflightIssue::new(
+                    PreflightCheck::AnotherInstance,
+                    format!(
+                        "Another ralphtool instance (pid {}) is running change '{}' in this project",
+                        holder.pid, holder.change_name
+                    ),
+                )
+            })
+            .into_iter()
+            .collect())
+    }
+
+    /// The project holds the work whatever the completion option.
+    async fn result_dir(&self) -> Result<PathBuf> {
+        self.work_dir()
+    }
+
+    async fn list_attempts(&self) -> Result<Vec<Attempt>> {
+        let this = self.clone();
+        blocking(move || this.attempts()).await
+    }
+
+    async fn list_checkpoints(&self) -> Result<Vec<StoryCheckpoint>> {
+        let this = self.clone();
+        blocking(move || this.story_checkpoints()).await
+    }
+
+    /// Diffs the archived tree against the snapshot the attempt started
+    /// from, or the latest snapshot for archives that do not name one.
+    async fn attempt_diff(&self, ref_name: &str) -> Result<String> {
+        let this = self.clone();
+        let ref_name = ref_name.to_string();
+        let (base, attempt) = blocking(move || {
+            if !this.attempts()?.iter().any(|a| a.ref_name == ref_name) {
+                return Err(Error::Command {
+                    cmd: "attempt diff".to_string(),
+                    stderr: format!("{} is not an archived attempt of {}", ref_name, this.change_name),
+                });
+            }
+            let attempt = PathBuf::from(&ref_name);
+            let base = match fs::read_to_string(attempt.join(BASE_FILE)) {
+                Ok(number) => this.checkpoints_dir().join(number.trim()),
+                Err(_) => this.latest_snapshot()?,
+            };
+            Ok((base.join("tree"), attempt.join("tree")))
+        })
+        .await?;
+
+        let (old, new) = (base.to_string_lossy(), attempt.to_string_lossy());
+        let output = async_cmd::run_unchecked_in_dir_with_timeout(
+            "diff",
+            &["-ruN", &old, &new],
+            &self.snapshot_dir,
+            self.timeout,
+        )
+        .await?;
+        match output.status.code() {
+            Some(0) | Some(1) => Ok(String::from_utf8_lossy(&output.stdout).to_string()),
+            _ => Err(Error::Command {
+                cmd: format!("diff -ruN {} {}", old, new),
+                stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+            }),
+        }
+    }
+
+    /// Deletes the later snapshots and restores the project to the chosen one.
+    async fn rollback_to(&self, commit: &str) -> Result<()> {
+        let this = self.clone();
+        let number = commit.to_string();
+        blocking(move || this.rollback(&number)).await
+    }
+
+    /// Starts a fresh run: discards earlier snapshots and snapshots the
+    /// project as the initial state.
+    async fn init(&mut self) -> Result<()> {
+        let this = self.clone();
+        blocking(move || {
+            remove_dir_if_exists(&this.snapshot_dir)?;
+            this.take_snapshot("initial state", &[])
+        })
+        .await
+    }
+
+    /// Resumes a previous run, restoring the project to its latest snapshot.
+    async fn resume(&mut self) -> Result<()> {
+        let this = self.clone();
+        blocking(move || this.restore_latest().map(|_| ())).await
+    }
+
+    /// Nothing to record: snapshots cover every file that is not ignored.
+    async fn begin_attempt(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    /// Lists files whose contents differ from the latest snapshot, including
+    /// files missing from either side.
+    async fn pending_files(&self) -> Result<Vec<String>> {
+        let this = self.clone();
+        blocking(move || this.changed_files()).await
+    }
+
+    /// Snapshots cover the project directory, so paths are relative to it.
+    async fn top_level_dir(&self) -> Result<PathBuf> {
+        self.work_dir()
+    }
+
+    /// Counts lines added and removed per changed file, ignoring line order
+    /// (a moved line counts as neither). Binary files count as changed only.
+    async fn pending_diffstat(&self) -> Result<DiffStat> {
+        let this = self.clone();
+        blocking(move || {
+            let project = this.work_dir()?;
+            let tree = this.latest_snapshot()?.join("tree");
+            let files = this.changed_files()?;
+            let mut stat = DiffStat {
+                files_changed: files.len(),
+                ..DiffStat::default()
+            };
+            for relative in &files {
+                let (insertions, deletions) = line_changes(&tree.join(relative), &project.join(relative));
+                stat.insertions += insertions;
+                stat.deletions += deletions;
+            }
+            Ok(stat)
+        })
+        .await
+    }
+
+    /// Snapshots the project, storing the commit message with the snapshot.
+    async fn commit_checkpoint(&self, commit: &CheckpointCommit) -> Result<()> {
+        let this = self.clone();
+        let message = commit.message(&self.change_name);
+        let excluded = commit.excluded_paths.clone();
+        blocking(move || this.take_snapshot(&message, &excluded)).await
+    }
+
+    /// Copies the project into `attempts/<story>-<attempt>` and returns its path.
+    async fn archive_attempt(&self, story_id: &str, attempt: usize, reason: &str) -> Result<String> {
+        let this = self.clone();
+        let story_id = story_id.to_string();
+        let reason = reason.to_string();
+        blocking(move || this.archive(&story_id, attempt, &reason)).await
+    }
+
+    /// Restores changed files from the latest snapshot and removes files
+    /// that are not in it.
+    async fn revert(&self) -> Result<RevertSummary> {
+        let this = self.clone();
+        blocking(move || this.restore_latest()).await
+    }
+
+    /// Handles completion. The project already holds the work, so `Cleanup`
+    /// just discards the snapshots; `Keep` and `ExportPatch` keep them.
+    /// `Squash` and `PreserveHistory` need git and fail.
+    async fn cleanup(&self, option: CompletionOption) -> Result<()> {
+        match option {
+            CompletionOption::Cleanup => {
+                let dir = self.snapshot_dir.clone();
+                blocking(move || remove_dir_if_exists(&dir)).await
+            }
+            CompletionOption::Keep | CompletionOption::ExportPatch => Ok(()),
+            CompletionOption::Squash | CompletionOption::PreserveHistory => Err(Error::Command {
+                cmd: "cleanup".to_string(),
+                stderr: format!(
+                    "{:?} needs a git repository; choose Cleanup or Keep instead",
+                    option
+                ),
+            }),
+        }
+    }
+
+    /// Writes one `diff -ruN` patch per checkpoint into `dir`, skipping
+    /// checkpoints without changes. Apply them with `patch -p2`.
+    async fn export_patches(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let this = self.clone();
+        let snapshots = blocking(move || this.snapshots()).await?;
+        fs::create_dir_all(dir)?;
+
+        let mut patches = Vec::new();
+        for (number, pair) in snapshots.windows(2).enumerate() {
+            let name = |path: &Path| {
+                format!("{}/tree", path.file_name().unwrap_or_default().to_string_lossy())
+            };
+            let (old, new) = (name(&pair[0]), name(&pair[1]));
+            let output = async_cmd::run_unchecked_in_dir_with_timeout(
+                "diff",
+                &["-ruN", &old, &new],
+                &self.checkpoints_dir(),
+                self.timeout,
+            )
+            .await?;
+            match output.status.code() {
+                Some(0) => continue,
+                Some(1) => {}
+                _ => {
+                    return Err(Error::Command {
+                        cmd: format!("diff -ruN {} {}", old, new),
+                        stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+                    })
+                }
+            }
+            let path = dir.join(format!("{:04}-checkpoint.patch", number + 1));
+            fs::write(&path, &output.stdout)?;
+            patches.push(path);
+        }
+        Ok(patches)
+    }
+}
+
+/// Runs blocking filesystem work off the tokio worker threads.
+async fn blocking<T: Send + 'static>(f: impl FnOnce() -> Result<T> + Send + 'static) -> Result<T> {
+    tokio::task::spawn_blocking(f).await.map_err(|e| Error::Command {
+        cmd: "snapshot".to_string(),
+        stderr: format!("Task join error: {}", e),
+    })?
+}
+
+/// Recursively collects regular files under `dir` as paths relative to
+/// `root`, skipping ignored paths and the snapshot directory itself.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    patterns: &[String],
+    snapshot_dir: &Path,
+    files: &mut BTreeSet<String>,
+) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let path = entry.path();
+        let Ok(relative) = path.strip_prefix(root) else {
+            continue;
+        };
+        let relative = relative.to_string_lossy().replace('\\', "/");
+        if path == snapshot_dir || is_ignored(&relative, patterns) {
+            continue;
+        }
+        let file_type = entry.file_type()?;
+        if file_type.is_dir() {
+            collect_files(root, &path, patterns, snapshot_dir, files)?;
+        } else if file_type.is_file() {
+            files.insert(relative);
+        }
+    }
+    Ok(())
+}
+
+/// Returns true if a relative path matches the ignore list. Patterns with a
+/// `/` match that path and everything below it; others match any component.
+fn is_ignored(relative: &str, patterns: &[String]) -> bool {
+    patterns.iter().any(|pattern| {
+        if pattern.contains('/') {
+            relative == pattern
+                || relative
+                    .strip_prefix(pattern.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+        } else {
+            relative.split('/').any(|component| component == pattern)
+        }
+    })
+}
+
+/// Returns true if both files exist and have the same contents.
+fn same_contents(a: &Path, b: &Path) -> bool {
+    match (fs::metadata(a), fs::metadata(b)) {
+        (Ok(ma), Ok(mb)) if ma.is_file() && mb.is_file() && ma.len() == mb.len() => {
+            matches!((fs::read(a), fs::read(b)), (Ok(ca), Ok(cb)) if ca == cb)
+        }
+        _ => false,
+    }
+}
+
+/// Counts the lines of `new` missing from `old` and the lines of `old`
+/// missing from `new`. Missing files count as empty; binary files count
+/// as no lines.
+fn line_changes(old: &Path, new: &Path) -> (usize, usize) {
+    let lines = |path: &Path| -> HashMap<String, usize> {
+        let mut counts = HashMap::new();
+        if let Ok(contents) = fs::read_to_string(path) {
+            for line in contents.lines() {
+                *counts.entry(line.to_string()).or_insert(0) += 1;
+            }
+        }
+        counts
+    };
+    let (old, new) = (lines(old), lines(new));
+    let missing = |from: &HashMap<String, usize>, to: &HashMap<String, usize>| -> usize {
+        from.iter()
+            .map(|(line, count)| count.saturating_sub(to.get(line).copied().unwrap_or(0)))
+            .sum()
+    };
+    (missing(&new, &old), missing(&old, &new))
+}
+
+/// Removes a directory tree, ignoring a missing one.
+fn remove_dir_if_exists(dir: &Path) -> Result<()> {
+    match fs::remove_dir_all(dir) {
+        Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn setup() -> (TempDir, SnapshotCheckpoint) {
+        let temp_dir = TempDir::new().expect("Failed to create temp dir");
+        let project = temp_dir.path().join("project");
+        fs::create_dir_all(project.join("src")).unwrap();
+        fs::write(project.join("src/main.txt"), "original").unwrap();
+        let checkpoint = SnapshotCheckpoint::with_dirs(
+            "my-change",
+            project,
+            temp_dir.path().join("snapshots"),
+        );
+        (temp_dir, checkpoint)
+    }
+
+    #[test]
+    fn default_snapshot_dir_is_keyed_by_project() {
+        let a = default_snapshot_dir(Path::new("/work/a"), "my-change");
+        let b = default_snapshot_dir(Path::new("/work/b"), "my-change");
+
+        assert_ne!(a, b);
+        assert!(a.ends_with("my-change"));
+        assert_ne!(a.parent(), b.parent());
+    }
+
+    #[tokio::test]
+    async fn preflight_reports_a_running_instance() {
+        let (_temp_dir, checkpoint) = setup();
+        assert!(checkpoint.preflight(1).await.unwrap().is_empty());
+
+        let lock = checkpoint.lock().await.expect("lock should succeed");
+        let issues = checkpoint.preflight(1).await.unwrap();
+        drop(lock);
+
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].check, PreflightCheck::AnotherInstance);
+    }
+
+    #[tokio::test]
+    async fn lists_story_checkpoints_and_attempts() {
+        let (_temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("broken.txt"), "broken").unwrap();
+        checkpoint.archive_attempt("1", 1, "tests failed").await.unwrap();
+        checkpoint.revert().await.unwrap();
+        fs::write(project.join("src/main.txt"), "original\nstory 1").unwrap();
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("1"))
+            .await
+            .expect("commit should succeed");
+
+        let checkpoints = checkpoint.list_checkpoints().await.unwrap();
+        assert_eq!(checkpoints.len(), 1);
+        assert_eq!(checkpoints[0].story_id, "1");
+        assert_eq!(checkpoints[0].commit, "0001");
+        assert_eq!(
+            (checkpoints[0].files_changed, checkpoints[0].insertions, checkpoints[0].deletions),
+            (1, 1, 0)
+        );
+
+        let attempts = checkpoint.list_attempts().await.unwrap();
+        assert_eq!(attempts.len(), 1);
+        assert_eq!((attempts[0].story_id.as_str(), attempts[0].attempt), ("1", 1));
+        assert_eq!(attempts[0].reason, "tests failed");
+    }
+
+    #[tokio::test]
+    async fn rollback_to_restores_snapshot_and_drops_later_ones() {
+        let (_temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("story1.txt"), "story 1").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+        fs::write(project.join("story2.txt"), "story 2").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("2")).await.unwrap();
+
+        assert!(checkpoint.rollback_to("0000").await.is_err());
+        checkpoint.rollback_to("0001").await.expect("rollback should succeed");
+
+        assert!(project.join("story1.txt").exists());
+        assert!(!project.join("story2.txt").exists());
+        let checkpoints = checkpoint.list_checkpoints().await.unwrap();
+        assert_eq!(checkpoints.iter().map(|c| c.story_id.as_str()).collect::<Vec<_>>(), vec!["1"]);
+    }
+
+    #[tokio::test]
+    async fn attempt_diff_compares_against_the_starting_snapshot() {
+        let (_temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("broken.txt"), "broken\n").unwrap();
+        let archived = checkpoint.archive_attempt("1", 1, "tests failed").await.unwrap();
+
+        let diff = checkpoint.attempt_diff(&archived).await.expect("diff should succeed");
+        assert!(diff.contains("+broken"));
+        assert!(checkpoint.attempt_diff("/etc").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn revert_restores_latest_snapshot() {
+        let (_temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("story1.txt"), "story 1").unwrap();
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("1"))
+            .await
+            .expect("commit should succeed");
+
+        fs::write(project.join("src/main.txt"), "broken").unwrap();
+        fs::create_dir_all(project.join("new/dir")).unwrap();
+        fs::write(project.join("new/dir/junk.txt"), "junk").unwrap();
+        fs::remove_file(project.join("story1.txt")).unwrap();
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert_eq!(summary.removed, vec!["new/dir/junk.txt"]);
+        assert_eq!(summary.restored, vec!["src/main.txt", "story1.txt"]);
+        assert_eq!(fs::read_to_string(project.join("src/main.txt")).unwrap(), "original");
+        assert_eq!(fs::read_to_string(project.join("story1.txt")).unwrap(), "story 1");
+        assert!(!project.join("new").exists());
+    }
+
+    #[tokio::test]
+    async fn snapshots_hardlink_unchanged_files_and_keep_message() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("story1.txt"), "story 1").unwrap();
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("1"))
+            .await
+            .expect("commit should succeed");
+
+        let checkpoints = temp_dir.path().join("snapshots/checkpoints");
+        let message = fs::read_to_string(checkpoints.join("0001/MESSAGE")).unwrap();
+        assert!(message.starts_with("checkpoint: 1"));
+
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::MetadataExt;
+            let old = fs::metadata(checkpoints.join("0000/tree/src/main.txt")).unwrap();
+            let new = fs::metadata(checkpoints.join("0001/tree/src/main.txt")).unwrap();
+            assert_eq!(old.ino(), new.ino());
+        }
+
+        // Editing the project in place must not reach the snapshots
+        fs::write(project.join("src/main.txt"), "edited").unwrap();
+        let snapshot = fs::read_to_string(checkpoints.join("0001/tree/src/main.txt")).unwrap();
+        assert_eq!(snapshot, "original");
+    }
+
+    #[tokio::test]
+    async fn pending_files_and_excluded_paths() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("src/main.txt"), "changed").unwrap();
+        fs::write(project.join(".env"), "SECRET=1").unwrap();
+
+        assert_eq!(checkpoint.pending_files().await.unwrap(), vec![".env", "src/main.txt"]);
+        fs::remove_file(project.join("src/main.txt")).unwrap();
+        assert_eq!(checkpoint.pending_files().await.unwrap(), vec![".env", "src/main.txt"]);
+        fs::write(project.join("src/main.txt"), "changed").unwrap();
+
+        let commit = CheckpointCommit {
+            excluded_paths: vec![".env".to_string(), "src/main.txt".to_string()],
+            ..CheckpointCommit::new("1")
+        };
+        checkpoint.commit_checkpoint(&commit).await.expect("commit should succeed");
+
+        // Excluded paths keep their previous content, or stay out entirely
+        let tree = temp_dir.path().join("snapshots/checkpoints/0001/tree");
+        assert!(!tree.join(".env").exists());
+        assert_eq!(fs::read_to_string(tree.join("src/main.txt")).unwrap(), "original");
+    }
+
+    #[tokio::test]
+    async fn pending_diffstat_counts_changed_lines() {
+        let (_temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("src/main.txt"), "changed\nadded\n").unwrap();
+        fs::write(project.join("new.txt"), "one\ntwo\n").unwrap();
+
+        let stat = checkpoint.pending_diffstat().await.unwrap();
+
+        assert_eq!(
+            stat,
+            DiffStat {
+                files_changed: 2,
+                insertions: 4,
+                deletions: 1,
+            }
+        );
+    }
+
+    #[tokio::test]
+    async fn ignored_paths_are_not_snapshotted_or_reverted() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+        fs::write(project.join(IGNORE_FILE), "# build output\ndist/\nsrc/generated\n").unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::create_dir_all(project.join("dist")).unwrap();
+        fs::write(project.join("dist/app.js"), "built").unwrap();
+        fs::create_dir_all(project.join("target")).unwrap();
+        fs::write(project.join("target/out"), "built").unwrap();
+        fs::write(project.join("src/generated"), "gen").unwrap();
+
+        let summary = checkpoint.revert().await.expect("revert should succeed");
+
+        assert!(summary.removed.is_empty());
+        assert!(project.join("dist/app.js").exists());
+        assert!(project.join("target/out").exists());
+        assert!(!temp_dir.path().join("snapshots/checkpoints/0000/tree/dist").exists());
+    }
+
+    #[tokio::test]
+    async fn archive_attempt_copies_failed_work() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("attempt.txt"), "failed work").unwrap();
+
+        let archived = checkpoint
+            .archive_attempt("story 1", 2, "tests failed")
+            .await
+            .expect("archive should succeed");
+
+        let dir = temp_dir.path().join("snapshots/attempts/story_1-2");
+        assert_eq!(PathBuf::from(archived), dir);
+        assert_eq!(fs::read_to_string(dir.join("tree/attempt.txt")).unwrap(), "failed work");
+        assert_eq!(fs::read_to_string(dir.join(REASON_FILE)).unwrap(), "tests failed");
+    }
+
+    #[tokio::test]
+    async fn resume_without_snapshots_fails() {
+        let (_temp_dir, mut checkpoint) = setup();
+        assert!(checkpoint.resume().await.is_err());
+    }
+
+    #[tokio::test]
+    async fn cleanup_discards_snapshots_but_keeps_work() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        fs::write(project.join("story1.txt"), "story 1").unwrap();
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("1"))
+            .await
+            .expect("commit should succeed");
+
+        assert!(checkpoint.cleanup(CompletionOption::Squash).await.is_err());
+        checkpoint
+            .cleanup(CompletionOption::Keep)
+            .await
+            .expect("keep should succeed");
+        assert!(temp_dir.path().join("snapshots").exists());
+
+        checkpoint
+            .cleanup(CompletionOption::Cleanup)
+            .await
+            .expect("cleanup should succeed");
+        assert!(!temp_dir.path().join("snapshots").exists());
+        assert!(project.join("story1.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn export_patches_writes_one_diff_per_changed_checkpoint() {
+        let (temp_dir, mut checkpoint) = setup();
+        let project = checkpoint.work_dir().unwrap();
+
+        checkpoint.init().await.expect("init should succeed");
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("1"))
+            .await
+            .expect("commit should succeed");
+        fs::write(project.join("story2.txt"), "story 2\n").unwrap();
+        checkpoint
+            .commit_checkpoint(&CheckpointCommit::new("2"))
+            .await
+            .expect("commit should succeed");
+
+        let patch_dir = temp_dir.path().join("patches");
+        let patches = checkpoint
+            .export_patches(&patch_dir)
+            .await
+            .expect("export should succeed");
+
+        assert_eq!(patches, vec![patch_dir.join("0002-checkpoint.patch")]);
+        let patch = fs::read_to_string(&patches[0]).unwrap();
+        assert!(patch.contains("+++ 0002/tree/story2.txt"));
+        assert!(patch.contains("+story 2"));
+    }
+
+    #[test]
+    fn is_ignored_matches_components_and_prefixes() {
+        let patterns = vec!["node_modules".to_string(), "src/generated".to_string()];
+        assert!(is_ignored("node_modules", &patterns));
+        assert!(is_ignored("web/node_modules/x.js", &patterns));
+        assert!(is_ignored("src/generated/a.rs", &patterns));
+        assert!(!is_ignored("src/generated_code.rs", &patterns));
+        assert!(!is_ignored("src/main.rs", &patterns));
+    }
+}
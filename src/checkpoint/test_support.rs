@@ -0,0 +1,46 @@
+//! Git repository fixtures shared by the checkpoint backends' tests.
+
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+use tempfile::TempDir;
+
+use super::InPlaceCheckpoint;
+
+/// Runs git in `dir` and returns trimmed stdout, panicking on failure.
+pub(super) fn git(dir: &Path, args: &[&str]) -> String {
+    let output = Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .output()
+        .expect("Failed to run git");
+    assert!(
+        output.status.success(),
+        "git {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8_lossy(&output.stdout).trim().to_string()
+}
+
+/// Creates a temporary git repository with one commit on `main`.
+/// The TempDir must stay in scope to keep the directory.
+pub(super) fn setup_temp_repo() -> TempDir {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let repo = temp_dir.path();
+    git(repo, &["init", "-q", "-b", "main"]);
+    git(repo, &["config", "user.email", "test@test.com"]);
+    git(repo, &["config", "user.name", "Test User"]);
+    fs::write(repo.join("initial.txt"), "initial content").expect("Failed to write initial file");
+    git(repo, &["add", "."]);
+    git(repo, &["commit", "-q", "-m", "Initial commit"]);
+    temp_dir
+}
+
+/// Creates a temporary git repository and an in-place checkpoint working in it.
+pub(super) fn setup_temp_repo_with_checkpoint(change_name: &str) -> (TempDir, InPlaceCheckpoint) {
+    let temp_dir = setup_temp_repo();
+    let checkpoint = InPlaceCheckpoint::with_work_dir(change_name, temp_dir.path().to_path_buf());
+    (temp_dir, checkpoint)
+}
@@ -0,0 +1,645 @@
+//! Worktree checkpoint backend.
+//!
+//! Checks the `ralph/{change_name}` branch out in a dedicated git worktree
+//! (`git worktree add <dir> ralph/<change>`) so the agent never touches the
+//! user's checkout. Checkpoints, attempts and reverts run inside the worktree;
+//! only the completion options that land the work touch the primary checkout,
+//! by merging the ralph branch into the branch that is already checked out
+//! there.
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use async_trait::async_trait;
+
+use super::preflight::PreflightCheck;
+use super::{
+    Attempt, Checkpoint, CheckpointCommit, CompletionOption, DiffStat, InPlaceCheckpoint,
+    PreflightIssue, RevertSummary, RunLock, RunState, StoryCheckpoint,
+};
+use crate::error::{Error, Result};
+
+/// Directory under the git common dir that holds ralph worktrees.
+const WORKTREES_DIR: &str = "ralph-worktrees";
+
+/// Checkpoint manager that runs the loop in a separate git worktree.
+///
+/// The worktree lives at `<git common dir>/ralph-worktrees/<change>`. It
+/// starts from the last commit of the current branch, so uncommitted changes
+/// in the primary checkout are not part of the run.
+#[derive(Debug, Clone)]
+pub struct WorktreeCheckpoint {
+    /// The name of the change being worked on.
+    change_name: String,
+    /// Timeout for git commands.
+    timeout: Duration,
+    /// Runs git in the primary checkout (branches, run state, worktree list).
+    primary: InPlaceCheckpoint,
+    /// Runs git inside the worktree, set once `init` or `resume` succeeded.
+    worktree: Option<InPlaceCheckpoint>,
+}
+
+impl WorktreeCheckpoint {
+    /// Creates a new WorktreeCheckpoint with a custom timeout.
+    pub fn with_timeout(change_name: impl Into<String>, timeout: Duration) -> Self {
+        let change_name = change_name.into();
+        Self {
+            primary: InPlaceCheckpoint::with_timeout(change_name.clone(), timeout),
+            change_name,
+            timeout,
+            worktree: None,
+        }
+    }
+
+    /// Creates a new WorktreeCheckpoint for the repository at `repo_dir`.
+    /// Used primarily for testing.
+    #[cfg(test)]
+    pub fn with_repo_dir(change_name: impl Into<String>, repo_dir: PathBuf) -> Self {
+        let change_name = change_name.into();
+        Self {
+            primary: InPlaceCheckpoint::with_work_dir(change_name.clone(), repo_dir),
+            change_name,
+            timeout: crate::async_cmd::DEFAULT_TIMEOUT,
+            worktree: None,
+        }
+    }
+
+    /// Returns the directory the worktree for this change is checked out in.
+    pub async fn worktree_path(&self) -> Result<PathBuf> {
+        let common_dir = self
+            .primary
+            .git_stdout(&["rev-parse", "--path-format=absolute", "--git-common-dir"])
+            .await?;
+        Ok(PathBuf::from(common_dir.trim())
+            .join(WORKTREES_DIR)
+            .join(&self.change_name))
+    }
+
+    /// Returns the checkpoint operating inside the worktree.
+    fn worktree(&self) -> Result<&InPlaceCheckpoint> {
+        self.worktree.as_ref().ok_or_else(not_set_up)
+    }
+
+    /// Starts using the worktree checked out at `path`.
+    ///
+    /// Nobody but the run works in the worktree, so its untracked files are
+    /// the agent's to remove on revert.
+    fn attach(&mut self, path: PathBuf) {
+        self.worktree = Some(
+            InPlaceCheckpoint::in_dir(self.change_name.clone(), path, self.timeout)
+                .owning_untracked_files(),
+        );
+    }
+
+    /// Adds a worktree at `path` with the ralph branch checked out.
+    ///
+    /// With `reset_to`, the branch is (re)created at that commit; otherwise the
+    /// existing branch is checked out as is.
+    async fn add_worktree(&self, path: &Path, reset_to: Option<&str>) -> Result<()> {
+        let branch_name = self.primary.branch_name();
+        let path = path.to_string_lossy();
+        match reset_to {
+            Some(commit) => {
+                self.primary
+                    .git_stdout(&["worktree", "add", "-B", &branch_name, &path, commit])
+                    .await?
+            }
+            None => {
+                self.primary
+                    .git_stdout(&["worktree", "add", &path, &branch_name])
+                    .await?
+            }
+        };
+        Ok(())
+    }
+
+    /// Removes the worktree at `path`, if any, and prunes stale registrations.
+    async fn remove_worktree(&self, path: &Path) -> Result<()> {
+        if path.exists() {
+            let path_str = path.to_string_lossy();
+            if self
+                .primary
+                .git_stdout(&["worktree", "remove", "--force", &path_str])
+                .await
+                .is_err()
+            {
+                // Not a registered worktree (e.g. left over from a crash)
+                std::fs::remove_dir_all(path)?;
+            }
+        }
+        self.primary.git_stdout(&["worktree", "prune"]).await?;
+        Ok(())
+    }
+
+    /// Returns the original branch, checking it is still checked out in the
+    /// primary checkout so it can be merged into without a checkout.
+    async fn checked_out_original_branch(&self) -> Result<String> {
+        let branch_name = self.primary.branch_name();
+        let original_branch = self.primary.original_branch_name().await?;
+
+        let current_branch = self.primary.current_branch().await?;
+        if current_branch != original_branch {
+            return Err(Error::Command {
+                cmd: format!("git merge {}", branch_name),
+                stderr: format!(
+                    "Primary checkout is on '{}' instead of '{}'; switch back to merge {}",
+                    current_branch, original_branch, branch_name
+                ),
+            });
+        }
+        Ok(original_branch)
+    }
+
+    /// Performs cleanup without checking out a branch in the primary checkout.
+    ///
+    /// Squash-merges the ralph branch into the original branch, which must
+    /// still be checked out in the primary checkout. If the original branch
+    /// advanced during the run, the ralph branch is first rebased onto it
+    /// inside the worktree, so conflicts never reach the primary checkout.
+    /// Without a commit message the result is left as unstaged changes; with
+    /// one it is committed as a single commit. Then removes the worktree and
+    /// the ralph branch.
+    async fn do_cleanup(&self, commit_message: Option<&str>) -> Result<()> {
+        let branch_name = self.primary.branch_name();
+        let original_branch = self.checked_out_original_branch().await?;
+
+        let worktree = self.worktree()?;
+        if worktree.has_advanced(&original_branch).await? {
+            worktree.rebase_onto(&original_branch).await?;
+        }
+
+        // Staged work of the user would end up in the squash commit
+        if commit_message.is_some() && self.primary.has_staged_changes().await? {
+            return Err(Error::Command {
+                cmd: format!("git merge --squash {}", branch_name),
+                stderr: "Primary checkout has staged changes; commit or unstage them first".to_string(),
+            });
+        }
+
+        // Merge --squash to bring all changes as staged
+        if let Err(e) = self
+            .primary
+            .git_stdout(&["merge", "--squash", &branch_name])
+            .await
+        {
+            let files = self.primary.unmerged_files().await?;
+            if files.is_empty() {
+                return Err(e);
+            }
+            // Undo the half-done merge, keeping the user's own changes
+            let _ = self.primary.git_stdout(&["reset", "-q", "--merge"]).await;
+            return Err(Error::MergeConflict {
+                branch: original_branch,
+                files,
+            });
+        }
+        match commit_message {
+            Some(message) => {
+                if self.primary.has_staged_changes().await? {
+                    self.primary.git_stdout(&["commit", "-q", "-m", message]).await?;
+                }
+            }
+            None => {
+                self.primary.git_stdout(&["reset", "-q", "HEAD"]).await?;
+            }
+        }
+
+        self.finish().await
+    }
+
+    /// Lands the checkpoint commits on the original branch, keeping history.
+    ///
+    /// Rebases the ralph branch onto the original branch inside the worktree,
+    /// then fast-forwards the original branch in the primary checkout.
+    async fn do_preserve_history(&self) -> Result<()> {
+        let branch_name = self.primary.branch_name();
+        let original_branch = self.checked_out_original_branch().await?;
+
+        self.worktree()?.rebase_onto(&original_branch).await?;
+        self.primary
+            .git_stdout(&["merge", "--ff-only", &branch_name])
+            .await?;
+
+        self.finish().await
+    }
+
+    /// Removes the worktree, the ralph branch and the run state once the
+    /// work has landed on the original branch.
+    async fn finish(&self) -> Result<()> {
+        // The branch can only be deleted once no worktree has it checked out
+        let path = self.worktree_path().await?;
+        self.remove_worktree(&path).await?;
+        self.primary
+            .git_stdout(&["branch", "-D", &self.primary.branch_name()])
+            .await?;
+
+        // The run is finished; nothing is left to resume
+        self.primary.remove_run_state().await
+    }
+}
+
+#[async_trait]
+impl Checkpoint for WorktreeCheckpoint {
+    fn work_dir(&self) -> Result<PathBuf> {
+        self.worktree()?.work_dir()
+    }
+
+    async fn lock(&self) -> Result<RunLock> {
+        self.primary.lock().await
+    }
+
+    /// Checks the primary checkout. The worktree starts from its last commit,
+    /// so untracked files are never committed and their size is not checked.
+    /// For the same reason, the agent would work from a missing or stale plan
+    /// if the change's directory has uncommitted changes.
+    async fn preflight(&self, _untracked_size_limit_mb: u64) -> Result<Vec<PreflightIssue>> {
+        let mut issues = self.primary.check_repository(u64::MAX).await?;
+
+        let change_dir = format!("openspec/changes/{}", self.change_name);
+        let status = self
+            .primary
+            .git_stdout(&["status", "--porcelain", "--untracked-files=all", "--", &change_dir])
+            .await?;
+        if !status.trim().is_empty() {
+            issues.push(PreflightIssue::new(
+                PreflightCheck::UncommittedChange,
+                format!(
+                    "{} has uncommitted changes the worktree would not see; commit them first",
+                    change_dir
+                ),
+            ));
+        }
+        Ok(issues)
+    }
+
+    /// The worktree while it is kept, the primary checkout once the work
+    /// was merged there and the worktree removed.
+    async fn result_dir(&self) -> Result<PathBuf> {
+        let path = self.worktree_path().await?;
+        if path.join(".git").exists() {
+            Ok(path)
+        } else {
+            self.primary.work_dir()
+        }
+    }
+
+    /// Attempt refs are shared by all worktrees, so the primary checkout lists them.
+    async fn list_attempts(&self) -> Result<Vec<Attempt>> {
+        self.primary.list_attempts().await
+    }
+
+    /// The ralph branch is shared by all worktrees, so the primary checkout lists it.
+    async fn list_checkpoints(&self) -> Result<Vec<StoryCheckpoint>> {
+        self.primary.list_checkpoints().await
+    }
+
+    async fn attempt_diff(&self, ref_name: &str) -> Result<String> {
+        self.primary.attempt_diff(ref_name).await
+    }
+
+    /// The primary checkout resets the worktree when it has the branch
+    /// checked out.
+    async fn rollback_to(&self, commit: &str) -> Result<()> {
+        self.primary.rollback_to(commit).await
+    }
+
+    /// Records the run state from the primary checkout and checks out a fresh
+    /// ralph branch at its HEAD in the worktree, replacing any earlier one.
+    async fn init(&mut self) -> Result<()> {
+        // A fresh run starts without attempts archived by earlier runs
+        self.primary.delete_attempts().await?;
+
+        let state = RunState::starting_now(
+            self.primary.current_branch().await?,
+            self.primary.head_commit().await?,
+        );
+        self.primary.save_run_state(&state).await?;
+
+        let path = self.worktree_path().await?;
+        self.remove_worktree(&path).await?;
+        self.add_worktree(&path, Some(&state.base_commit)).await?;
+        self.attach(path);
+        Ok(())
+    }
+
+    /// Reuses the worktree of the interrupted run, discarding uncommitted
+    /// work from its last attempt, or checks the existing ralph branch out in
+    /// a new worktree if it is gone.
+    async fn resume(&mut self) -> Result<()> {
+        let branch_name = self.primary.branch_name();
+        if self.primary.load_run_state().await?.is_none() {
+            return Err(Error::Command {
+                cmd: "resume".to_string(),
+                stderr: format!("No run state recorded for {}", branch_name),
+            });
+        }
+
+        let path = self.worktree_path().await?;
+        if path.join(".git").exists() {
+            self.attach(path);
+            // The interrupted attempt never reached a checkpoint
+            self.worktree()?.revert().await?;
+        } else {
+            self.remove_worktree(&path).await?;
+            self.add_worktree(&path, None).await?;
+            self.attach(path);
+        }
+        Ok(())
+    }
+
+    async fn begin_attempt(&mut self) -> Result<()> {
+        self.worktree.as_mut().ok_or_else(not_set_up)?.begin_attempt().await
+    }
+
+    async fn pending_files(&self) -> Result<Vec<String>> {
+        self.worktree()?.pending_files().await
+    }
+
+    async fn top_level_dir(&self) -> Result<PathBuf> {
+        self.worktree()?.top_level_dir().await
+    }
+
+    async fn pending_diffstat(&self) -> Result<DiffStat> {
+        self.worktree()?.pending_diffstat().await
+    }
+
+    async fn commit_checkpoint(&self, commit: &CheckpointCommit) -> Result<()> {
+        self.worktree()?.commit_checkpoint(commit).await
+    }
+
+    async fn archive_attempt(&self, story_id: &str, attempt: usize, reason: &str) -> Result<String> {
+        self.worktree()?.archive_attempt(story_id, attempt, reason).await
+    }
+
+    async fn revert(&self) -> Result<RevertSummary> {
+        self.worktree()?.revert().await
+    }
+
+    /// Handles completion based on the user's choice.
+    ///
+    /// - `Cleanup`: Squash-merges into the original branch and removes the worktree
+    /// - `Keep`: Leaves the worktree and the ralph branch in place
+    /// - `Squash`: Like `Cleanup`, but commits the changes as one commit
+    /// - `PreserveHistory`: Rebases in the worktree and fast-forwards the original branch
+    /// - `ExportPatch`: Like `Keep` (the series is written separately)
+    async fn cleanup(&self, option: CompletionOption) -> Result<()> {
+        match option {
+            CompletionOption::Cleanup => self.do_cleanup(None).await,
+            CompletionOption::Squash => {
+                let original_branch = self.primary.original_branch_name().await?;
+                let message = self.primary.squash_message(&original_branch).await?;
+                self.do_cleanup(Some(&message)).await
+            }
+            CompletionOption::PreserveHistory => self.do_preserve_history().await,
+            // No-op for keep; export_patches writes the series
+            CompletionOption::Keep | CompletionOption::ExportPatch => Ok(()),
+        }
+    }
+
+    async fn export_patches(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        self.primary.export_patches(dir).await
+    }
+}
+
+/// Error for operations that need the worktree before it was set up.
+fn not_set_up() -> Error {
+    Error::Command {
+        cmd: "worktree".to_string(),
+        stderr: "Worktree not set up - was init() called?".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::checkpoint::test_support::{self, git};
+    use std::fs;
+    use tempfile::TempDir;
+
+    /// Creates a temporary git repository and a worktree checkpoint for it.
+    fn setup_temp_repo(change_name: &str) -> (TempDir, WorktreeCheckpoint) {
+        let temp_dir = test_support::setup_temp_repo();
+        let checkpoint =
+            WorktreeCheckpoint::with_repo_dir(change_name, temp_dir.path().to_path_buf());
+        (temp_dir, checkpoint)
+    }
+
+    #[tokio::test]
+    async fn init_checks_out_ralph_branch_in_worktree() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+
+        let work_dir = checkpoint.work_dir().unwrap();
+        assert_ne!(work_dir, temp_dir.path());
+        assert_eq!(git(&work_dir, &["rev-parse", "--abbrev-ref", "HEAD"]), "ralph/test-change");
+        assert!(work_dir.join("initial.txt").exists());
+
+        // The primary checkout stays on its branch
+        assert_eq!(git(temp_dir.path(), &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+        let state = checkpoint.primary.load_run_state().await.unwrap().unwrap();
+        assert_eq!(state.original_branch, "main");
+    }
+
+    #[tokio::test]
+    async fn preflight_reports_uncommitted_change_dir() {
+        let (temp_dir, checkpoint) = setup_temp_repo("test-change");
+        let change_dir = temp_dir.path().join("openspec/changes/test-change");
+        fs::create_dir_all(&change_dir).unwrap();
+        fs::write(change_dir.join("tasks.md"), "- [ ] 1.1 Task").unwrap();
+
+        let issues = checkpoint.preflight(10).await.unwrap();
+        assert!(issues.iter().any(|i| i.check == PreflightCheck::UncommittedChange));
+
+        git(temp_dir.path(), &["add", "."]);
+        git(temp_dir.path(), &["commit", "-q", "-m", "Add change"]);
+        let issues = checkpoint.preflight(10).await.unwrap();
+        assert!(issues.is_empty());
+    }
+
+    #[tokio::test]
+    async fn work_dir_fails_before_init() {
+        let (_temp_dir, checkpoint) = setup_temp_repo("test-change");
+        assert!(checkpoint.work_dir().is_err());
+    }
+
+    #[tokio::test]
+    async fn checkpoints_and_reverts_stay_in_worktree() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        checkpoint.begin_attempt().await.unwrap();
+        fs::write(work_dir.join("story2.txt"), "broken").unwrap();
+        let summary = checkpoint.revert().await.unwrap();
+
+        assert_eq!(summary.removed, vec!["story2.txt"]);
+        assert!(work_dir.join("story1.txt").exists());
+        assert!(!temp_dir.path().join("story1.txt").exists());
+        assert_eq!(git(&work_dir, &["log", "-1", "--format=%s"]), "checkpoint: 1");
+    }
+
+    #[tokio::test]
+    async fn cleanup_merges_into_primary_checkout_and_removes_worktree() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+        checkpoint.cleanup(CompletionOption::Cleanup).await.unwrap();
+
+        let repo = temp_dir.path();
+        assert_eq!(git(repo, &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+        assert_eq!(fs::read_to_string(repo.join("story1.txt")).unwrap(), "done");
+        assert_eq!(git(repo, &["status", "--porcelain"]), "?? story1.txt");
+        assert!(!work_dir.exists());
+        assert!(git(repo, &["branch", "--list", "ralph/test-change"]).is_empty());
+        assert!(checkpoint.primary.load_run_state().await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn cleanup_refuses_when_primary_checkout_switched_branch() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        git(temp_dir.path(), &["checkout", "-q", "-b", "other"]);
+
+        let err = checkpoint.cleanup(CompletionOption::Cleanup).await.unwrap_err();
+        assert!(err.to_string().contains("instead of 'main'"));
+        assert!(checkpoint.work_dir().unwrap().exists());
+    }
+
+    #[tokio::test]
+    async fn squash_commits_into_primary_checkout() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        checkpoint.cleanup(CompletionOption::Squash).await.unwrap();
+
+        let repo = temp_dir.path();
+        assert_eq!(git(repo, &["log", "--format=%s"]), "Implement test-change\nInitial commit");
+        assert_eq!(git(repo, &["status", "--porcelain"]), "");
+        assert!(!work_dir.exists());
+    }
+
+    #[tokio::test]
+    async fn preserve_history_fast_forwards_primary_checkout() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        checkpoint.cleanup(CompletionOption::PreserveHistory).await.unwrap();
+
+        let repo = temp_dir.path();
+        assert_eq!(git(repo, &["log", "--format=%s"]), "checkpoint: 1\nInitial commit");
+        assert!(repo.join("story1.txt").exists());
+        assert!(git(repo, &["branch", "--list", "ralph/test-change"]).is_empty());
+    }
+
+    #[tokio::test]
+    async fn cleanup_conflict_leaves_both_checkouts_untouched() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("initial.txt"), "ralph version").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        let repo = temp_dir.path();
+        fs::write(repo.join("initial.txt"), "upstream version").unwrap();
+        git(repo, &["commit", "-q", "-am", "Upstream work"]);
+
+        let err = checkpoint.cleanup(CompletionOption::Cleanup).await.unwrap_err();
+
+        assert!(matches!(err, Error::MergeConflict { ref files, .. } if files == &["initial.txt"]));
+        assert_eq!(git(repo, &["status", "--porcelain"]), "");
+        assert_eq!(git(&work_dir, &["status", "--porcelain"]), "");
+        assert_eq!(git(&work_dir, &["log", "-1", "--format=%s"]), "checkpoint: 1");
+    }
+
+    #[tokio::test]
+    async fn cleanup_rebases_onto_advanced_original_branch() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        let repo = temp_dir.path();
+        fs::write(repo.join("upstream.txt"), "upstream").unwrap();
+        git(repo, &["add", "."]);
+        git(repo, &["commit", "-q", "-m", "Upstream work"]);
+
+        checkpoint.cleanup(CompletionOption::PreserveHistory).await.unwrap();
+
+        assert_eq!(
+            git(repo, &["log", "--format=%s"]),
+            "checkpoint: 1\nUpstream work\nInitial commit"
+        );
+    }
+
+    #[tokio::test]
+    async fn keep_leaves_worktree_and_branch() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        checkpoint.cleanup(CompletionOption::Keep).await.unwrap();
+
+        assert!(work_dir.join("story1.txt").exists());
+        assert!(!temp_dir.path().join("story1.txt").exists());
+        assert_eq!(git(temp_dir.path(), &["rev-parse", "--abbrev-ref", "HEAD"]), "main");
+    }
+
+    #[tokio::test]
+    async fn resume_recreates_missing_worktree_from_branch() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+        fs::remove_dir_all(&work_dir).unwrap();
+
+        let mut resumed = WorktreeCheckpoint::with_repo_dir("test-change", temp_dir.path().to_path_buf());
+        resumed.resume().await.unwrap();
+
+        assert_eq!(resumed.work_dir().unwrap(), work_dir);
+        assert!(work_dir.join("story1.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn resume_discards_interrupted_attempt_in_existing_worktree() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("partial.txt"), "half done").unwrap();
+
+        let mut resumed = WorktreeCheckpoint::with_repo_dir("test-change", temp_dir.path().to_path_buf());
+        resumed.resume().await.unwrap();
+
+        assert!(!work_dir.join("partial.txt").exists());
+    }
+
+    #[tokio::test]
+    async fn fresh_init_replaces_previous_worktree() {
+        let (temp_dir, mut checkpoint) = setup_temp_repo("test-change");
+        checkpoint.init().await.unwrap();
+        let work_dir = checkpoint.work_dir().unwrap();
+        fs::write(work_dir.join("story1.txt"), "done").unwrap();
+        checkpoint.commit_checkpoint(&CheckpointCommit::new("1")).await.unwrap();
+
+        let mut fresh = WorktreeCheckpoint::with_repo_dir("test-change", temp_dir.path().to_path_buf());
+        fresh.init().await.unwrap();
+
+        assert!(!work_dir.join("story1.txt").exists());
+        assert_eq!(git(&work_dir, &["log", "-1", "--format=%s"]), "Initial commit");
+    }
+}
@@ -20,10 +20,18 @@ pub enum Error {
     Json(serde_json::Error),
     /// Command execution error.
     Command { cmd: String, stderr: String },
+    /// Merge or rebase conflict, aborted before anything was changed.
+    MergeConflict { branch: String, files: Vec<String> },
+    /// Another instance holds the repository's run lock.
+    AlreadyRunning { change: String, pid: u32 },
     /// Parse error.
     Parse(String),
+    /// Invalid configuration (flags or config files).
+    Config(String),
     /// Claude CLI not found.
     ClaudeNotFound,
+    /// Agent CLI (named by its program) not found.
+    AgentNotFound(String),
     /// Agent execution error.
     AgentExecution(String),
     /// Agent output error.
@@ -44,8 +52,12 @@ impl Error {
             Error::Io(_) => "IO_ERROR",
             Error::Json(_) => "JSON_ERROR",
             Error::Command { .. } => "COMMAND_ERROR",
+            Error::MergeConflict { .. } => "MERGE_CONFLICT",
+            Error::AlreadyRunning { .. } => "ALREADY_RUNNING",
             Error::Parse(_) => "PARSE_ERROR",
+            Error::Config(_) => "CONFIG_ERROR",
             Error::ClaudeNotFound => "CLAUDE_NOT_FOUND",
+            Error::AgentNotFound(_) => "AGENT_NOT_FOUND",
             Error::AgentExecution(_) => "AGENT_EXECUTION_ERROR",
             Error::AgentOutput(_) => "AGENT_OUTPUT_ERROR",
         }
@@ -63,12 +75,27 @@ impl fmt::Display for Error {
             Error::Command { cmd, stderr } => {
                 write!(f, "Command '{}' failed: {}", cmd, stderr)
             }
+            Error::MergeConflict { branch, files } => {
+                write!(f, "Conflicts with {} in: {}", branch, files.join(", "))
+            }
+            Error::AlreadyRunning { change, pid } => write!(
+                f,
+                "Another ralphtool instance (pid {}) is running change '{}' in this repository",
+                pid, change
+            ),
             Error::Parse(msg) => write!(f, "Parse error: {}", msg),
+            Error::Config(msg) => write!(f, "Invalid configuration: {}", msg),
             Error::ClaudeNotFound => write!(
                 f,
                 "Claude CLI not found.\n\
                  Please ensure Claude Code is installed and in your PATH."
             ),
+            Error::AgentNotFound(program) => write!(
+                f,
+                "Agent CLI '{}' not found.\n\
+                 Please ensure it is installed and in your PATH.",
+                program
+            ),
             Error::AgentExecution(msg) => write!(f, "Agent execution error: {}", msg),
             Error::AgentOutput(msg) => write!(f, "Agent output error: {}", msg),
         }
@@ -108,6 +135,34 @@ mod tests {
         assert_eq!(Error::StoryNotFound("1".into()).code(), "STORY_NOT_FOUND");
     }
 
+    #[test]
+    fn merge_conflict_lists_files() {
+        let err = Error::MergeConflict {
+            branch: "main".into(),
+            files: vec!["a.rs".into(), "b.rs".into()],
+        };
+        assert_eq!(err.code(), "MERGE_CONFLICT");
+        assert_eq!(err.to_string(), "Conflicts with main in: a.rs, b.rs");
+    }
+
+    #[test]
+    fn already_running_names_the_change() {
+        let err = Error::AlreadyRunning {
+            change: "add-auth".into(),
+            pid: 42,
+        };
+        assert_eq!(err.code(), "ALREADY_RUNNING");
+        assert!(err.to_string().contains("pid 42"));
+        assert!(err.to_string().contains("'add-auth'"));
+    }
+
+    #[test]
+    fn agent_not_found_names_the_program() {
+        let err = Error::AgentNotFound("codex".into());
+        assert_eq!(err.code(), "AGENT_NOT_FOUND");
+        assert!(err.to_string().contains("'codex'"));
+    }
+
     #[test]
     fn io_error_converts() {
         let io_err = std::io::Error::new(std::io::ErrorKind::NotFound, "file not found");
@@ -3,26 +3,23 @@ use std::time::Duration;
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers, MouseEvent, MouseEventKind};
 
-use crate::app::{App, Screen};
+use crate::app::{App, ResultTab, Screen};
+use crate::checkpoint::CompletionOption;
 
 const POLL_TIMEOUT: Duration = Duration::from_millis(250);
 
 pub fn handle_events(app: &mut App) -> Result<()> {
     if event::poll(POLL_TIMEOUT)? {
         match event::read()? {
-            Event::Key(key_event) => {
-                if key_event.kind == KeyEventKind::Press {
-                    match app.screen {
-                        Screen::ChangeSelection => handle_selection_events(app, key_event.code)?,
-                        Screen::ConversionPreview => {
-                            handle_preview_events(app, key_event.code, key_event.modifiers)
-                        }
-                        Screen::LoopExecution => handle_loop_events(app, key_event.code),
-                        Screen::LoopCompletion => handle_completion_events(app, key_event.code),
-                        Screen::LoopResult => handle_result_events(app, key_event.code),
-                    }
+            Event::Key(key_event) if key_event.kind == KeyEventKind::Press => match app.screen {
+                Screen::ChangeSelection => handle_selection_events(app, key_event.code)?,
+                Screen::ConversionPreview => {
+                    handle_preview_events(app, key_event.code, key_event.modifiers)
                 }
-            }
+                Screen::LoopExecution => handle_loop_events(app, key_event.code),
+                Screen::LoopCompletion => handle_completion_events(app, key_event.code),
+                Screen::LoopResult => handle_result_events(app, key_event.code),
+            },
             Event::Mouse(mouse_event) => match app.screen {
                 Screen::ConversionPreview => handle_preview_mouse(app, mouse_event),
                 Screen::LoopExecution => handle_loop_mouse(app, mouse_event),
@@ -40,10 +37,8 @@ fn handle_selection_events(app: &mut App, code: KeyCode) -> Result<()> {
         KeyCode::Char('q') | KeyCode::Char('Q') => app.quit(),
         KeyCode::Up => app.select_previous(),
         KeyCode::Down => app.select_next(),
-        KeyCode::Enter => {
-            if !app.available_changes.is_empty() {
-                app.select_change(app.selected_index)?;
-            }
+        KeyCode::Enter if !app.available_changes.is_empty() => {
+            app.select_change(app.selected_index)?;
         }
         _ => {}
     }
@@ -51,9 +46,21 @@ fn handle_selection_events(app: &mut App, code: KeyCode) -> Result<()> {
 }
 
 fn handle_preview_events(app: &mut App, code: KeyCode, modifiers: KeyModifiers) {
+    // Pre-flight issues must be confirmed or aborted first
+    if app.preflight_issues.is_some() {
+        match code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => app.confirm_preflight(),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => app.abort_preflight(),
+            KeyCode::Char('q') | KeyCode::Char('Q') => app.quit(),
+            _ => {}
+        }
+        return;
+    }
+
     match code {
         KeyCode::Char('q') | KeyCode::Char('Q') => app.quit(),
-        KeyCode::Char('r') | KeyCode::Char('R') => app.start_loop(),
+        KeyCode::Char('r') | KeyCode::Char('R') => app.request_loop_start(false),
+        KeyCode::Char('n') | KeyCode::Char('N') => app.request_loop_start(true),
         KeyCode::Esc => app.back_to_selection(),
         KeyCode::Up => app.scroll_up(),
         KeyCode::Down => app.scroll_down(),
@@ -69,6 +76,17 @@ fn handle_preview_events(app: &mut App, code: KeyCode, modifiers: KeyModifiers)
 fn handle_loop_events(app: &mut App, code: KeyCode) {
     use crate::app::ForceQuitAction;
 
+    // Changes over the diff limits must be accepted or rejected first
+    if app.diff_approval.is_some() {
+        match code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => return app.answer_diff_approval(true),
+            KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Esc => {
+                return app.answer_diff_approval(false)
+            }
+            _ => {}
+        }
+    }
+
     match code {
         // 'q' handles force-quit mechanism with tracking of consecutive presses
         KeyCode::Char('q') | KeyCode::Char('Q') => {
@@ -112,6 +130,29 @@ fn handle_completion_events(app: &mut App, code: KeyCode) {
         return;
     }
 
+    // After a conflict only retry and keep are offered
+    if app.completion_data.conflict.is_some() {
+        match code {
+            KeyCode::Char('r') | KeyCode::Char('R') => {
+                app.retry_completion_choice();
+            }
+            KeyCode::Char('k') | KeyCode::Char('K') => {
+                app.completion_data.select_keep();
+                app.send_completion_choice();
+            }
+            KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                // The orchestrator is waiting for a choice; keep the branch
+                // so the loop thread can finish before it is joined
+                app.completion_data.select_keep();
+                app.send_completion_choice();
+                app.cleanup_loop();
+                app.back_to_selection();
+            }
+            _ => {}
+        }
+        return;
+    }
+
     match code {
         // Select cleanup option
         KeyCode::Char('c') | KeyCode::Char('C') => {
@@ -121,9 +162,24 @@ fn handle_completion_events(app: &mut App, code: KeyCode) {
         KeyCode::Char('k') | KeyCode::Char('K') => {
             app.completion_data.select_keep();
         }
-        // Toggle between options
-        KeyCode::Up | KeyCode::Down => {
-            app.completion_data.toggle_option();
+        // Select squash option
+        KeyCode::Char('s') | KeyCode::Char('S') => {
+            app.completion_data.select(CompletionOption::Squash);
+        }
+        // Select preserve-history option
+        KeyCode::Char('h') | KeyCode::Char('H') => {
+            app.completion_data.select(CompletionOption::PreserveHistory);
+        }
+        // Select export-patch option
+        KeyCode::Char('p') | KeyCode::Char('P') => {
+            app.completion_data.select(CompletionOption::ExportPatch);
+        }
+        // Move between options
+        KeyCode::Up => {
+            app.completion_data.select_previous();
+        }
+        KeyCode::Down => {
+            app.completion_data.select_next();
         }
         // Confirm selection - send choice to orchestrator for cleanup
         KeyCode::Enter => {
@@ -142,9 +198,24 @@ fn handle_completion_events(app: &mut App, code: KeyCode) {
 }
 
 fn handle_result_events(app: &mut App, code: KeyCode) {
+    // A pending rollback takes y to confirm; any other key cancels it
+    if app.result_rollback_confirm {
+        match code {
+            KeyCode::Char('y') | KeyCode::Char('Y') => app.confirm_rollback(),
+            _ => {
+                app.cancel_rollback();
+            }
+        }
+        return;
+    }
+
     match code {
         KeyCode::Char('q') | KeyCode::Char('Q') => app.quit(),
+        // Esc closes an open attempt diff before leaving the screen
+        KeyCode::Esc if app.close_attempt_diff() => {}
         KeyCode::Esc => app.back_to_selection(),
+        KeyCode::Enter if app.result_tab == ResultTab::Checkpoints => app.request_rollback(),
+        KeyCode::Enter => app.open_selected_attempt_diff(),
         KeyCode::Tab => app.switch_result_tab(),
         KeyCode::Up => app.result_scroll_up(),
         KeyCode::Down => app.result_scroll_down(),
@@ -398,10 +469,54 @@ mod tests {
         handle_result_events(&mut app, KeyCode::Tab);
         assert_eq!(app.result_tab, ResultTab::ChangedFiles);
 
+        handle_result_events(&mut app, KeyCode::Tab);
+        assert_eq!(app.result_tab, ResultTab::Checkpoints);
+
+        handle_result_events(&mut app, KeyCode::Tab);
+        assert_eq!(app.result_tab, ResultTab::Attempts);
+
         handle_result_events(&mut app, KeyCode::Tab);
         assert_eq!(app.result_tab, ResultTab::Tasks);
     }
 
+    #[test]
+    fn result_enter_on_checkpoints_asks_before_rollback() {
+        let mut app = App::new();
+        app.screen = Screen::LoopResult;
+        app.result_tab = ResultTab::Checkpoints;
+        app.loop_result.checkpoints = vec![crate::checkpoint::StoryCheckpoint {
+            commit: "abc".to_string(),
+            story_id: "1".to_string(),
+            subject: "checkpoint: 1".to_string(),
+            files_changed: 1,
+            insertions: 1,
+            deletions: 0,
+        }];
+
+        handle_result_events(&mut app, KeyCode::Enter);
+        assert!(app.result_rollback_confirm);
+        assert!(app.result_attempt_diff.is_none());
+
+        // Esc cancels the rollback without leaving the screen
+        handle_result_events(&mut app, KeyCode::Esc);
+        assert!(!app.result_rollback_confirm);
+        assert_eq!(app.screen, Screen::LoopResult);
+    }
+
+    #[test]
+    fn result_esc_closes_attempt_diff_before_leaving() {
+        let mut app = App::new();
+        app.screen = Screen::LoopResult;
+        app.result_attempt_diff = Some("diff".to_string());
+
+        handle_result_events(&mut app, KeyCode::Esc);
+        assert!(app.result_attempt_diff.is_none());
+        assert_eq!(app.screen, Screen::LoopResult);
+
+        handle_result_events(&mut app, KeyCode::Esc);
+        assert_eq!(app.screen, Screen::ChangeSelection);
+    }
+
     #[test]
     fn result_scroll_preserves_position_when_switching_tabs() {
         use crate::app::ResultTab;
@@ -419,7 +534,9 @@ mod tests {
         handle_result_events(&mut app, KeyCode::Down);
         assert_eq!(app.result_scroll_offset, 2);
 
-        // Switch back to Tasks
+        // Switch back to Tasks (via Checkpoints and Attempts)
+        handle_result_events(&mut app, KeyCode::Tab);
+        handle_result_events(&mut app, KeyCode::Tab);
         handle_result_events(&mut app, KeyCode::Tab);
         assert_eq!(app.result_tab, ResultTab::Tasks);
 
@@ -428,4 +545,63 @@ mod tests {
         // ChangedFiles scroll position should be preserved
         assert_eq!(app.result_scroll_offset, 2);
     }
+
+    #[test]
+    fn completion_keys_select_options() {
+        let mut app = App::new();
+
+        handle_completion_events(&mut app, KeyCode::Char('s'));
+        assert_eq!(app.completion_data.selected_completion_option(), CompletionOption::Squash);
+
+        handle_completion_events(&mut app, KeyCode::Char('h'));
+        assert_eq!(app.completion_data.selected_completion_option(), CompletionOption::PreserveHistory);
+
+        handle_completion_events(&mut app, KeyCode::Char('p'));
+        assert_eq!(app.completion_data.selected_completion_option(), CompletionOption::ExportPatch);
+
+        handle_completion_events(&mut app, KeyCode::Down);
+        assert_eq!(app.completion_data.selected_completion_option(), CompletionOption::Cleanup);
+
+        handle_completion_events(&mut app, KeyCode::Up);
+        assert_eq!(app.completion_data.selected_completion_option(), CompletionOption::ExportPatch);
+    }
+
+    #[test]
+    fn cancelling_a_conflict_keeps_the_branch() {
+        use crate::ui::CleanupConflict;
+
+        let mut app = App::new();
+        let (tx, mut rx) = tokio::sync::oneshot::channel();
+        app.completion_choice_tx = Some(tx);
+        app.completion_data.conflict = Some(CleanupConflict {
+            option: CompletionOption::Squash,
+            branch: "main".to_string(),
+            files: vec!["src/lib.rs".to_string()],
+        });
+
+        handle_completion_events(&mut app, KeyCode::Esc);
+
+        assert_eq!(rx.try_recv(), Ok(CompletionOption::Keep));
+        assert!(app.completion_choice_tx.is_none());
+    }
+
+    #[test]
+    fn preview_keys_cannot_confirm_blocking_preflight_issues() {
+        use crate::checkpoint::{PreflightCheck, PreflightIssue};
+
+        let mut app = App::new();
+        app.screen = Screen::ConversionPreview;
+        app.preflight_issues = Some(vec![PreflightIssue {
+            check: PreflightCheck::DetachedHead,
+            message: "HEAD is detached".to_string(),
+        }]);
+
+        handle_preview_events(&mut app, KeyCode::Char('y'), KeyModifiers::NONE);
+        assert!(app.preflight_issues.is_some());
+        assert_eq!(app.screen, Screen::ConversionPreview);
+
+        handle_preview_events(&mut app, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(app.preflight_issues.is_none());
+        assert_eq!(app.screen, Screen::ConversionPreview);
+    }
 }
@@ -0,0 +1,594 @@
+//! Headless execution of the Ralph Loop.
+//!
+//! Runs the orchestrator without the TUI and writes every `LoopEvent` as a
+//! single JSON line (NDJSON) on stdout, so the loop can be driven from CI or
+//! scripts. The completion choice is taken from the command line instead of
+//! the completion screen.
+
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use clap::ValueEnum;
+use serde_json::{json, Value};
+
+use crate::agent::{self, AgentConfig, StreamEvent};
+use crate::checkpoint::{self, CommitFormat, PreflightIssue, Severity};
+use crate::ralph_loop::{
+    CheckpointBackend, CompletionOption, DiffLimits, FailurePolicy, IntegrityAction, LoopEvent, LoopState, Orchestrator,
+    ScanConfig,
+};
+
+/// Final outcome of a headless run, mapped to the process exit code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunOutcome {
+    /// All stories completed.
+    Success,
+    /// The loop could not run or stopped on an error.
+    Error,
+    /// At least one story exceeded its retry budget.
+    MaxRetries,
+    /// The loop was stopped by the user (Ctrl-C).
+    Stopped,
+}
+
+impl RunOutcome {
+    /// Returns the process exit code for this outcome.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            RunOutcome::Success => 0,
+            RunOutcome::Error => 1,
+            RunOutcome::MaxRetries => 2,
+            RunOutcome::Stopped => 3,
+        }
+    }
+}
+
+/// Options for a headless run.
+#[derive(Debug, Clone)]
+pub struct HeadlessOptions {
+    /// Name of the change to run.
+    pub change_name: String,
+    /// Completion action applied when the loop finishes.
+    pub completion: CompletionOption,
+    /// Coding agent CLI that works on the stories.
+    pub agent_config: AgentConfig,
+    /// Maximum number of retries per story.
+    pub max_retries: usize,
+    /// Timeout in seconds for external commands.
+    pub command_timeout: u64,
+    /// Timeout in seconds for each verification command.
+    pub verify_timeout: u64,
+    /// Policy applied when a story exceeds max retries.
+    pub failure_policy: FailurePolicy,
+    /// Where the ralph branch is checked out while the loop runs.
+    pub checkpoint_backend: CheckpointBackend,
+    /// Directory for the `export-patch` completion option, if not the default.
+    pub patch_dir: Option<PathBuf>,
+    /// Subject format of checkpoint commits.
+    pub commit_format: CommitFormat,
+    /// Author identity of checkpoint commits, if not the git user.
+    pub commit_author: Option<String>,
+    /// Untracked files above this many MiB are reported by the pre-flight checks.
+    pub untracked_size_limit_mb: u64,
+    /// Scanner run before each checkpoint commit.
+    pub scan_config: ScanConfig,
+    /// Globs of paths an attempt must not change.
+    pub protected_paths: Vec<String>,
+    /// What to do when an attempt changes the plan beyond its own ticks.
+    pub integrity_action: IntegrityAction,
+    /// Limits on the size of a story's changes. There is nobody to approve
+    /// changes over the limits, so they always fail the attempt.
+    pub diff_limits: DiffLimits,
+    /// Whether to start despite pre-flight warnings.
+    pub accept_warnings: bool,
+    /// Whether to resume an interrupted run on the existing ralph branch.
+    pub resume: bool,
+}
+
+/// Runs the Ralph Loop headlessly, streaming events as NDJSON on stdout.
+pub fn run(options: HeadlessOptions) -> anyhow::Result<RunOutcome> {
+    let rt = tokio::runtime::Runtime::new()?;
+    rt.block_on(run_async(options))
+}
+
+async fn run_async(options: HeadlessOptions) -> anyhow::Result<RunOutcome> {
+    // There is nobody to ask, so warnings only pass with --accept-warnings
+    let checkpoint = checkpoint::create_checkpoint(
+        options.checkpoint_backend,
+        &options.change_name,
+        Duration::from_secs(options.command_timeout),
+    );
+    let issues = checkpoint
+        .preflight(options.untracked_size_limit_mb)
+        .await
+        .unwrap_or_else(|e| vec![PreflightIssue::check_failed(e)]);
+    let mut stdout = io::stdout();
+    for issue in &issues {
+        writeln!(stdout, "{}", preflight_issue_to_json(issue))?;
+    }
+    if PreflightIssue::any_blocking(&issues) || (!issues.is_empty() && !options.accept_warnings) {
+        return Ok(RunOutcome::Error);
+    }
+
+    let (tx, mut rx) = tokio::sync::mpsc::channel::<LoopEvent>(100);
+
+    let agent = agent::create_agent(&options.agent_config);
+    let mut orchestrator = Orchestrator::new(&options.change_name, agent, tx, options.max_retries)
+        .with_command_timeout(options.command_timeout)
+        .with_verify_timeout(options.verify_timeout)
+        .with_failure_policy(options.failure_policy)
+        .with_checkpoint_backend(options.checkpoint_backend)
+        .with_commit_format(options.commit_format)
+        .with_commit_author(options.commit_author)
+        .with_scan_config(options.scan_config)
+        .with_protected_paths(options.protected_paths)
+        .with_integrity_action(options.integrity_action)
+        .with_diff_limits(options.diff_limits)
+        .with_resume(options.resume);
+    if let Some(dir) = options.patch_dir {
+        orchestrator = orchestrator.with_patch_dir(dir);
+    }
+
+    // Ctrl-C requests a graceful stop, same as the first 'q' press in the TUI
+    let stop_token = orchestrator.stop_handle();
+    let signal_token = stop_token.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            signal_token.cancel();
+        }
+    });
+
+    // The orchestrator is moved into its future so the event channel closes
+    // as soon as the run returns, even when it bails out with an error.
+    let run = async move { orchestrator.run().await };
+
+    let consume = async {
+        let mut max_retries_exceeded = false;
+        let mut cleanup_conflict = false;
+        let mut stdout = io::stdout();
+
+        while let Some(event) = rx.recv().await {
+            if matches!(event, LoopEvent::MaxRetriesExceeded { .. }) {
+                max_retries_exceeded = true;
+            }
+
+            writeln!(stdout, "{}", event_to_json(&event))?;
+            stdout.flush()?;
+
+            match event {
+                LoopEvent::CleanupConflict { .. } => cleanup_conflict = true,
+                LoopEvent::AwaitingDiffApproval { approve_tx, .. } => {
+                    // There is nobody to approve oversized changes
+                    let _ = approve_tx.send(false);
+                }
+                LoopEvent::AwaitingUserChoice { choice_tx } => {
+                    // There is nobody to resolve a conflict, so keep the branch
+                    let choice = if cleanup_conflict {
+                        CompletionOption::Keep
+                    } else {
+                        options.completion
+                    };
+                    let _ = choice_tx.send(choice);
+                }
+                _ => {}
+            }
+        }
+
+        Ok::<bool, io::Error>(max_retries_exceeded)
+    };
+
+    let (result, consumed) = tokio::join!(run, consume);
+    let max_retries_exceeded = consumed?;
+    let mut stdout = io::stdout();
+
+    let outcome = match result {
+        Ok(state) => determine_outcome(&state, max_retries_exceeded, stop_token.is_cancelled()),
+        Err(e) => {
+            writeln!(
+                stdout,
+                "{}",
+                json!({ "event": "error", "message": e.to_string() })
+            )?;
+            RunOutcome::Error
+        }
+    };
+
+    Ok(outcome)
+}
+
+/// Determines the run outcome from the final loop state.
+fn determine_outcome(state: &LoopState, max_retries_exceeded: bool, stopped: bool) -> RunOutcome {
+    if max_retries_exceeded {
+        RunOutcome::MaxRetries
+    } else if stopped {
+        RunOutcome::Stopped
+    } else if state.total_stories > 0
+        && state.current_story_id.is_none()
+        && state.completed_stories == state.total_stories
+    {
+        RunOutcome::Success
+    } else {
+        RunOutcome::Error
+    }
+}
+
+/// Serializes a loop event as a JSON object.
+fn event_to_json(event: &LoopEvent) -> Value {
+    match event {
+        LoopEvent::StoryProgress {
+            story_id,
+            story_title,
+            current,
+            total,
+            completed,
+        } => json!({
+            "event": "story_progress",
+            "story_id": story_id,
+            "story_title": story_title,
+            "current": current,
+            "total": total,
+            "completed": completed,
+        }),
+        LoopEvent::StoryEvent { story_id, event } => {
+            let mut value = stream_event_to_json(event);
+            value["event"] = json!("story_event");
+            value["story_id"] = json!(story_id);
+            value
+        }
+        LoopEvent::Verification { story_id, result } => json!({
+            "event": "verification",
+            "story_id": story_id,
+            "command": result.command,
+            "passed": result.passed,
+            "output": result.output,
+        }),
+        LoopEvent::SignalStateMismatch {
+            story_id,
+            unchecked_tasks,
+        } => json!({
+            "event": "signal_state_mismatch",
+            "story_id": story_id,
+            "unchecked_tasks": unchecked_tasks,
+        }),
+        LoopEvent::PlanChanged {
+            story_id,
+            changes,
+            action,
+        } => json!({
+            "event": "plan_changed",
+            "story_id": story_id,
+            "action": action.to_possible_value().map(|v| v.get_name().to_string()),
+            "changes": changes
+                .iter()
+                .map(|change| json!({
+                    "kind": change.code(),
+                    "message": change.describe(),
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        LoopEvent::DiffLimitExceeded {
+            story_id,
+            stat,
+            exceeded,
+            action,
+        } => json!({
+            "event": "diff_limit_exceeded",
+            "story_id": story_id,
+            "action": action.to_possible_value().map(|v| v.get_name().to_string()),
+            "files_changed": stat.files_changed,
+            "insertions": stat.insertions,
+            "deletions": stat.deletions,
+            "exceeded": exceeded,
+        }),
+        LoopEvent::AwaitingDiffApproval { story_id, .. } => json!({
+            "event": "awaiting_diff_approval",
+            "story_id": story_id,
+        }),
+        LoopEvent::ScanFindings {
+            story_id,
+            findings,
+            action,
+        } => json!({
+            "event": "scan_findings",
+            "story_id": story_id,
+            "action": action.to_possible_value().map(|v| v.get_name().to_string()),
+            "findings": findings
+                .iter()
+                .map(|finding| json!({
+                    "path": finding.path,
+                    "kind": finding.kind.code(),
+                    "message": finding.describe(),
+                }))
+                .collect::<Vec<_>>(),
+        }),
+        LoopEvent::Reverted {
+            story_id,
+            restored,
+            removed,
+            archive_ref,
+        } => json!({
+            "event": "reverted",
+            "story_id": story_id,
+            "restored": restored,
+            "removed": removed,
+            "archive_ref": archive_ref,
+        }),
+        LoopEvent::PatchesExported { dir, patches } => json!({
+            "event": "patches_exported",
+            "dir": dir,
+            "patches": patches,
+        }),
+        LoopEvent::CleanupConflict {
+            option,
+            branch,
+            files,
+        } => json!({
+            "event": "cleanup_conflict",
+            "option": option.to_possible_value().map(|v| v.get_name().to_string()),
+            "branch": branch,
+            "files": files,
+        }),
+        LoopEvent::Error { message } => json!({
+            "event": "error",
+            "message": message,
+        }),
+        LoopEvent::MaxRetriesExceeded { story_id } => json!({
+            "event": "max_retries_exceeded",
+            "story_id": story_id,
+        }),
+        LoopEvent::AwaitingUserChoice { .. } => json!({
+            "event": "awaiting_user_choice",
+        }),
+        LoopEvent::Complete => json!({
+            "event": "complete",
+        }),
+    }
+}
+
+/// Serializes a pre-flight issue as a JSON object.
+fn preflight_issue_to_json(issue: &PreflightIssue) -> Value {
+    json!({
+        "event": "preflight",
+        "check": issue.check.code(),
+        "severity": match issue.severity() {
+            Severity::Error => "error",
+            Severity::Warning => "warning",
+        },
+        "message": issue.message,
+    })
+}
+
+/// Serializes an agent stream event as a JSON object.
+fn stream_event_to_json(event: &StreamEvent) -> Value {
+    match event {
+        StreamEvent::Message(text) => json!({
+            "type": "message",
+            "text": text,
+        }),
+        StreamEvent::Thinking(text) => json!({
+            "type": "thinking",
+            "text": text,
+        }),
+        StreamEvent::ToolUse { name, input } => json!({
+            "type": "tool_use",
+            "name": name,
+            "input": input,
+        }),
+        StreamEvent::ToolResult { is_error, summary } => json!({
+            "type": "tool_result",
+            "is_error": is_error,
+            "summary": summary,
+        }),
+        StreamEvent::Done(response) => json!({
+            "type": "done",
+            "content": response.content,
+            "turns": response.turns,
+            "tokens": response.tokens,
+            "cost": response.cost,
+        }),
+        StreamEvent::Exited(exit) => json!({
+            "type": "exited",
+            "code": exit.code,
+            "stderr": exit.stderr,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agent::{AgentExit, Response};
+    use crate::checkpoint::PreflightCheck;
+
+    fn finished_state(completed: usize, total: usize) -> LoopState {
+        let mut state = LoopState::new("test-change");
+        state.total_stories = total;
+        state.completed_stories = completed;
+        state
+    }
+
+    #[test]
+    fn exit_codes_are_distinct() {
+        assert_eq!(RunOutcome::Success.exit_code(), 0);
+        assert_eq!(RunOutcome::Error.exit_code(), 1);
+        assert_eq!(RunOutcome::MaxRetries.exit_code(), 2);
+        assert_eq!(RunOutcome::Stopped.exit_code(), 3);
+    }
+
+    #[test]
+    fn outcome_success_when_all_stories_complete() {
+        let state = finished_state(3, 3);
+        assert_eq!(determine_outcome(&state, false, false), RunOutcome::Success);
+    }
+
+    #[test]
+    fn outcome_max_retries_takes_precedence() {
+        let state = finished_state(1, 3);
+        assert_eq!(determine_outcome(&state, true, true), RunOutcome::MaxRetries);
+    }
+
+    #[test]
+    fn outcome_stopped_when_stop_flag_set() {
+        let mut state = finished_state(1, 3);
+        state.current_story_id = Some("2".to_string());
+        assert_eq!(determine_outcome(&state, false, true), RunOutcome::Stopped);
+    }
+
+    #[test]
+    fn outcome_error_when_loop_never_ran() {
+        let state = finished_state(0, 0);
+        assert_eq!(determine_outcome(&state, false, false), RunOutcome::Error);
+    }
+
+    #[test]
+    fn story_progress_serializes_all_fields() {
+        let value = event_to_json(&LoopEvent::StoryProgress {
+            story_id: "1".to_string(),
+            story_title: "First".to_string(),
+            current: 1,
+            total: 2,
+            completed: 0,
+        });
+        assert_eq!(value["event"], "story_progress");
+        assert_eq!(value["story_id"], "1");
+        assert_eq!(value["story_title"], "First");
+        assert_eq!(value["total"], 2);
+    }
+
+    #[test]
+    fn story_event_serializes_done_response() {
+        let value = event_to_json(&LoopEvent::StoryEvent {
+            story_id: "1".to_string(),
+            event: StreamEvent::Done(Response {
+                content: "ok".to_string(),
+                turns: 3,
+                tokens: 150,
+                cost: 0.05,
+            }),
+        });
+        assert_eq!(value["event"], "story_event");
+        assert_eq!(value["story_id"], "1");
+        assert_eq!(value["type"], "done");
+        assert_eq!(value["turns"], 3);
+        assert_eq!(value["tokens"], 150);
+    }
+
+    #[test]
+    fn cleanup_conflict_serializes_option_and_files() {
+        let value = event_to_json(&LoopEvent::CleanupConflict {
+            option: CompletionOption::PreserveHistory,
+            branch: "main".to_string(),
+            files: vec!["src/lib.rs".to_string()],
+        });
+        assert_eq!(value["event"], "cleanup_conflict");
+        assert_eq!(value["option"], "preserve-history");
+        assert_eq!(value["branch"], "main");
+        assert_eq!(value["files"][0], "src/lib.rs");
+    }
+
+    #[test]
+    fn scan_findings_serialize_kind_and_action() {
+        use crate::ralph_loop::scan::{FindingKind, ScanAction, ScanFinding};
+
+        let value = event_to_json(&LoopEvent::ScanFindings {
+            story_id: "2".to_string(),
+            findings: vec![ScanFinding {
+                path: ".env".to_string(),
+                kind: FindingKind::DeniedPath {
+                    glob: ".env".to_string(),
+                },
+            }],
+            action: ScanAction::Unstage,
+        });
+        assert_eq!(value["event"], "scan_findings");
+        assert_eq!(value["action"], "unstage");
+        assert_eq!(value["findings"][0]["path"], ".env");
+        assert_eq!(value["findings"][0]["kind"], "denied_path");
+    }
+
+    #[test]
+    fn plan_changed_serializes_changes_and_action() {
+        use crate::ralph_loop::PlanChange;
+
+        let value = event_to_json(&LoopEvent::PlanChanged {
+            story_id: "2".to_string(),
+            changes: vec![PlanChange::TaskRemoved {
+                task_id: "2.2".to_string(),
+            }],
+            action: IntegrityAction::Repair,
+        });
+        assert_eq!(value["event"], "plan_changed");
+        assert_eq!(value["action"], "repair");
+        assert_eq!(value["changes"][0]["kind"], "task_removed");
+        assert_eq!(value["changes"][0]["message"], "task 2.2 was removed");
+    }
+
+    #[test]
+    fn diff_limit_exceeded_serializes_stat() {
+        use crate::ralph_loop::{DiffLimitAction, DiffStat};
+
+        let value = event_to_json(&LoopEvent::DiffLimitExceeded {
+            story_id: "3".to_string(),
+            stat: DiffStat {
+                files_changed: 42,
+                insertions: 900,
+                deletions: 10,
+            },
+            exceeded: vec!["42 files changed (limit 10)".to_string()],
+            action: DiffLimitAction::Fail,
+        });
+        assert_eq!(value["event"], "diff_limit_exceeded");
+        assert_eq!(value["action"], "fail");
+        assert_eq!(value["files_changed"], 42);
+        assert_eq!(value["exceeded"][0], "42 files changed (limit 10)");
+    }
+
+    #[test]
+    fn preflight_issue_serializes_check_and_severity() {
+        let issue = PreflightIssue {
+            check: PreflightCheck::LargeUntrackedFile,
+            message: "Untracked file big.bin is 12.0 MiB and would be committed".to_string(),
+        };
+        let value = preflight_issue_to_json(&issue);
+        assert_eq!(value["event"], "preflight");
+        assert_eq!(value["check"], "large_untracked_file");
+        assert_eq!(value["severity"], "warning");
+    }
+
+    #[test]
+    fn tool_events_serialize_name_input_and_summary() {
+        let value = stream_event_to_json(&StreamEvent::ToolUse {
+            name: "Bash".to_string(),
+            input: json!({ "command": "cargo test" }),
+        });
+        assert_eq!(value["type"], "tool_use");
+        assert_eq!(value["name"], "Bash");
+        assert_eq!(value["input"]["command"], "cargo test");
+
+        let value = stream_event_to_json(&StreamEvent::tool_result(true, "error: failed"));
+        assert_eq!(value["type"], "tool_result");
+        assert_eq!(value["is_error"], true);
+        assert_eq!(value["summary"], "error: failed");
+    }
+
+    #[test]
+    fn exit_event_serializes_code_and_stderr() {
+        let value = stream_event_to_json(&StreamEvent::Exited(AgentExit {
+            code: None,
+            stderr: "Killed\n".to_string(),
+        }));
+        assert_eq!(value["type"], "exited");
+        assert!(value["code"].is_null());
+        assert_eq!(value["stderr"], "Killed\n");
+    }
+
+    #[test]
+    fn event_json_is_single_line() {
+        let value = event_to_json(&LoopEvent::StoryEvent {
+            story_id: "1".to_string(),
+            event: StreamEvent::Message("line one\nline two".to_string()),
+        });
+        assert!(!value.to_string().contains('\n'));
+    }
+}
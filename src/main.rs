@@ -5,14 +5,16 @@ mod ralph_loop;
 mod app;
 mod error;
 mod event;
+mod headless;
 mod spec;
 mod ui;
 
 use std::io;
+use std::path::PathBuf;
 use std::panic;
 
 use anyhow::Result;
-use clap::Parser;
+use clap::{Parser, Subcommand};
 use crossterm::{
     event::{DisableMouseCapture, EnableMouseCapture},
     execute,
@@ -20,9 +22,14 @@ use crossterm::{
 };
 use ratatui::prelude::*;
 
+use agent::{AgentBackend, AgentConfig, CommandConfig};
 use app::{App, Screen};
 use event::handle_events;
-use ralph_loop::{DEFAULT_MAX_RETRIES, DEFAULT_COMMAND_TIMEOUT_SECS};
+use headless::HeadlessOptions;
+use checkpoint::{parse_author, CommitFormat, DEFAULT_UNTRACKED_SIZE_LIMIT_MB};
+use ralph_loop::verify::DEFAULT_VERIFY_TIMEOUT_SECS;
+use ralph_loop::scan::DEFAULT_SCAN_MAX_FILE_SIZE_MB;
+use ralph_loop::{CheckpointBackend, CompletionOption, DiffLimitAction, DiffLimits, FailurePolicy, IntegrityAction, ScanAction, ScanConfig, DEFAULT_MAX_RETRIES, DEFAULT_COMMAND_TIMEOUT_SECS};
 use ui::render;
 
 /// Ralph Loop - Autonomous AI development orchestrator
@@ -30,21 +37,155 @@ use ui::render;
 #[command(name = "ralphtool")]
 #[command(about = "TUI for running the Ralph Loop with OpenSpec changes")]
 struct Cli {
+    /// Coding agent CLI that works on the stories: claude, codex or command
+    /// (any CLI, described by --agent-config)
+    #[arg(long, global = true, value_enum, default_value_t = AgentBackend::Claude)]
+    agent: AgentBackend,
+
+    /// JSON file describing the command agent: program, args template with
+    /// {user_prompt}/{system_prompt}, prompt input and output format
+    #[arg(long, global = true, value_name = "FILE")]
+    agent_config: Option<PathBuf>,
+
     /// Maximum number of retries per story when agent fails
-    #[arg(long, default_value_t = DEFAULT_MAX_RETRIES)]
+    #[arg(long, global = true, default_value_t = DEFAULT_MAX_RETRIES)]
     max_retries: usize,
 
     /// Timeout in seconds for external commands (git, openspec)
-    #[arg(long, default_value_t = DEFAULT_COMMAND_TIMEOUT_SECS)]
+    #[arg(long, global = true, default_value_t = DEFAULT_COMMAND_TIMEOUT_SECS)]
     command_timeout: u64,
+
+    /// Timeout in seconds for each verification command run after a story completes
+    #[arg(long, global = true, default_value_t = DEFAULT_VERIFY_TIMEOUT_SECS)]
+    verify_timeout: u64,
+
+    /// What to do when a story exceeds max retries: abort, skip, or stop-after-<N>
+    #[arg(long, global = true, default_value_t = FailurePolicy::Abort)]
+    failure_policy: FailurePolicy,
+
+    /// Where to check out the ralph branch: in-place (switch this checkout),
+    /// worktree (a separate git worktree, leaving this checkout untouched) or
+    /// snapshot (copies of the project tree; chosen automatically without git)
+    #[arg(long, global = true, value_enum, default_value_t = CheckpointBackend::InPlace)]
+    checkpoint_backend: CheckpointBackend,
+
+    /// Directory for the export-patch completion option
+    /// [default: /tmp/ralphtool/<change>-patches]
+    #[arg(long, global = true)]
+    patch_dir: Option<PathBuf>,
+
+    /// Subject format of checkpoint commits: plain (checkpoint: <story>) or
+    /// conventional (feat(<change>): <story title>)
+    #[arg(long, global = true, value_enum, default_value_t = CommitFormat::Plain)]
+    commit_format: CommitFormat,
+
+    /// Author of checkpoint commits as "Name <email>" [default: the git user]
+    #[arg(long, global = true, value_parser = parse_author)]
+    commit_author: Option<String>,
+
+    /// Size in MiB above which untracked files are reported before the loop starts
+    #[arg(long, global = true, default_value_t = DEFAULT_UNTRACKED_SIZE_LIMIT_MB)]
+    untracked_size_limit: u64,
+
+    /// What to do when the pre-commit scan finds secrets, denied or oversized
+    /// files: unstage (leave them out of the checkpoint), fail (retry the story) or off
+    #[arg(long, global = true, value_enum, default_value_t = ScanAction::Unstage)]
+    scan_action: ScanAction,
+
+    /// File glob that must never be committed, on top of .env files and keys (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    scan_deny: Vec<String>,
+
+    /// Regex marking a secret in committed files, on top of the built-in patterns (repeatable)
+    #[arg(long, global = true, value_name = "REGEX")]
+    scan_secret: Vec<String>,
+
+    /// Size in MiB above which files are not committed in checkpoints
+    #[arg(long, global = true, default_value_t = DEFAULT_SCAN_MAX_FILE_SIZE_MB)]
+    scan_max_file_size: u64,
+
+    /// File glob the agent must not change, e.g. 'Cargo.lock' or
+    /// '.github/workflows/*'; a violation fails the attempt (repeatable)
+    #[arg(long, global = true, value_name = "GLOB")]
+    protect: Vec<String>,
+
+    /// What to do when the agent changes tasks.md beyond ticking the current
+    /// story's tasks: reject (retry the story), repair (restore the plan) or off
+    #[arg(long, global = true, value_enum, default_value_t = IntegrityAction::Reject)]
+    plan_integrity: IntegrityAction,
+
+    /// Most files a story may change before the diff limit applies
+    #[arg(long, global = true, value_name = "N")]
+    max_files_changed: Option<usize>,
+
+    /// Most lines a story may add before the diff limit applies
+    #[arg(long, global = true, value_name = "N")]
+    max_lines_added: Option<usize>,
+
+    /// Most lines a story may remove before the diff limit applies
+    #[arg(long, global = true, value_name = "N")]
+    max_lines_removed: Option<usize>,
+
+    /// What to do when a story exceeds a diff limit: fail (retry the story) or
+    /// ask (pause for approval in the TUI; the run subcommand always fails)
+    #[arg(long, global = true, value_enum, default_value_t = DiffLimitAction::Fail)]
+    diff_limit_action: DiffLimitAction,
+
+    #[command(subcommand)]
+    command: Option<Commands>,
 }
 
-fn main() -> Result<()> {
-    let cli = Cli::parse();
-    run_tui(cli.max_retries, cli.command_timeout)
+impl Cli {
+    /// Resolves the agent flags, reading the command agent's config file.
+    fn agent_config(&self) -> Result<AgentConfig> {
+        let command = self.agent_config.as_deref().map(CommandConfig::load).transpose()?;
+        Ok(AgentConfig::new(self.agent, command)?)
+    }
+
+    /// Collects the diff limit flags.
+    fn diff_limits(&self) -> DiffLimits {
+        DiffLimits {
+            max_files: self.max_files_changed,
+            max_insertions: self.max_lines_added,
+            max_deletions: self.max_lines_removed,
+            action: self.diff_limit_action,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug)]
+enum Commands {
+    /// Run the loop for a change without the TUI, printing events as NDJSON
+    Run {
+        /// Name of the OpenSpec change to run
+        change: String,
+
+        /// What to do with the ralph branch when the loop finishes
+        #[arg(long, value_enum, default_value_t = CompletionOption::Keep)]
+        completion: CompletionOption,
+
+        /// Resume an interrupted run on the existing ralph branch instead of starting over
+        #[arg(long)]
+        resume: bool,
+
+        /// Start even if the pre-flight checks report warnings
+        #[arg(long)]
+        accept_warnings: bool,
+    },
 }
 
-fn run_tui(max_retries: usize, command_timeout: u64) -> Result<()> {
+fn main() -> Result<()> {
+    let mut cli = Cli::parse();
+
+    // Without a git repository only the snapshot backend works
+    cli.checkpoint_backend = cli.checkpoint_backend.resolve(&std::env::current_dir()?);
+
+    let scan_config = ScanConfig::default()
+        .with_action(cli.scan_action)
+        .with_denied_globs(&cli.scan_deny)
+        .with_secret_patterns(&cli.scan_secret)?
+        .with_max_file_size_mb(cli.scan_max_file_size);
+
     // Check if openspec CLI is available
     if let Err(e) = check_openspec_cli() {
         eprintln!("Error: {}", e);
@@ -52,13 +193,62 @@ fn run_tui(max_retries: usize, command_timeout: u64) -> Result<()> {
         std::process::exit(1);
     }
 
+    let diff_limits = cli.diff_limits();
+    let agent_config = cli.agent_config()?;
+
+    match cli.command {
+        Some(Commands::Run {
+            change,
+            completion,
+            resume,
+            accept_warnings,
+        }) => {
+            let outcome = headless::run(HeadlessOptions {
+                change_name: change,
+                completion,
+                agent_config,
+                max_retries: cli.max_retries,
+                command_timeout: cli.command_timeout,
+                verify_timeout: cli.verify_timeout,
+                failure_policy: cli.failure_policy,
+                checkpoint_backend: cli.checkpoint_backend,
+                patch_dir: cli.patch_dir,
+                commit_format: cli.commit_format,
+                commit_author: cli.commit_author,
+                untracked_size_limit_mb: cli.untracked_size_limit,
+                scan_config,
+                protected_paths: cli.protect,
+                integrity_action: cli.plan_integrity,
+                diff_limits,
+                accept_warnings,
+                resume,
+            })?;
+            std::process::exit(outcome.exit_code());
+        }
+        None => run_tui(cli, agent_config, scan_config, diff_limits),
+    }
+}
+
+fn run_tui(cli: Cli, agent_config: AgentConfig, scan_config: ScanConfig, diff_limits: DiffLimits) -> Result<()> {
     install_panic_hook();
 
     let mut terminal = init_terminal()?;
 
     let mut app = App::new()
-        .with_max_retries(max_retries)
-        .with_command_timeout(command_timeout);
+        .with_agent_config(agent_config)
+        .with_max_retries(cli.max_retries)
+        .with_command_timeout(cli.command_timeout)
+        .with_verify_timeout(cli.verify_timeout)
+        .with_failure_policy(cli.failure_policy)
+        .with_checkpoint_backend(cli.checkpoint_backend)
+        .with_patch_dir(cli.patch_dir)
+        .with_commit_format(cli.commit_format)
+        .with_commit_author(cli.commit_author)
+        .with_untracked_size_limit(cli.untracked_size_limit)
+        .with_scan_config(scan_config)
+        .with_protected_paths(cli.protect)
+        .with_integrity_action(cli.plan_integrity)
+        .with_diff_limits(diff_limits);
 
     // Load available changes on startup
     if let Err(e) = app.load_changes() {
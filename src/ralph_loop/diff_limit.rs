@@ -0,0 +1,103 @@
+//! Per-story limits on the size of an attempt's changes.
+//!
+//! A runaway agent can rewrite half the codebase for a two-task story. Before
+//! a story is committed, the orchestrator measures the attempt's changes
+//! against the last checkpoint and compares them with the configured limits
+//! on files changed and lines added or removed. Depending on the
+//! `DiffLimitAction`, exceeding a limit fails the attempt or asks the user
+//! to approve the changes.
+
+use crate::checkpoint::DiffStat;
+
+/// What to do when an attempt exceeds a diff limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum DiffLimitAction {
+    /// Treat the oversized attempt as failed; the retry asks for smaller changes.
+    #[default]
+    Fail,
+    /// Pause the loop and ask the user to accept or reject the changes.
+    Ask,
+}
+
+/// Limits on the changes of a single story. `None` means unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DiffLimits {
+    /// Maximum number of files added, modified or deleted.
+    pub max_files: Option<usize>,
+    /// Maximum number of lines added.
+    pub max_insertions: Option<usize>,
+    /// Maximum number of lines removed.
+    pub max_deletions: Option<usize>,
+    /// What to do when a limit is exceeded.
+    pub action: DiffLimitAction,
+}
+
+impl DiffLimits {
+    /// Returns true if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_files.is_none() && self.max_insertions.is_none() && self.max_deletions.is_none()
+    }
+
+    /// Describes each limit `stat` exceeds, e.g. "42 files changed (limit 20)".
+    pub fn exceeded(&self, stat: &DiffStat) -> Vec<String> {
+        [
+            (stat.files_changed, self.max_files, "files changed"),
+            (stat.insertions, self.max_insertions, "lines added"),
+            (stat.deletions, self.max_deletions, "lines removed"),
+        ]
+        .into_iter()
+        .filter_map(|(count, limit, label)| {
+            let limit = limit?;
+            (count > limit).then(|| format!("{} {} (limit {})", count, label, limit))
+        })
+        .collect()
+    }
+}
+
+/// Builds the retry reason asking the agent to keep its changes small.
+pub fn retry_reason(exceeded: &[String]) -> String {
+    super::list_retry_reason(
+        "Your changes were far larger than this story allows:",
+        exceeded,
+        "Implement only what the story's tasks require; do not rewrite, reformat or move unrelated code.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stat(files_changed: usize, insertions: usize, deletions: usize) -> DiffStat {
+        DiffStat {
+            files_changed,
+            insertions,
+            deletions,
+        }
+    }
+
+    #[test]
+    fn default_limits_are_unlimited() {
+        let limits = DiffLimits::default();
+        assert!(limits.is_unlimited());
+        assert!(limits.exceeded(&stat(1000, 100_000, 100_000)).is_empty());
+    }
+
+    #[test]
+    fn exceeded_lists_each_limit_over() {
+        let limits = DiffLimits {
+            max_files: Some(10),
+            max_insertions: Some(500),
+            max_deletions: Some(200),
+            ..DiffLimits::default()
+        };
+
+        assert!(limits.exceeded(&stat(10, 500, 200)).is_empty());
+        assert_eq!(
+            limits.exceeded(&stat(42, 100, 900)),
+            vec![
+                "42 files changed (limit 10)".to_string(),
+                "900 lines removed (limit 200)".to_string(),
+            ]
+        );
+    }
+}
@@ -0,0 +1,375 @@
+//! Integrity guard for the story plan in tasks.md.
+//!
+//! Agents mark tasks done by editing tasks.md themselves, so an attempt can
+//! just as well delete tasks, rename stories or tick tasks of other stories.
+//! The orchestrator snapshots the plan before each attempt and compares it
+//! with the plan the agent left behind. The only allowed change is ticking
+//! an open task of the current story; anything else is reported as a
+//! `PlanChange` and, depending on the `IntegrityAction`, fails the attempt or
+//! is undone by restoring the snapshot with just the allowed ticks applied.
+
+use std::fs;
+use std::path::PathBuf;
+
+use crate::error::Result;
+use crate::spec::{SpecAdapter, Story};
+
+/// What to do when an attempt changed the plan beyond ticking its own tasks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum IntegrityAction {
+    /// Reject the attempt; reverting it also restores the plan.
+    #[default]
+    Reject,
+    /// Restore the plan and keep only the current story's ticked tasks.
+    Repair,
+    /// Do not check the plan.
+    Off,
+}
+
+/// A change to the plan that an attempt is not allowed to make.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanChange {
+    /// A story header was deleted.
+    StoryRemoved { story_id: String },
+    /// A story header was added.
+    StoryAdded { story_id: String },
+    /// A story's title was changed.
+    StoryRenamed { story_id: String, title: String },
+    /// The stories are in a different order.
+    StoriesReordered,
+    /// A task was deleted (or moved to another story).
+    TaskRemoved { task_id: String },
+    /// A task was added.
+    TaskAdded { task_id: String },
+    /// A task's description was changed.
+    TaskEdited { task_id: String },
+    /// A completed task was unchecked.
+    TaskUnchecked { task_id: String },
+    /// A task of another story was ticked.
+    ForeignTaskChecked { task_id: String },
+}
+
+impl PlanChange {
+    /// Machine-readable name of the change.
+    pub fn code(&self) -> &'static str {
+        match self {
+            PlanChange::StoryRemoved { .. } => "story_removed",
+            PlanChange::StoryAdded { .. } => "story_added",
+            PlanChange::StoryRenamed { .. } => "story_renamed",
+            PlanChange::StoriesReordered => "stories_reordered",
+            PlanChange::TaskRemoved { .. } => "task_removed",
+            PlanChange::TaskAdded { .. } => "task_added",
+            PlanChange::TaskEdited { .. } => "task_edited",
+            PlanChange::TaskUnchecked { .. } => "task_unchecked",
+            PlanChange::ForeignTaskChecked { .. } => "foreign_task_checked",
+        }
+    }
+
+    /// Human-readable description, e.g. "task 2.1 was removed".
+    pub fn describe(&self) -> String {
+        match self {
+            PlanChange::StoryRemoved { story_id } => format!("story {} was removed", story_id),
+            PlanChange::StoryAdded { story_id } => format!("story {} was added", story_id),
+            PlanChange::StoryRenamed { story_id, title } => {
+                format!("story {} was renamed to \"{}\"", story_id, title)
+            }
+            PlanChange::StoriesReordered => "stories were reordered".to_string(),
+            PlanChange::TaskRemoved { task_id } => format!("task {} was removed", task_id),
+            PlanChange::TaskAdded { task_id } => format!("task {} was added", task_id),
+            PlanChange::TaskEdited { task_id } => format!("task {} was reworded", task_id),
+            PlanChange::TaskUnchecked { task_id } => format!("task {} was unchecked", task_id),
+            PlanChange::ForeignTaskChecked { task_id } => {
+                format!("task {} of another story was checked", task_id)
+            }
+        }
+    }
+}
+
+/// The plan as it was before an attempt.
+#[derive(Debug, Clone)]
+pub struct PlanSnapshot {
+    /// Path of the tasks file.
+    pub path: PathBuf,
+    /// Raw content of the tasks file, restored on repair.
+    pub content: String,
+    /// Parsed stories.
+    pub stories: Vec<Story>,
+}
+
+impl PlanSnapshot {
+    /// Captures the adapter's stories and the tasks file they were parsed from.
+    pub fn capture(adapter: &dyn SpecAdapter) -> Result<Self> {
+        let path = adapter.tasks_file();
+        let content = if path.exists() {
+            fs::read_to_string(&path)?
+        } else {
+            String::new()
+        };
+        Ok(Self {
+            path,
+            content,
+            stories: adapter.stories()?,
+        })
+    }
+
+    /// Rewrites the tasks file to the snapshot with `ticked` tasks of the
+    /// story marked done, and returns the resulting stories.
+    pub fn repair(&self, story_id: &str, ticked: &[String]) -> Result<Vec<Story>> {
+        fs::write(&self.path, tick_tasks(&self.content, story_id, ticked))?;
+        Ok(apply_ticks(&self.stories, story_id, ticked))
+    }
+}
+
+/// Returns the changes from `before` to `after` other than ticking open
+/// tasks of the story `story_id`, in plan order.
+pub fn disallowed_changes(before: &[Story], after: &[Story], story_id: &str) -> Vec<PlanChange> {
+    let mut changes = Vec::new();
+
+    for old in before {
+        let Some(new) = after.iter().find(|s| s.id == old.id) else {
+            changes.push(PlanChange::StoryRemoved {
+                story_id: old.id.clone(),
+            });
+            continue;
+        };
+        if new.title != old.title {
+            changes.push(PlanChange::StoryRenamed {
+                story_id: old.id.clone(),
+                title: new.title.clone(),
+            });
+        }
+
+        for old_task in &old.tasks {
+            let Some(new_task) = new.tasks.iter().find(|t| t.id == old_task.id) else {
+                changes.push(PlanChange::TaskRemoved {
+                    task_id: old_task.id.clone(),
+                });
+                continue;
+            };
+            if new_task.description != old_task.description {
+                changes.push(PlanChange::TaskEdited {
+                    task_id: old_task.id.clone(),
+                });
+            }
+            if old_task.done && !new_task.done {
+                changes.push(PlanChange::TaskUnchecked {
+                    task_id: old_task.id.clone(),
+                });
+            } else if !old_task.done && new_task.done && old.id != story_id {
+                changes.push(PlanChange::ForeignTaskChecked {
+                    task_id: old_task.id.clone(),
+                });
+            }
+        }
+        for new_task in &new.tasks {
+            if !old.tasks.iter().any(|t| t.id == new_task.id) {
+                changes.push(PlanChange::TaskAdded {
+                    task_id: new_task.id.clone(),
+                });
+            }
+        }
+    }
+
+    for new in after {
+        if !before.iter().any(|s| s.id == new.id) {
+            changes.push(PlanChange::StoryAdded {
+                story_id: new.id.clone(),
+            });
+        }
+    }
+
+    // Reordering only matters when the same stories are still there
+    let story_ids = |stories: &[Story]| stories.iter().map(|s| s.id.clone()).collect::<Vec<_>>();
+    if changes.is_empty() && story_ids(before) != story_ids(after) {
+        changes.push(PlanChange::StoriesReordered);
+    }
+
+    changes
+}
+
+/// Returns the IDs of the story's open tasks in `before` that are done in
+/// `after`, in plan order.
+pub fn ticked_tasks(before: &[Story], after: &[Story], story_id: &str) -> Vec<String> {
+    let (Some(old), Some(new)) = (
+        before.iter().find(|s| s.id == story_id),
+        after.iter().find(|s| s.id == story_id),
+    ) else {
+        return Vec::new();
+    };
+
+    old.tasks
+        .iter()
+        .filter(|task| !task.done)
+        .filter(|task| new.tasks.iter().any(|t| t.id == task.id && t.done))
+        .map(|task| task.id.clone())
+        .collect()
+}
+
+/// Builds the retry reason listing what the agent must not change in tasks.md.
+pub fn retry_reason(changes: &[PlanChange]) -> String {
+    super::list_retry_reason(
+        "Your edits to tasks.md changed the plan. Only mark this story's tasks `- [x]`; \
+         do not add, remove, reword or reorder stories and tasks, or check other stories' tasks:",
+        changes.iter().map(PlanChange::describe),
+        "",
+    )
+}
+
+/// Marks the `ticked` tasks under the story's `## N. Title` header done in
+/// tasks.md content, leaving every other line as it is.
+fn tick_tasks(content: &str, story_id: &str, ticked: &[String]) -> String {
+    let mut in_story = false;
+    let mut result = String::with_capacity(content.len());
+
+    for line in content.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if let Some(header) = trimmed.strip_prefix("## ") {
+            in_story = header.split_once(". ").map(|(id, _)| id.trim()) == Some(story_id);
+        } else if in_story {
+            if let Some(rest) = trimmed.strip_prefix("- [ ] ") {
+                let id = rest.split(' ').next().unwrap_or("").trim();
+                if ticked.iter().any(|t| t == id) {
+                    result.push_str(&line.replacen("- [ ] ", "- [x] ", 1));
+                    continue;
+                }
+            }
+        }
+        result.push_str(line);
+    }
+
+    result
+}
+
+/// Returns `stories` with the `ticked` tasks of the story marked done.
+fn apply_ticks(stories: &[Story], story_id: &str, ticked: &[String]) -> Vec<Story> {
+    let mut stories = stories.to_vec();
+    if let Some(story) = stories.iter_mut().find(|s| s.id == story_id) {
+        for task in story.tasks.iter_mut().filter(|t| ticked.contains(&t.id)) {
+            task.done = true;
+        }
+    }
+    stories
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spec::Task;
+
+    fn story(id: &str, title: &str, tasks: &[(&str, bool)]) -> Story {
+        Story {
+            id: id.to_string(),
+            title: title.to_string(),
+            tasks: tasks
+                .iter()
+                .map(|(task_id, done)| Task {
+                    id: task_id.to_string(),
+                    description: format!("Task {}", task_id),
+                    done: *done,
+                })
+                .collect(),
+        }
+    }
+
+    fn plan() -> Vec<Story> {
+        vec![
+            story("1", "Setup", &[("1.1", true)]),
+            story("2", "Feature", &[("2.1", false), ("2.2", false)]),
+            story("3", "Docs", &[("3.1", false)]),
+        ]
+    }
+
+    #[test]
+    fn ticking_own_tasks_is_allowed() {
+        let mut after = plan();
+        after[1].tasks[0].done = true;
+        after[1].tasks[1].done = true;
+
+        assert!(disallowed_changes(&plan(), &after, "2").is_empty());
+        assert_eq!(ticked_tasks(&plan(), &after, "2"), vec!["2.1", "2.2"]);
+    }
+
+    #[test]
+    fn structural_edits_are_reported() {
+        let mut after = plan();
+        after[0].tasks[0].done = false;
+        after[1].title = "Renamed".to_string();
+        after[1].tasks.remove(1);
+        after[1].tasks[0].description = "Easier task".to_string();
+        after[2].tasks[0].done = true;
+        after.push(story("4", "Extra", &[("4.1", false)]));
+
+        assert_eq!(
+            disallowed_changes(&plan(), &after, "2"),
+            vec![
+                PlanChange::TaskUnchecked {
+                    task_id: "1.1".to_string()
+                },
+                PlanChange::StoryRenamed {
+                    story_id: "2".to_string(),
+                    title: "Renamed".to_string()
+                },
+                PlanChange::TaskEdited {
+                    task_id: "2.1".to_string()
+                },
+                PlanChange::TaskRemoved {
+                    task_id: "2.2".to_string()
+                },
+                PlanChange::ForeignTaskChecked {
+                    task_id: "3.1".to_string()
+                },
+                PlanChange::StoryAdded {
+                    story_id: "4".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn removed_story_and_reorder_are_reported() {
+        let after = vec![plan()[0].clone(), plan()[1].clone()];
+        assert_eq!(
+            disallowed_changes(&plan(), &after, "2"),
+            vec![PlanChange::StoryRemoved {
+                story_id: "3".to_string()
+            }]
+        );
+
+        let reordered = vec![plan()[0].clone(), plan()[2].clone(), plan()[1].clone()];
+        assert_eq!(
+            disallowed_changes(&plan(), &reordered, "2"),
+            vec![PlanChange::StoriesReordered]
+        );
+    }
+
+    #[test]
+    fn tick_tasks_only_touches_the_story() {
+        let content = "# Tasks\n\n## 1. Setup\n- [ ] 1.1 First\n\n## 2. Feature\n- [ ] 2.1 Second\n- [ ] 2.2 Third\n";
+        let repaired = tick_tasks(content, "2", &["2.1".to_string(), "1.1".to_string()]);
+        assert_eq!(
+            repaired,
+            "# Tasks\n\n## 1. Setup\n- [ ] 1.1 First\n\n## 2. Feature\n- [x] 2.1 Second\n- [ ] 2.2 Third\n"
+        );
+    }
+
+    #[test]
+    fn repair_restores_snapshot_with_ticks() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("tasks.md");
+        fs::write(&path, "## 2. Feature\n").unwrap();
+        let snapshot = PlanSnapshot {
+            path: path.clone(),
+            content: "## 2. Feature\n- [ ] 2.1 Task 2.1\n- [ ] 2.2 Task 2.2\n".to_string(),
+            stories: vec![plan()[1].clone()],
+        };
+
+        let stories = snapshot.repair("2", &["2.2".to_string()]).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&path).unwrap(),
+            "## 2. Feature\n- [ ] 2.1 Task 2.1\n- [x] 2.2 Task 2.2\n"
+        );
+        assert!(!stories[0].tasks[0].done);
+        assert!(stories[0].tasks[1].done);
+    }
+}
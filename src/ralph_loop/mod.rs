@@ -4,17 +4,30 @@
 //! The simplified orchestrator spawns a single agent with a self-contained prompt.
 //! The agent reads files directly and marks tasks complete by editing tasks.md.
 
+pub mod diff_limit;
+pub mod integrity;
 pub mod learnings;
 mod orchestrator;
+pub mod protected;
+pub mod scan;
+pub mod verify;
 
+pub use diff_limit::{DiffLimitAction, DiffLimits};
+pub use integrity::{IntegrityAction, PlanChange};
 pub use orchestrator::{Orchestrator, DEFAULT_MAX_RETRIES};
+pub use scan::{ScanAction, ScanConfig, ScanFinding};
+pub use verify::VerificationResult;
 
-// Re-export CompletionOption from checkpoint module for TUI use
-pub use crate::checkpoint::CompletionOption;
+// Re-export checkpoint options from checkpoint module for TUI use
+pub use crate::checkpoint::{CheckpointBackend, CompletionOption, DiffStat};
 
 /// Default timeout in seconds for external commands (git, openspec).
 pub const DEFAULT_COMMAND_TIMEOUT_SECS: u64 = 30;
 
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
 use crate::agent::StreamEvent;
 use tokio::sync::{mpsc, oneshot};
 
@@ -48,6 +61,106 @@ pub enum LoopEvent {
         event: StreamEvent,
     },
 
+    /// Result of an orchestrator-run verification command for a story.
+    Verification {
+        /// ID of the story being verified.
+        story_id: String,
+        /// Outcome of the command.
+        result: VerificationResult,
+    },
+
+    /// The agent signaled COMPLETE but tasks.md still has unchecked tasks
+    /// for the story. The attempt is rejected and counts as a retry.
+    SignalStateMismatch {
+        /// ID of the story the agent claimed to complete.
+        story_id: String,
+        /// IDs of the story's tasks that are still unchecked.
+        unchecked_tasks: Vec<String>,
+    },
+
+    /// The agent changed the plan in tasks.md beyond ticking the story's own
+    /// tasks.
+    PlanChanged {
+        /// ID of the story whose attempt changed the plan.
+        story_id: String,
+        /// The disallowed changes, in plan order.
+        changes: Vec<PlanChange>,
+        /// How the changes were handled: `Reject` failed the attempt,
+        /// `Repair` restored the plan with only the allowed ticks.
+        action: IntegrityAction,
+    },
+
+    /// A story's changes exceed the configured diff limits.
+    DiffLimitExceeded {
+        /// ID of the story whose changes were measured.
+        story_id: String,
+        /// Size of the changes since the last checkpoint.
+        stat: DiffStat,
+        /// Descriptions of the exceeded limits.
+        exceeded: Vec<String>,
+        /// How the excess is handled: `Fail` fails the attempt, `Ask` is
+        /// followed by `AwaitingDiffApproval`.
+        action: DiffLimitAction,
+    },
+
+    /// Orchestrator is waiting for the user to accept or reject a story
+    /// whose changes exceed the diff limits. Sending `true` commits the
+    /// story; `false` (or dropping the sender) fails the attempt.
+    AwaitingDiffApproval {
+        /// ID of the story awaiting approval.
+        story_id: String,
+        /// Size of the changes since the last checkpoint.
+        stat: DiffStat,
+        /// Descriptions of the exceeded limits.
+        exceeded: Vec<String>,
+        /// Sender to communicate the user's decision back to the orchestrator.
+        approve_tx: oneshot::Sender<bool>,
+    },
+
+    /// The pre-commit scan found secrets, denied paths or oversized files in
+    /// a story's changes.
+    ScanFindings {
+        /// ID of the story whose changes were scanned.
+        story_id: String,
+        /// What was found, in path order.
+        findings: Vec<ScanFinding>,
+        /// How the findings were handled: `Unstage` left the paths out of
+        /// the checkpoint, `Fail` failed the attempt.
+        action: ScanAction,
+    },
+
+    /// A failed attempt was reverted to the last checkpoint.
+    Reverted {
+        /// ID of the story whose attempt was reverted.
+        story_id: String,
+        /// Tracked files restored to their checkpoint content.
+        restored: Vec<String>,
+        /// Untracked files the attempt created that were removed.
+        removed: Vec<String>,
+        /// Ref the failed attempt was archived under, if archiving succeeded.
+        archive_ref: Option<String>,
+    },
+
+    /// The run was exported as a `git format-patch` series.
+    PatchesExported {
+        /// Directory the patches were written to.
+        dir: PathBuf,
+        /// Patch files, in order.
+        patches: Vec<PathBuf>,
+    },
+
+    /// A completion option could not land the changes because the original
+    /// branch moved on during the run and conflicts with it. The attempt was
+    /// aborted, and the user is asked for a choice again.
+    CleanupConflict {
+        /// The option that ran into the conflict.
+        option: CompletionOption,
+        /// The original branch the changes conflict with.
+        branch: String,
+        /// Files with conflicts.
+        files: Vec<String>,
+    },
+
     /// An error occurred during loop execution.
     Error {
         #[allow(dead_code)] // Used in Story 5 UI rendering
@@ -71,6 +184,62 @@ pub enum LoopEvent {
     Complete,
 }
 
+/// What the loop does when a story exceeds its retry budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FailurePolicy {
+    /// Stop the whole loop at the first failed story.
+    #[default]
+    Abort,
+    /// Revert the failed story and continue with the next incomplete one.
+    Skip,
+    /// Like `Skip`, but stop once this many stories have failed.
+    StopAfter(usize),
+}
+
+impl FailurePolicy {
+    /// Returns true if the loop should keep going after `failed_count` stories failed.
+    pub fn continues_after(self, failed_count: usize) -> bool {
+        match self {
+            FailurePolicy::Abort => false,
+            FailurePolicy::Skip => true,
+            FailurePolicy::StopAfter(limit) => failed_count < limit,
+        }
+    }
+}
+
+impl FromStr for FailurePolicy {
+    type Err = String;
+
+    /// Parses `abort`, `skip` (or `skip-and-continue`) or `stop-after-<N>`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "abort" => Ok(FailurePolicy::Abort),
+            "skip" | "skip-and-continue" => Ok(FailurePolicy::Skip),
+            _ => s
+                .strip_prefix("stop-after-")
+                .and_then(|n| n.parse::<usize>().ok())
+                .filter(|n| *n > 0)
+                .map(FailurePolicy::StopAfter)
+                .ok_or_else(|| {
+                    format!(
+                        "invalid failure policy '{}' (expected abort, skip or stop-after-<N>)",
+                        s
+                    )
+                }),
+        }
+    }
+}
+
+impl fmt::Display for FailurePolicy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FailurePolicy::Abort => write!(f, "abort"),
+            FailurePolicy::Skip => write!(f, "skip"),
+            FailurePolicy::StopAfter(limit) => write!(f, "stop-after-{}", limit),
+        }
+    }
+}
+
 /// State tracking for the loop execution.
 ///
 /// Tracks current story being worked on and overall progress.
@@ -93,6 +262,9 @@ pub struct LoopState {
 
     /// IDs of stories that have been started, in order.
     pub started_story_ids: Vec<String>,
+
+    /// IDs of stories that exceeded max retries, in order.
+    pub failed_story_ids: Vec<String>,
 }
 
 impl LoopState {
@@ -105,10 +277,26 @@ impl LoopState {
             total_stories: 0,
             completed_stories: 0,
             started_story_ids: Vec::new(),
+            failed_story_ids: Vec::new(),
         }
     }
 }
 
+/// Builds a retry reason from an intro line, one `- item` line per item and
+/// a closing instruction (which may be empty).
+pub(crate) fn list_retry_reason<I>(intro: &str, items: I, instruction: &str) -> String
+where
+    I: IntoIterator,
+    I::Item: fmt::Display,
+{
+    let mut reason = format!("{}\n", intro);
+    for item in items {
+        reason.push_str(&format!("- {}\n", item));
+    }
+    reason.push_str(instruction);
+    reason
+}
+
 /// Sender for loop events.
 pub type LoopEventSender = mpsc::Sender<LoopEvent>;
 
@@ -121,3 +309,44 @@ pub type LoopEventReceiver = mpsc::Receiver<LoopEvent>;
 pub fn event_channel(buffer: usize) -> (LoopEventSender, LoopEventReceiver) {
     mpsc::channel(buffer)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failure_policy_parses_all_forms() {
+        assert_eq!("abort".parse(), Ok(FailurePolicy::Abort));
+        assert_eq!("skip".parse(), Ok(FailurePolicy::Skip));
+        assert_eq!("skip-and-continue".parse(), Ok(FailurePolicy::Skip));
+        assert_eq!("stop-after-2".parse(), Ok(FailurePolicy::StopAfter(2)));
+    }
+
+    #[test]
+    fn failure_policy_rejects_invalid_values() {
+        assert!("stop-after-0".parse::<FailurePolicy>().is_err());
+        assert!("stop-after-x".parse::<FailurePolicy>().is_err());
+        assert!("continue".parse::<FailurePolicy>().is_err());
+    }
+
+    #[test]
+    fn failure_policy_display_round_trips() {
+        for policy in [FailurePolicy::Abort, FailurePolicy::Skip, FailurePolicy::StopAfter(3)] {
+            assert_eq!(policy.to_string().parse(), Ok(policy));
+        }
+    }
+
+    #[test]
+    fn list_retry_reason_puts_each_item_on_its_own_line() {
+        let reason = list_retry_reason("Intro:", ["a.rs", "b.rs"], "Fix it.");
+        assert_eq!(reason, "Intro:\n- a.rs\n- b.rs\nFix it.");
+    }
+
+    #[test]
+    fn failure_policy_continues_after() {
+        assert!(!FailurePolicy::Abort.continues_after(1));
+        assert!(FailurePolicy::Skip.continues_after(10));
+        assert!(FailurePolicy::StopAfter(2).continues_after(1));
+        assert!(!FailurePolicy::StopAfter(2).continues_after(2));
+    }
+}
@@ -5,21 +5,34 @@
 //! 2. For each incomplete story, generates a story-specific prompt
 //! 3. Spawns an agent for that story
 //! 4. Detects `<promise>COMPLETE</promise>` to mark story iteration done
-//! 5. Refreshes story list and continues to next incomplete story
-//! 6. Emits Complete when all stories are done
-
-use std::sync::atomic::{AtomicBool, Ordering};
-use std::sync::Arc;
+//! 5. Checks that tasks.md only gained ticks for the story's own tasks, then
+//!    runs the project's verification commands before accepting the story
+//! 6. Checks the size of the story's changes, rejects changes to protected
+//!    paths and scans for secrets and large files, then commits them as a
+//!    checkpoint
+//! 7. Refreshes story list and continues to next incomplete story
+//! 8. Emits Complete when all stories are done
+
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
 
+use super::diff_limit::{self, DiffLimitAction, DiffLimits};
+use super::integrity::{self, IntegrityAction, PlanSnapshot};
 use super::learnings::{ensure_learnings_file, read_learnings};
-use super::{CompletionOption, LoopEvent, LoopEventSender, LoopState, DEFAULT_COMMAND_TIMEOUT_SECS};
-use crate::agent::{CodingAgent, PromptBuilder, StreamEvent};
-use crate::checkpoint::Checkpoint;
-use crate::error::Result;
-use crate::spec::{self, Story};
+use super::protected;
+use super::scan::{self, ScanAction, ScanConfig};
+use super::verify::{self, DEFAULT_VERIFY_TIMEOUT_SECS};
+use super::{
+    CompletionOption, FailurePolicy, LoopEvent, LoopEventSender, LoopState,
+    DEFAULT_COMMAND_TIMEOUT_SECS,
+};
+use crate::agent::{AgentExit, CodingAgent, Prompt, PromptBuilder, Response, StreamEvent};
+use crate::checkpoint::{self, Checkpoint, CheckpointBackend, CheckpointCommit, CommitFormat};
+use crate::error::{Error, Result};
+use crate::spec::{self, SpecAdapter, Story, Task};
 
 /// Completion signal that agents output when a story is done and verified.
 const COMPLETION_SIGNAL: &str = "<promise>COMPLETE</promise>";
@@ -42,17 +55,51 @@ pub struct Orchestrator {
     /// Event sender for TUI updates.
     event_tx: LoopEventSender,
 
-    /// Flag to stop the loop.
-    stop_flag: Arc<AtomicBool>,
+    /// Cancelled to stop the loop.
+    stop_token: CancellationToken,
 
     /// Checkpoint manager for branch-based state preservation.
-    checkpoint: Checkpoint,
+    checkpoint: Box<dyn Checkpoint>,
+
+    /// Where the checkpoint manager checks out the ralph branch.
+    checkpoint_backend: CheckpointBackend,
 
     /// Maximum number of retries per story.
     max_retries: usize,
 
+    /// What to do when a story exceeds max retries.
+    failure_policy: FailurePolicy,
+
     /// Timeout for external commands (git, openspec).
     command_timeout: Duration,
+
+    /// Timeout for each verification command run after a COMPLETE signal.
+    verify_timeout: Duration,
+
+    /// Whether to resume an interrupted run on the existing ralph branch.
+    resume: bool,
+
+    /// Directory the `ExportPatch` completion option writes patches to.
+    patch_dir: PathBuf,
+
+    /// Subject format of checkpoint commits.
+    commit_format: CommitFormat,
+
+    /// Author identity (`Name <email>`) for checkpoint commits, if not the git user.
+    commit_author: Option<String>,
+
+    /// Scanner run on a story's changes before they are committed.
+    scan_config: ScanConfig,
+
+    /// Globs of paths an attempt must not change.
+    protected_paths: Vec<String>,
+
+    /// What to do when an attempt changes the plan in tasks.md beyond
+    /// ticking the story's own tasks.
+    integrity_action: IntegrityAction,
+
+    /// Limits on the size of a story's changes.
+    diff_limits: DiffLimits,
 }
 
 impl Orchestrator {
@@ -68,10 +115,21 @@ impl Orchestrator {
             change_name: change_name.to_string(),
             agent,
             event_tx,
-            stop_flag: Arc::new(AtomicBool::new(false)),
-            checkpoint: Checkpoint::with_timeout(change_name, timeout),
+            stop_token: CancellationToken::new(),
+            checkpoint: checkpoint::create_checkpoint(CheckpointBackend::default(), change_name, timeout),
+            checkpoint_backend: CheckpointBackend::default(),
             max_retries,
+            failure_policy: FailurePolicy::default(),
             command_timeout: timeout,
+            verify_timeout: Duration::from_secs(DEFAULT_VERIFY_TIMEOUT_SECS),
+            resume: false,
+            patch_dir: checkpoint::default_patch_dir(change_name),
+            commit_format: CommitFormat::default(),
+            commit_author: None,
+            scan_config: ScanConfig::default(),
+            protected_paths: Vec::new(),
+            integrity_action: IntegrityAction::default(),
+            diff_limits: DiffLimits::default(),
         }
     }
 
@@ -79,13 +137,86 @@ impl Orchestrator {
     pub fn with_command_timeout(mut self, timeout_secs: u64) -> Self {
         let timeout = Duration::from_secs(timeout_secs);
         self.command_timeout = timeout;
-        self.checkpoint = Checkpoint::with_timeout(&self.change_name, timeout);
+        self.checkpoint = checkpoint::create_checkpoint(self.checkpoint_backend, &self.change_name, timeout);
+        self
+    }
+
+    /// Sets where the ralph branch is checked out while the loop runs.
+    pub fn with_checkpoint_backend(mut self, backend: CheckpointBackend) -> Self {
+        self.checkpoint_backend = backend;
+        self.checkpoint = checkpoint::create_checkpoint(backend, &self.change_name, self.command_timeout);
+        self
+    }
+
+    /// Sets the timeout for each verification command.
+    pub fn with_verify_timeout(mut self, timeout_secs: u64) -> Self {
+        self.verify_timeout = Duration::from_secs(timeout_secs);
+        self
+    }
+
+    /// Sets the policy applied when a story exceeds max retries.
+    pub fn with_failure_policy(mut self, policy: FailurePolicy) -> Self {
+        self.failure_policy = policy;
+        self
+    }
+
+    /// Resumes on top of the existing ralph branch instead of recreating it.
+    pub fn with_resume(mut self, resume: bool) -> Self {
+        self.resume = resume;
+        self
+    }
+
+    /// Sets the directory the `ExportPatch` completion option writes patches to.
+    pub fn with_patch_dir(mut self, dir: PathBuf) -> Self {
+        self.patch_dir = dir;
+        self
+    }
+
+    /// Sets the subject format of checkpoint commits.
+    pub fn with_commit_format(mut self, format: CommitFormat) -> Self {
+        self.commit_format = format;
+        self
+    }
+
+    /// Sets the author identity (`Name <email>`) of checkpoint commits.
+    pub fn with_commit_author(mut self, author: Option<String>) -> Self {
+        self.commit_author = author;
+        self
+    }
+
+    /// Sets the scanner run on a story's changes before they are committed.
+    pub fn with_scan_config(mut self, config: ScanConfig) -> Self {
+        self.scan_config = config;
+        self
+    }
+
+    /// Sets the globs of paths an attempt must not change.
+    pub fn with_protected_paths(mut self, globs: Vec<String>) -> Self {
+        self.protected_paths = globs;
+        self
+    }
+
+    /// Sets what to do when an attempt changes the plan beyond its own ticks.
+    pub fn with_integrity_action(mut self, action: IntegrityAction) -> Self {
+        self.integrity_action = action;
+        self
+    }
+
+    /// Sets the limits on the size of a story's changes.
+    pub fn with_diff_limits(mut self, limits: DiffLimits) -> Self {
+        self.diff_limits = limits;
+        self
+    }
+
+    /// Stops the loop when `token` is cancelled.
+    pub fn with_stop_token(mut self, token: CancellationToken) -> Self {
+        self.stop_token = token;
         self
     }
 
     /// Get a handle to stop the loop.
-    pub fn stop_handle(&self) -> Arc<AtomicBool> {
-        Arc::clone(&self.stop_flag)
+    pub fn stop_handle(&self) -> CancellationToken {
+        self.stop_token.clone()
     }
 
     /// Run the orchestration loop.
@@ -96,25 +227,51 @@ impl Orchestrator {
     /// on failure.
     ///
     /// Uses branch-based checkpoints:
+    /// - `lock()` keeps other instances out of the repository for the whole run
     /// - `init()` at loop start creates ralph/{change} branch
-    /// - `commit_checkpoint()` after each successful story
-    /// - `revert()` (reset --hard HEAD) on failure
+    ///   (or `resume()` continues on it when resuming)
+    /// - The agent, verification and spec reads run in the checkpoint's `work_dir()`
+    /// - `commit_checkpoint()` after each story that passes verification
+    /// - `revert()` of the agent's footprint on failure, or when a stop
+    ///   request kills the agent mid-story
     /// - Returns LoopState with completion_option for TUI to handle
     pub async fn run(&mut self) -> Result<LoopState> {
         // Initialize state
         let mut state = LoopState::new(&self.change_name);
         state.running = true;
 
-        // Initialize checkpoint system at loop start (creates ralph branch)
-        if let Err(e) = self.checkpoint.init().await {
-            self.emit(LoopEvent::Error {
-                message: format!("Failed to initialize checkpoint system: {}", e),
-            })
-            .await;
-            self.emit(LoopEvent::Complete).await;
-            state.running = false;
-            return Ok(state);
-        }
+        // Keep other instances off the ralph branches until the run returns
+        let _lock = match self.checkpoint.lock().await {
+            Ok(lock) => lock,
+            Err(e) => {
+                self.emit(LoopEvent::Error {
+                    message: format!("Failed to start: {}", e),
+                })
+                .await;
+                self.emit(LoopEvent::Complete).await;
+                state.running = false;
+                return Ok(state);
+            }
+        };
+
+        // Initialize checkpoint system at loop start (creates or resumes ralph branch)
+        let initialized = if self.resume {
+            self.checkpoint.resume().await
+        } else {
+            self.checkpoint.init().await
+        };
+        let work_dir = match initialized.and_then(|()| self.checkpoint.work_dir()) {
+            Ok(work_dir) => work_dir,
+            Err(e) => {
+                self.emit(LoopEvent::Error {
+                    message: format!("Failed to initialize checkpoint system: {}", e),
+                })
+                .await;
+                self.emit(LoopEvent::Complete).await;
+                state.running = false;
+                return Ok(state);
+            }
+        };
 
         // Ensure learnings file exists (creates if missing, preserves existing)
         if let Err(e) = ensure_learnings_file(&self.change_name) {
@@ -128,21 +285,21 @@ impl Orchestrator {
         // Story iteration loop
         'story_loop: loop {
             // Check for stop request
-            if self.stop_flag.load(Ordering::Relaxed) {
+            if self.stop_token.is_cancelled() {
                 state.running = false;
                 break 'story_loop;
             }
 
             // Refresh adapter to get latest story state (async with timeout)
-            let adapter = spec::create_adapter_async_with_timeout(&self.change_name, self.command_timeout).await?;
+            let adapter = spec::create_adapter_in_dir(&self.change_name, &work_dir, self.command_timeout).await?;
             let stories = adapter.stories()?;
 
             // Update state with story counts
             state.total_stories = stories.len();
             state.completed_stories = stories.iter().filter(|s| is_story_complete(s)).count();
 
-            // Find next incomplete story
-            let next_story = next_incomplete_story(&stories);
+            // Find next incomplete story (skipping stories that already failed)
+            let next_story = next_incomplete_story(&stories, &state.failed_story_ids);
 
             match next_story {
                 Some(story) => {
@@ -173,6 +330,16 @@ impl Orchestrator {
                     let mut retry_reason: Option<String> = None;
                     let story_id = story.id.clone();
                     let story_title = story.title.clone();
+                    // The tasks this story ticks off once it passes
+                    let open_tasks: Vec<Task> = story
+                        .tasks
+                        .iter()
+                        .filter(|task| !task.done)
+                        .map(|task| Task {
+                            done: true,
+                            ..task.clone()
+                        })
+                        .collect();
 
                     'retry_loop: loop {
                         // Read learnings content for prompt (if available)
@@ -185,197 +352,596 @@ impl Orchestrator {
                         let prompt =
                             prompt_builder.for_story_with_retry_context(&story_id, retry_reason.take())?;
 
-                        // Run agent for this story
-                        match self.agent.run(&prompt) {
-                            Ok(stream) => {
-                                let mut final_content = String::new();
-
-                                // Process streaming events
-                                for event in stream {
-                                    match &event {
-                                        StreamEvent::Message(_) => {
-                                            // Emit intermediate message with story context
-                                            self.emit(LoopEvent::StoryEvent {
-                                                story_id: story_id.clone(),
-                                                event: event.clone(),
-                                            })
-                                            .await;
-                                        }
-                                        StreamEvent::Done(response) => {
-                                            // Store final content for completion check
-                                            final_content = response.content.clone();
-                                            // Emit done event with full response
-                                            self.emit(LoopEvent::StoryEvent {
-                                                story_id: story_id.clone(),
-                                                event: event.clone(),
-                                            })
-                                            .await;
-                                        }
-                                    }
-                                }
-
-                                // Parse agent output for signals
-                                let result = parse_agent_result(&final_content);
-
-                                match result {
-                                    AgentResult::Complete => {
-                                        // Story completed successfully
-                                        // Create checkpoint commit for this story
-                                        if let Err(e) = self.checkpoint.commit_checkpoint(&story_id).await {
-                                            // Log but don't fail - changes are still in working dir
-                                            self.emit(LoopEvent::Error {
-                                                message: format!(
-                                                    "Warning: Failed to create checkpoint for story {}: {}",
-                                                    story_id, e
-                                                ),
-                                            })
-                                            .await;
-                                        }
-                                        continue 'story_loop;
-                                    }
-                                    AgentResult::Failed(reason) => {
-                                        // Agent explicitly reported failure
-                                        retry_count += 1;
-
-                                        if retry_count >= self.max_retries {
-                                            // Max retries exceeded
-                                            self.emit(LoopEvent::Error {
-                                                message: format!(
-                                                    "Max retries ({}) exceeded for story {} ({}): {}",
-                                                    self.max_retries, story_id, story_title, reason
-                                                ),
-                                            })
-                                            .await;
-                                            self.emit(LoopEvent::MaxRetriesExceeded {
-                                                story_id: story_id.clone(),
-                                            })
-                                            .await;
-                                            break 'story_loop;
-                                        }
-
-                                        // Revert to checkpoint (reset --hard HEAD)
-                                        if let Err(e) = self.checkpoint.revert().await {
-                                            self.emit(LoopEvent::Error {
-                                                message: format!(
-                                                    "Failed to revert checkpoint for story {}: {}",
-                                                    story_id, e
-                                                ),
-                                            })
-                                            .await;
-                                            break 'story_loop;
-                                        }
-
-                                        // Store failure reason for next retry prompt
-                                        retry_reason = Some(reason);
-
-                                        // Continue retry loop
-                                        continue 'retry_loop;
-                                    }
-                                    AgentResult::NoSignal => {
-                                        // Abnormal termination - no promise signal
-                                        retry_count += 1;
-
-                                        if retry_count >= self.max_retries {
-                                            // Max retries exceeded
-                                            self.emit(LoopEvent::Error {
-                                                message: format!(
-                                                    "Max retries ({}) exceeded for story {} ({}): agent finished without completion signal",
-                                                    self.max_retries, story_id, story_title
-                                                ),
-                                            })
-                                            .await;
-                                            self.emit(LoopEvent::MaxRetriesExceeded {
-                                                story_id: story_id.clone(),
-                                            })
-                                            .await;
-                                            break 'story_loop;
-                                        }
-
-                                        // Revert to checkpoint (reset --hard HEAD)
-                                        if let Err(e) = self.checkpoint.revert().await {
-                                            self.emit(LoopEvent::Error {
-                                                message: format!(
-                                                    "Failed to revert checkpoint for story {}: {}",
-                                                    story_id, e
-                                                ),
-                                            })
-                                            .await;
-                                            break 'story_loop;
-                                        }
-
-                                        // Continue retry loop (no extra context for abnormal termination)
-                                        continue 'retry_loop;
-                                    }
-                                }
-                            }
+                        // Snapshot the plan so the integrity guard sees what the agent changed
+                        let plan = match PlanSnapshot::capture(adapter.as_ref()) {
+                            Ok(plan) => Some(plan),
                             Err(e) => {
-                                // Agent error - treat as failure and retry
-                                retry_count += 1;
-
-                                if retry_count >= self.max_retries {
-                                    self.emit(LoopEvent::Error {
-                                        message: format!(
-                                            "Max retries ({}) exceeded for story {} ({}): {}",
-                                            self.max_retries, story_id, story_title, e
-                                        ),
-                                    })
-                                    .await;
-                                    self.emit(LoopEvent::MaxRetriesExceeded {
-                                        story_id: story_id.clone(),
-                                    })
-                                    .await;
-                                    break 'story_loop;
-                                }
+                                self.emit(LoopEvent::Error {
+                                    message: format!(
+                                        "Warning: Failed to snapshot tasks for story {}: {}",
+                                        story_id, e
+                                    ),
+                                })
+                                .await;
+                                None
+                            }
+                        };
+
+                        // Snapshot untracked files so revert spares anything pre-existing
+                        if let Err(e) = self.checkpoint.begin_attempt().await {
+                            self.emit(LoopEvent::Error {
+                                message: format!(
+                                    "Warning: Failed to snapshot untracked files for story {}: {}",
+                                    story_id, e
+                                ),
+                            })
+                            .await;
+                        }
 
-                                // Revert to checkpoint (reset --hard HEAD)
-                                if let Err(revert_err) = self.checkpoint.revert().await {
+                        // Run agent for this story and verify the result
+                        let attempt = match self.run_attempt(adapter.as_ref(), &work_dir, &story_id, &prompt, plan.as_ref()).await {
+                            Ok(response) => self
+                                .check_changes(&story_id)
+                                .await
+                                .map(|excluded_paths| (response, excluded_paths)),
+                            Err(failure) => Err(failure),
+                        };
+                        let failure = match attempt {
+                            Ok((response, excluded_paths)) => {
+                                // Story completed, verified and scanned
+                                // Create checkpoint commit for this story
+                                let commit = CheckpointCommit {
+                                    story_title: story_title.clone(),
+                                    completed_tasks: open_tasks.clone(),
+                                    attempts: retry_count + 1,
+                                    response,
+                                    format: self.commit_format,
+                                    author: self.commit_author.clone(),
+                                    excluded_paths,
+                                    ..CheckpointCommit::new(story_id.clone())
+                                };
+                                if let Err(e) = self.checkpoint.commit_checkpoint(&commit).await {
+                                    // Log but don't fail - changes are still in working dir
                                     self.emit(LoopEvent::Error {
                                         message: format!(
-                                            "Failed to revert checkpoint for story {}: {}",
-                                            story_id, revert_err
+                                            "Warning: Failed to create checkpoint for story {}: {}",
+                                            story_id, e
                                         ),
                                     })
                                     .await;
-                                    break 'story_loop;
                                 }
+                                continue 'story_loop;
+                            }
+                            Err(failure) => failure,
+                        };
+
+                        if failure.cancelled {
+                            // Stopped mid-story: put the partial attempt aside and leave
+                            self.discard_attempt(&story_id, retry_count + 1, &failure).await;
+                            state.running = false;
+                            break 'story_loop;
+                        }
 
+                        retry_count += 1;
+                        match self
+                            .handle_failure(&mut state, &story_id, &story_title, retry_count, &failure)
+                            .await
+                        {
+                            FailureOutcome::Retry => {
+                                // Store failure reason for next retry prompt (if any)
+                                retry_reason = failure.retry_reason;
                                 continue 'retry_loop;
                             }
+                            FailureOutcome::NextStory => continue 'story_loop,
+                            FailureOutcome::Stop => break 'story_loop,
                         }
                     }
                 }
                 None => {
-                    // All stories complete!
-                    state.completed_stories = state.total_stories;
+                    // All stories complete (or failed and skipped)
                     state.current_story_id = None;
                     break 'story_loop;
                 }
             }
         }
 
-        // Create oneshot channel for user choice
-        let (choice_tx, choice_rx) = oneshot::channel::<CompletionOption>();
+        // Ask again after a conflict, until the user keeps the branch or
+        // the cleanup goes through
+        loop {
+            // Create oneshot channel for user choice
+            let (choice_tx, choice_rx) = oneshot::channel::<CompletionOption>();
+
+            // Send AwaitingUserChoice event with the sender
+            self.emit(LoopEvent::AwaitingUserChoice { choice_tx }).await;
+
+            // Wait for user's choice via the receiver
+            // Handle Err case (dropped sender / force quit) as Keep
+            let user_choice = choice_rx.await.unwrap_or(CompletionOption::Keep);
+
+            if user_choice == CompletionOption::ExportPatch {
+                match self.checkpoint.export_patches(&self.patch_dir).await {
+                    Ok(patches) => {
+                        self.emit(LoopEvent::PatchesExported {
+                            dir: self.patch_dir.clone(),
+                            patches,
+                        })
+                        .await;
+                    }
+                    Err(e) => {
+                        self.emit(LoopEvent::Error {
+                            message: format!("Failed to export patches: {}", e),
+                        })
+                        .await;
+                    }
+                }
+            }
+
+            // Call checkpoint.cleanup with the received choice
+            match self.checkpoint.cleanup(user_choice).await {
+                Ok(()) => break,
+                Err(Error::MergeConflict { branch, files }) => {
+                    // Cleanup was aborted; the ralph branch is untouched
+                    self.emit(LoopEvent::CleanupConflict {
+                        option: user_choice,
+                        branch,
+                        files,
+                    })
+                    .await;
+                }
+                Err(e) => {
+                    self.emit(LoopEvent::Error {
+                        message: format!("Failed to cleanup: {}", e),
+                    })
+                    .await;
+                    break;
+                }
+            }
+        }
 
-        // Send AwaitingUserChoice event with the sender
-        self.emit(LoopEvent::AwaitingUserChoice { choice_tx }).await;
+        // Only send Complete event after cleanup finishes
+        self.emit(LoopEvent::Complete).await;
+        state.running = false;
 
-        // Wait for user's choice via the receiver
-        // Handle Err case (dropped sender / force quit) as Keep
-        let user_choice = choice_rx.await.unwrap_or(CompletionOption::Keep);
+        Ok(state)
+    }
 
-        // Call checkpoint.cleanup with the received choice
-        if let Err(e) = self.checkpoint.cleanup(user_choice).await {
+    /// Handles the failed `attempt` of a story.
+    ///
+    /// Every failed attempt is archived and reverted, including the last one
+    /// before the failure policy stops the loop. Once the story is out of
+    /// retries it is marked failed and the failure policy decides whether
+    /// the loop moves on.
+    async fn handle_failure(
+        &self,
+        state: &mut LoopState,
+        story_id: &str,
+        story_title: &str,
+        attempt: usize,
+        failure: &AttemptFailure,
+    ) -> FailureOutcome {
+        let exhausted = attempt >= self.max_retries;
+
+        if exhausted {
+            // Max retries exceeded
             self.emit(LoopEvent::Error {
-                message: format!("Failed to cleanup: {}", e),
+                message: format!(
+                    "Max retries ({}) exceeded for story {} ({}): {}",
+                    self.max_retries, story_id, story_title, failure.message
+                ),
             })
             .await;
+            self.emit(LoopEvent::MaxRetriesExceeded {
+                story_id: story_id.to_string(),
+            })
+            .await;
+            state.failed_story_ids.push(story_id.to_string());
         }
 
-        // Only send Complete event after cleanup finishes
-        self.emit(LoopEvent::Complete).await;
-        state.running = false;
+        let reverted = self.discard_attempt(story_id, attempt, failure).await;
 
-        Ok(state)
+        let stops = exhausted && !self.failure_policy.continues_after(state.failed_story_ids.len());
+        if stops || !reverted {
+            FailureOutcome::Stop
+        } else if exhausted {
+            // Failure policy allows skipping to the next story
+            FailureOutcome::NextStory
+        } else {
+            FailureOutcome::Retry
+        }
+    }
+
+    /// Runs a single agent attempt for a story.
+    ///
+    /// Streams agent events to the TUI, parses the promise signal, and on
+    /// `<promise>COMPLETE</promise>` checks tasks.md against the pre-attempt
+    /// `plan` and runs the verification gate. Returns the agent's final
+    /// response if the attempt succeeded, or the reason it failed.
+    async fn run_attempt(
+        &self,
+        adapter: &dyn SpecAdapter,
+        work_dir: &Path,
+        story_id: &str,
+        prompt: &Prompt,
+        plan: Option<&PlanSnapshot>,
+    ) -> std::result::Result<Option<Response>, AttemptFailure> {
+        let (final_response, exit) = self.stream_agent(work_dir, story_id, prompt).await?;
+
+        // Parse agent output for signals
+        let final_content = final_response.as_ref().map(|r| r.content.as_str()).unwrap_or("");
+        match parse_agent_result(final_content) {
+            AgentResult::Complete => {
+                let stories = self.reread_stories(work_dir).await?;
+                let stories = self.check_plan_integrity(plan, stories, story_id).await?;
+                self.check_tasks_updated(&stories, story_id).await?;
+                self.run_verification(adapter, work_dir, story_id).await?;
+                Ok(final_response)
+            }
+            // Agent explicitly reported failure
+            AgentResult::Failed(reason) => Err(AttemptFailure::new(reason.clone(), Some(reason))),
+            // Abnormal termination - no promise signal; a crashed agent's
+            // exit status and stderr explain why
+            AgentResult::NoSignal => Err(match exit.filter(|exit| !exit.success()) {
+                Some(exit) => no_signal_failure(&exit),
+                None => AttemptFailure::new("agent finished without completion signal", None),
+            }),
+        }
+    }
+
+    /// Archives a failed attempt and reverts its footprint back to the
+    /// checkpoint.
+    ///
+    /// Returns false if the revert failed and the loop cannot go on.
+    async fn discard_attempt(&self, story_id: &str, attempt: usize, failure: &AttemptFailure) -> bool {
+        // Archive the failed attempt so it can be inspected later
+        let archive_ref = match self
+            .checkpoint
+            .archive_attempt(story_id, attempt, &failure.describe())
+            .await
+        {
+            Ok(ref_name) => Some(ref_name),
+            Err(e) => {
+                self.emit(LoopEvent::Error {
+                    message: format!(
+                        "Warning: Failed to archive attempt {} for story {}: {}",
+                        attempt, story_id, e
+                    ),
+                })
+                .await;
+                None
+            }
+        };
+
+        // Revert the attempt's footprint back to the checkpoint
+        match self.checkpoint.revert().await {
+            Ok(summary) => {
+                self.emit(LoopEvent::Reverted {
+                    story_id: story_id.to_string(),
+                    restored: summary.restored,
+                    removed: summary.removed,
+                    archive_ref,
+                })
+                .await;
+                true
+            }
+            Err(e) => {
+                self.emit(LoopEvent::Error {
+                    message: format!("Failed to revert checkpoint for story {}: {}", story_id, e),
+                })
+                .await;
+                false
+            }
+        }
+    }
+
+    /// Runs the agent and streams its events to the TUI.
+    ///
+    /// Returns the agent's final response, if it sent one, and how its
+    /// process exited. A stop request kills the agent mid-story and fails
+    /// the attempt as cancelled.
+    async fn stream_agent(
+        &self,
+        work_dir: &Path,
+        story_id: &str,
+        prompt: &Prompt,
+    ) -> std::result::Result<(Option<Response>, Option<AgentExit>), AttemptFailure> {
+        // Agent error - treat as failure and retry
+        let mut stream = self
+            .agent
+            .run(prompt, work_dir)
+            .map_err(|e| AttemptFailure::new(e.to_string(), None))?;
+
+        let mut final_response: Option<Response> = None;
+        let mut exit: Option<AgentExit> = None;
+
+        // Process streaming events until the agent is done or the loop stops
+        loop {
+            let event = tokio::select! {
+                event = stream.next() => event,
+                () = self.stop_token.cancelled() => {
+                    stream.kill().await;
+                    return Err(AttemptFailure::cancelled("the agent was running"));
+                }
+            };
+            let Some(event) = event else {
+                break;
+            };
+
+            match &event {
+                // Store final response for completion check and commit trailers
+                StreamEvent::Done(response) => final_response = Some(response.clone()),
+                StreamEvent::Exited(status) => exit = Some(status.clone()),
+                _ => {}
+            }
+            // Emit event with story context
+            self.emit(LoopEvent::StoryEvent {
+                story_id: story_id.to_string(),
+                event,
+            })
+            .await;
+        }
+
+        Ok((final_response, exit))
+    }
+
+    /// Re-reads the stories from tasks.md after a COMPLETE signal.
+    async fn reread_stories(&self, work_dir: &Path) -> std::result::Result<Vec<Story>, AttemptFailure> {
+        spec::create_adapter_in_dir(&self.change_name, work_dir, self.command_timeout)
+            .await
+            .and_then(|adapter| adapter.stories())
+            .map_err(|e| {
+                AttemptFailure::new(format!("failed to re-read tasks after completion signal: {}", e), None)
+            })
+    }
+
+    /// Compares the stories the agent left in tasks.md with the pre-attempt
+    /// `plan`, allowing only ticks of the story's own open tasks.
+    ///
+    /// Any other change is reported as a `PlanChanged` event and either fails
+    /// the attempt or is repaired by restoring the snapshot with just the
+    /// allowed ticks. Returns the stories to check completion against.
+    async fn check_plan_integrity(
+        &self,
+        plan: Option<&PlanSnapshot>,
+        stories: Vec<Story>,
+        story_id: &str,
+    ) -> std::result::Result<Vec<Story>, AttemptFailure> {
+        let action = self.integrity_action;
+        let Some(plan) = plan.filter(|_| action != IntegrityAction::Off) else {
+            return Ok(stories);
+        };
+
+        let changes = integrity::disallowed_changes(&plan.stories, &stories, story_id);
+        if changes.is_empty() {
+            return Ok(stories);
+        }
+
+        let ticked = integrity::ticked_tasks(&plan.stories, &stories, story_id);
+        let reason = integrity::retry_reason(&changes);
+        self.emit(LoopEvent::PlanChanged {
+            story_id: story_id.to_string(),
+            changes,
+            action,
+        })
+        .await;
+
+        match action {
+            IntegrityAction::Repair => plan.repair(story_id, &ticked).map_err(|e| {
+                AttemptFailure::new(format!("failed to repair tasks.md: {}", e), None)
+            }),
+            _ => Err(AttemptFailure::new(
+                "agent changed the plan in tasks.md beyond ticking its own tasks",
+                Some(reason),
+            )),
+        }
+    }
+
+    /// Checks that the agent actually ticked every task of the story in the
+    /// stories re-read after a COMPLETE signal.
+    ///
+    /// Without this check the story loop would find the same story incomplete
+    /// and silently spend another agent run on it without counting a retry.
+    async fn check_tasks_updated(
+        &self,
+        stories: &[Story],
+        story_id: &str,
+    ) -> std::result::Result<(), AttemptFailure> {
+        let unchecked = unchecked_task_ids(stories, story_id);
+        if unchecked.is_empty() {
+            return Ok(());
+        }
+
+        self.emit(LoopEvent::SignalStateMismatch {
+            story_id: story_id.to_string(),
+            unchecked_tasks: unchecked.clone(),
+        })
+        .await;
+
+        Err(AttemptFailure::new(
+            format!(
+                "agent signaled COMPLETE but tasks {} are still unchecked",
+                unchecked.join(", ")
+            ),
+            Some(format!(
+                "You signaled COMPLETE, but these tasks of Story {} are still unchecked in tasks.md: {}. \
+                 Finish the remaining work and mark each task `- [x]` in tasks.md before signaling COMPLETE.",
+                story_id,
+                unchecked.join(", ")
+            )),
+        ))
+    }
+
+    /// Runs the project's verification commands after a COMPLETE signal.
+    ///
+    /// Emits a `Verification` event per command and stops at the first failure,
+    /// whose output becomes the retry reason for the next attempt. A stop
+    /// request kills the running command and fails the attempt as cancelled.
+    async fn run_verification(
+        &self,
+        adapter: &dyn SpecAdapter,
+        work_dir: &Path,
+        story_id: &str,
+    ) -> std::result::Result<(), AttemptFailure> {
+        let verify = adapter.verify_commands().map_err(|e| {
+            AttemptFailure::new(format!("failed to get verification commands: {}", e), None)
+        })?;
+
+        for command in verify::commands_to_run(&verify) {
+            let result = tokio::select! {
+                result = verify::run_command(&command, work_dir, self.verify_timeout) => result,
+                () = self.stop_token.cancelled() => {
+                    return Err(AttemptFailure::cancelled("verification was running"));
+                }
+            };
+            let passed = result.passed;
+            let reason = verify::retry_reason(&result);
+
+            self.emit(LoopEvent::Verification {
+                story_id: story_id.to_string(),
+                result,
+            })
+            .await;
+
+            if !passed {
+                return Err(AttemptFailure::new(
+                    format!("verification command `{}` failed", command),
+                    Some(reason),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Checks a verified attempt's changes before they are committed: their
+    /// size, protected paths and the pre-commit scan.
+    ///
+    /// Returns the paths to leave out of the checkpoint.
+    async fn check_changes(&self, story_id: &str) -> std::result::Result<Vec<String>, AttemptFailure> {
+        self.check_diff_limits(story_id).await?;
+        self.check_protected_paths().await?;
+        self.scan_changes(story_id).await
+    }
+
+    /// Fails the attempt if its changes exceed the diff limits, unless the
+    /// action is `Ask` and the user accepts them.
+    ///
+    /// Failing to measure the changes fails the attempt as well.
+    async fn check_diff_limits(&self, story_id: &str) -> std::result::Result<(), AttemptFailure> {
+        if self.diff_limits.is_unlimited() {
+            return Ok(());
+        }
+
+        let stat = self.checkpoint.pending_diffstat().await.map_err(|e| {
+            AttemptFailure::new(format!("failed to measure changes for the diff limits: {}", e), None)
+        })?;
+        let exceeded = self.diff_limits.exceeded(&stat);
+        if exceeded.is_empty() {
+            return Ok(());
+        }
+
+        let action = self.diff_limits.action;
+        self.emit(LoopEvent::DiffLimitExceeded {
+            story_id: story_id.to_string(),
+            stat,
+            exceeded: exceeded.clone(),
+            action,
+        })
+        .await;
+
+        if action == DiffLimitAction::Ask {
+            let (approve_tx, approve_rx) = oneshot::channel::<bool>();
+            self.emit(LoopEvent::AwaitingDiffApproval {
+                story_id: story_id.to_string(),
+                stat,
+                exceeded: exceeded.clone(),
+                approve_tx,
+            })
+            .await;
+            // A dropped sender (nobody to ask) rejects the changes
+            if approve_rx.await.unwrap_or(false) {
+                return Ok(());
+            }
+        }
+
+        Err(AttemptFailure::new(
+            format!("attempt exceeded the diff limits: {}", exceeded.join(", ")),
+            Some(diff_limit::retry_reason(&exceeded)),
+        ))
+    }
+
+    /// Fails the attempt if it changed any protected path.
+    ///
+    /// The retry reason names the offending files, so the next attempt
+    /// starts from the reverted checkpoint knowing what to leave alone.
+    /// Failing to list the changes fails the attempt as well.
+    async fn check_protected_paths(&self) -> std::result::Result<(), AttemptFailure> {
+        if self.protected_paths.is_empty() {
+            return Ok(());
+        }
+
+        let changed = self.checkpoint.pending_files().await.map_err(|e| {
+            AttemptFailure::new(format!("failed to list changes for the protected-path check: {}", e), None)
+        })?;
+        let files = protected::violations(&changed, &self.protected_paths);
+        if files.is_empty() {
+            return Ok(());
+        }
+
+        Err(AttemptFailure::new(
+            format!("attempt changed protected files: {}", files.join(", ")),
+            Some(protected::retry_reason(&files)),
+        ))
+    }
+
+    /// Scans the files the checkpoint would commit.
+    ///
+    /// Returns the paths to leave out of the checkpoint, or fails the attempt
+    /// if the scan action is `Fail` and anything was found, or if the scan
+    /// could not run. A failure to list the files only warns, since the
+    /// checkpoint commit reports it again.
+    async fn scan_changes(&self, story_id: &str) -> std::result::Result<Vec<String>, AttemptFailure> {
+        let action = self.scan_config.action;
+        if action == ScanAction::Off {
+            return Ok(Vec::new());
+        }
+
+        let listed = match self.checkpoint.top_level_dir().await {
+            Ok(root) => self.checkpoint.pending_files().await.map(|paths| (root, paths)),
+            Err(e) => Err(e),
+        };
+        let (root, paths) = match listed {
+            Ok(listed) => listed,
+            Err(e) => {
+                self.emit(LoopEvent::Error {
+                    message: format!("Warning: Failed to list changes of story {} for scanning: {}", story_id, e),
+                })
+                .await;
+                return Ok(Vec::new());
+            }
+        };
+        let findings = scan::scan_files(root, paths, self.scan_config.clone())
+            .await
+            .map_err(|e| AttemptFailure::new(format!("pre-commit scan could not run: {}", e), None))?;
+        if findings.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let offending = scan::offending_paths(&findings);
+        let reason = scan::retry_reason(&findings);
+        self.emit(LoopEvent::ScanFindings {
+            story_id: story_id.to_string(),
+            findings,
+            action,
+        })
+        .await;
+
+        match action {
+            ScanAction::Fail => Err(AttemptFailure::new(
+                "pre-commit scan found secrets or disallowed files",
+                Some(reason),
+            )),
+            _ => Ok(offending),
+        }
     }
 
     /// Emit a loop event.
@@ -384,6 +950,54 @@ impl Orchestrator {
     }
 }
 
+/// Why a single story attempt failed.
+#[derive(Debug, Clone, PartialEq)]
+struct AttemptFailure {
+    /// Description used in the max-retries error message.
+    message: String,
+    /// Context passed to the next attempt's prompt, if any.
+    retry_reason: Option<String>,
+    /// The loop was stopped while the attempt ran; it is not retried.
+    cancelled: bool,
+}
+
+impl AttemptFailure {
+    fn new(message: impl Into<String>, retry_reason: Option<String>) -> Self {
+        Self {
+            message: message.into(),
+            retry_reason,
+            cancelled: false,
+        }
+    }
+
+    /// An attempt interrupted by a stop request `during` a step.
+    fn cancelled(during: &str) -> Self {
+        Self {
+            cancelled: true,
+            ..Self::new(format!("stopped while {}", during), None)
+        }
+    }
+
+    /// Full description of the failure, including retry context if it adds detail.
+    fn describe(&self) -> String {
+        match &self.retry_reason {
+            Some(reason) if *reason != self.message => format!("{}\n\n{}", self.message, reason),
+            _ => self.message.clone(),
+        }
+    }
+}
+
+/// What the story loop does after a failed attempt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum FailureOutcome {
+    /// Retry the same story.
+    Retry,
+    /// Give up on the story and continue with the next one.
+    NextStory,
+    /// Stop the loop.
+    Stop,
+}
+
 /// Result of parsing agent output for promise signals.
 #[derive(Debug, PartialEq)]
 enum AgentResult {
@@ -418,9 +1032,50 @@ fn parse_agent_result(content: &str) -> AgentResult {
     AgentResult::NoSignal
 }
 
-/// Returns the first incomplete story, or None if all are complete.
-fn next_incomplete_story(stories: &[Story]) -> Option<&Story> {
-    stories.iter().find(|s| !is_story_complete(s))
+/// Builds the failure of an agent that died without a promise signal.
+///
+/// The retry prompt gets the tail of stderr, which often names the cause
+/// (a crash, an auth problem, a rate limit).
+fn no_signal_failure(exit: &AgentExit) -> AttemptFailure {
+    let stderr = exit.stderr.trim();
+    let last_line = stderr.lines().next_back().unwrap_or_default();
+    let message = if last_line.is_empty() {
+        format!("agent {} without completion signal", exit.status())
+    } else {
+        format!("agent {} without completion signal: {}", exit.status(), last_line)
+    };
+    let retry_reason = (!stderr.is_empty()).then(|| {
+        format!(
+            "The previous attempt's agent process {} before finishing. Its stderr ended with:\n{}",
+            exit.status(),
+            stderr
+        )
+    });
+    AttemptFailure::new(message, retry_reason)
+}
+
+/// Returns the IDs of the story's tasks that are not marked done.
+///
+/// A story that is missing or has no tasks cannot be complete, so it is
+/// reported with a placeholder entry.
+fn unchecked_task_ids(stories: &[Story], story_id: &str) -> Vec<String> {
+    match stories.iter().find(|s| s.id == story_id) {
+        Some(story) if !story.tasks.is_empty() => story
+            .tasks
+            .iter()
+            .filter(|t| !t.done)
+            .map(|t| t.id.clone())
+            .collect(),
+        _ => vec![format!("(story {} has no tasks)", story_id)],
+    }
+}
+
+/// Returns the first incomplete story that has not already failed,
+/// or None if no such story remains.
+fn next_incomplete_story<'a>(stories: &'a [Story], failed_story_ids: &[String]) -> Option<&'a Story> {
+    stories
+        .iter()
+        .find(|s| !is_story_complete(s) && !failed_story_ids.contains(&s.id))
 }
 
 /// Checks if a story is complete (all tasks done).
@@ -431,25 +1086,106 @@ fn is_story_complete(story: &Story) -> bool {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::agent::claude::ClaudeParser;
     use crate::agent::{AgentStream, Prompt};
     use crate::spec::Task;
-    use std::io::{BufRead, BufReader};
-    use std::process::{Command, Stdio};
+    use std::process::Stdio;
+    use std::sync::Arc;
+
+    /// Agent running a shell script in place of the agent CLI.
+    struct MockAgent {
+        script: &'static str,
+    }
 
-    struct MockAgent;
+    /// Agent that exits without output.
+    const QUIET_AGENT: MockAgent = MockAgent { script: "true" };
 
     impl CodingAgent for MockAgent {
-        fn run(&self, _prompt: &Prompt) -> Result<AgentStream> {
-            // Create a simple command that outputs nothing (for mock purposes)
-            let mut child = Command::new("true")
+        fn run(&self, _prompt: &Prompt, _work_dir: &Path) -> Result<AgentStream> {
+            let child = tokio::process::Command::new("sh")
+                .args(["-c", self.script])
                 .stdout(Stdio::piped())
+                .kill_on_drop(true)
                 .spawn()
                 .expect("Failed to spawn mock process");
 
-            let stdout = child.stdout.take().unwrap();
-            let reader = BufReader::new(stdout);
+            AgentStream::new(child, ClaudeParser)
+        }
+    }
+
+    /// Checkpoint that records archived attempts and reverts.
+    #[derive(Default)]
+    struct RecordingCheckpoint {
+        archived: std::sync::Mutex<Vec<(String, usize)>>,
+        reverts: std::sync::Mutex<usize>,
+    }
 
-            Ok(AgentStream::new_for_test(child, reader.lines()))
+    #[async_trait::async_trait]
+    impl Checkpoint for Arc<RecordingCheckpoint> {
+        fn work_dir(&self) -> Result<PathBuf> {
+            Ok(std::env::temp_dir())
+        }
+        async fn lock(&self) -> Result<checkpoint::RunLock> {
+            Err(Error::Command {
+                cmd: "lock".to_string(),
+                stderr: "the recording checkpoint takes no lock".to_string(),
+            })
+        }
+        async fn preflight(&self, _untracked_size_limit_mb: u64) -> Result<Vec<checkpoint::PreflightIssue>> {
+            Ok(Vec::new())
+        }
+        async fn result_dir(&self) -> Result<PathBuf> {
+            self.work_dir()
+        }
+        async fn list_attempts(&self) -> Result<Vec<checkpoint::Attempt>> {
+            Ok(Vec::new())
+        }
+        async fn list_checkpoints(&self) -> Result<Vec<checkpoint::StoryCheckpoint>> {
+            Ok(Vec::new())
+        }
+        async fn attempt_diff(&self, _ref_name: &str) -> Result<String> {
+            Ok(String::new())
+        }
+        async fn rollback_to(&self, _commit: &str) -> Result<()> {
+            Ok(())
+        }
+        async fn init(&mut self) -> Result<()> {
+            Ok(())
+        }
+        async fn resume(&mut self) -> Result<()> {
+            Ok(())
+        }
+        async fn begin_attempt(&mut self) -> Result<()> {
+            Ok(())
+        }
+        async fn pending_files(&self) -> Result<Vec<String>> {
+            Ok(Vec::new())
+        }
+        async fn top_level_dir(&self) -> Result<PathBuf> {
+            self.work_dir()
+        }
+        async fn pending_diffstat(&self) -> Result<checkpoint::DiffStat> {
+            Ok(checkpoint::DiffStat::default())
+        }
+        async fn commit_checkpoint(&self, _commit: &CheckpointCommit) -> Result<()> {
+            Ok(())
+        }
+        async fn archive_attempt(&self, story_id: &str, attempt: usize, _reason: &str) -> Result<String> {
+            self.archived.lock().unwrap().push((story_id.to_string(), attempt));
+            Ok(format!("refs/ralph/test-change/attempts/{}-{}", story_id, attempt))
+        }
+        async fn revert(&self) -> Result<checkpoint::RevertSummary> {
+            *self.reverts.lock().unwrap() += 1;
+            Ok(checkpoint::RevertSummary {
+                removed: vec!["partial.rs".to_string()],
+                ..Default::default()
+            })
+        }
+        async fn cleanup(&self, _option: CompletionOption) -> Result<()> {
+            Ok(())
+        }
+        async fn export_patches(&self, _dir: &Path) -> Result<Vec<PathBuf>> {
+            Ok(Vec::new())
         }
     }
 
@@ -476,7 +1212,7 @@ mod tests {
             },
         ];
 
-        let next = next_incomplete_story(&stories);
+        let next = next_incomplete_story(&stories, &[]);
         assert!(next.is_some());
         assert_eq!(next.unwrap().id, "2");
     }
@@ -493,10 +1229,165 @@ mod tests {
             }],
         }];
 
-        let next = next_incomplete_story(&stories);
+        let next = next_incomplete_story(&stories, &[]);
+        assert!(next.is_none());
+    }
+
+    #[test]
+    fn next_incomplete_story_skips_failed_stories() {
+        let stories = vec![
+            Story {
+                id: "1".to_string(),
+                title: "First".to_string(),
+                tasks: vec![Task {
+                    id: "1.1".to_string(),
+                    description: "Not done".to_string(),
+                    done: false,
+                }],
+            },
+            Story {
+                id: "2".to_string(),
+                title: "Second".to_string(),
+                tasks: vec![Task {
+                    id: "2.1".to_string(),
+                    description: "Not done".to_string(),
+                    done: false,
+                }],
+            },
+        ];
+
+        let next = next_incomplete_story(&stories, &["1".to_string()]);
+        assert_eq!(next.map(|s| s.id.as_str()), Some("2"));
+
+        let next = next_incomplete_story(&stories, &["1".to_string(), "2".to_string()]);
         assert!(next.is_none());
     }
 
+    #[test]
+    fn orchestrator_defaults_to_abort_policy() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let orchestrator = Orchestrator::new(
+            "test-change",
+            Box::new(QUIET_AGENT),
+            tx,
+            DEFAULT_MAX_RETRIES,
+        );
+        assert_eq!(orchestrator.failure_policy, FailurePolicy::Abort);
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let orchestrator = Orchestrator::new(
+            "test-change",
+            Box::new(QUIET_AGENT),
+            tx,
+            DEFAULT_MAX_RETRIES,
+        )
+        .with_failure_policy(FailurePolicy::Skip);
+        assert_eq!(orchestrator.failure_policy, FailurePolicy::Skip);
+    }
+
+    #[tokio::test]
+    async fn protected_path_changes_fail_the_attempt() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let project = temp_dir.path().join("project");
+        std::fs::create_dir_all(&project).unwrap();
+        std::fs::write(project.join("Cargo.lock"), "v1").unwrap();
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let mut orchestrator = Orchestrator::new("test-change", Box::new(QUIET_AGENT), tx, DEFAULT_MAX_RETRIES)
+            .with_protected_paths(vec!["Cargo.lock".to_string()]);
+        orchestrator.checkpoint = Box::new(checkpoint::SnapshotCheckpoint::with_dirs(
+            "test-change",
+            project.clone(),
+            temp_dir.path().join("snapshots"),
+        ));
+        orchestrator.checkpoint.init().await.unwrap();
+
+        std::fs::write(project.join("src.rs"), "fn main() {}").unwrap();
+        assert_eq!(orchestrator.check_protected_paths().await, Ok(()));
+
+        std::fs::write(project.join("Cargo.lock"), "v2").unwrap();
+        let failure = orchestrator.check_protected_paths().await.unwrap_err();
+        assert_eq!(failure.message, "attempt changed protected files: Cargo.lock");
+        assert!(failure.retry_reason.unwrap().contains("- Cargo.lock"));
+    }
+
+    #[test]
+    fn orchestrator_resume_is_opt_in() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let orchestrator = Orchestrator::new(
+            "test-change",
+            Box::new(QUIET_AGENT),
+            tx,
+            DEFAULT_MAX_RETRIES,
+        );
+        assert!(!orchestrator.resume);
+
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let orchestrator = Orchestrator::new(
+            "test-change",
+            Box::new(QUIET_AGENT),
+            tx,
+            DEFAULT_MAX_RETRIES,
+        )
+        .with_resume(true);
+        assert!(orchestrator.resume);
+    }
+
+    #[test]
+    fn attempt_failure_describe_includes_distinct_retry_reason() {
+        let failure = AttemptFailure::new("verification failed", Some("cargo test failed".to_string()));
+        assert_eq!(failure.describe(), "verification failed\n\ncargo test failed");
+
+        let failure = AttemptFailure::new("blocked", Some("blocked".to_string()));
+        assert_eq!(failure.describe(), "blocked");
+
+        let failure = AttemptFailure::new("no signal", None);
+        assert_eq!(failure.describe(), "no signal");
+    }
+
+    #[test]
+    fn unchecked_task_ids_lists_incomplete_tasks() {
+        let stories = vec![Story {
+            id: "1".to_string(),
+            title: "First".to_string(),
+            tasks: vec![
+                Task {
+                    id: "1.1".to_string(),
+                    description: "Done".to_string(),
+                    done: true,
+                },
+                Task {
+                    id: "1.2".to_string(),
+                    description: "Not done".to_string(),
+                    done: false,
+                },
+            ],
+        }];
+
+        assert_eq!(unchecked_task_ids(&stories, "1"), vec!["1.2".to_string()]);
+    }
+
+    #[test]
+    fn unchecked_task_ids_empty_when_story_complete() {
+        let stories = vec![Story {
+            id: "1".to_string(),
+            title: "First".to_string(),
+            tasks: vec![Task {
+                id: "1.1".to_string(),
+                description: "Done".to_string(),
+                done: true,
+            }],
+        }];
+
+        assert!(unchecked_task_ids(&stories, "1").is_empty());
+    }
+
+    #[test]
+    fn unchecked_task_ids_flags_missing_story() {
+        let stories: Vec<Story> = Vec::new();
+        assert_eq!(unchecked_task_ids(&stories, "3").len(), 1);
+    }
+
     #[test]
     fn is_story_complete_true_when_all_tasks_done() {
         let story = Story {
@@ -558,18 +1449,191 @@ mod tests {
 
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         );
 
-        // Set stop flag before running
-        orchestrator.stop_flag.store(true, Ordering::Relaxed);
+        // Request a stop before running
+        orchestrator.stop_token.cancel();
 
         // The run would return early due to stop flag
         // (Can't actually run without a real change, but this validates structure)
     }
 
+    #[tokio::test]
+    async fn stop_request_kills_the_running_agent() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let agent = MockAgent {
+            script: concat!(
+                r#"echo '{"type":"assistant","message":{"content":[{"type":"text","text":"Working"}]}}';"#,
+                "exec sleep 30",
+            ),
+        };
+        let orchestrator = Orchestrator::new("test-change", Box::new(agent), tx, DEFAULT_MAX_RETRIES);
+        let stop_token = orchestrator.stop_handle();
+
+        let work_dir = std::env::temp_dir();
+        let prompt = Prompt::default();
+        let attempt = orchestrator.stream_agent(&work_dir, "1", &prompt);
+        let stop = async {
+            // Stop once the agent is running
+            let event = rx.recv().await;
+            assert!(matches!(event, Some(LoopEvent::StoryEvent { .. })));
+            stop_token.cancel();
+        };
+        let (result, ()) = tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(attempt, stop) })
+            .await
+            .expect("stop request should end the attempt");
+
+        let failure = result.unwrap_err();
+        assert!(failure.cancelled);
+        assert_eq!(failure.retry_reason, None);
+    }
+
+    /// Adapter whose only verification command is `tests`.
+    struct VerifyAdapter {
+        tests: &'static str,
+    }
+
+    impl SpecAdapter for VerifyAdapter {
+        fn stories(&self) -> Result<Vec<Story>> {
+            Ok(Vec::new())
+        }
+        fn scenarios(&self) -> Result<Vec<spec::Scenario>> {
+            Ok(Vec::new())
+        }
+        fn context(&self, _story_id: &str) -> Result<spec::Context> {
+            unreachable!("verification does not read the context")
+        }
+        fn verify_commands(&self) -> Result<spec::VerifyCommands> {
+            Ok(spec::VerifyCommands {
+                checks: Vec::new(),
+                tests: self.tests.to_string(),
+            })
+        }
+        fn tasks_file(&self) -> PathBuf {
+            PathBuf::from("tasks.md")
+        }
+        fn tool_prompt(&self) -> String {
+            String::new()
+        }
+    }
+
+    #[tokio::test]
+    async fn stop_request_cancels_running_verification() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let orchestrator = Orchestrator::new("test-change", Box::new(QUIET_AGENT), tx, DEFAULT_MAX_RETRIES);
+        let stop_token = orchestrator.stop_handle();
+        let adapter = VerifyAdapter { tests: "sleep 30" };
+
+        let work_dir = std::env::temp_dir();
+        let verification = orchestrator.run_verification(&adapter, &work_dir, "1");
+        let stop = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            stop_token.cancel();
+        };
+        let (result, ()) = tokio::time::timeout(Duration::from_secs(5), async { tokio::join!(verification, stop) })
+            .await
+            .expect("stop request should end the verification");
+
+        let failure = result.unwrap_err();
+        assert!(failure.cancelled);
+    }
+
+    #[tokio::test]
+    async fn abort_archives_and_reverts_the_final_attempt() {
+        let (tx, mut rx) = tokio::sync::mpsc::channel(10);
+        let recorder = Arc::new(RecordingCheckpoint::default());
+        let mut orchestrator = Orchestrator::new("test-change", Box::new(QUIET_AGENT), tx, 1)
+            .with_failure_policy(FailurePolicy::Abort);
+        orchestrator.checkpoint = Box::new(Arc::clone(&recorder));
+        let mut state = LoopState::new("test-change");
+
+        let failure = AttemptFailure::new("tests failed", None);
+        let outcome = orchestrator
+            .handle_failure(&mut state, "1", "First", 1, &failure)
+            .await;
+        drop(orchestrator);
+
+        assert_eq!(outcome, FailureOutcome::Stop);
+        assert_eq!(state.failed_story_ids, vec!["1".to_string()]);
+        assert_eq!(*recorder.archived.lock().unwrap(), vec![("1".to_string(), 1)]);
+        assert_eq!(*recorder.reverts.lock().unwrap(), 1);
+
+        let mut reverted = None;
+        while let Some(event) = rx.recv().await {
+            if let LoopEvent::Reverted { removed, archive_ref, .. } = event {
+                reverted = Some((removed, archive_ref));
+            }
+        }
+        assert_eq!(
+            reverted,
+            Some((
+                vec!["partial.rs".to_string()],
+                Some("refs/ralph/test-change/attempts/1-1".to_string())
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn failure_retries_until_the_story_is_out_of_retries() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let recorder = Arc::new(RecordingCheckpoint::default());
+        let mut orchestrator = Orchestrator::new("test-change", Box::new(QUIET_AGENT), tx, 2)
+            .with_failure_policy(FailurePolicy::Skip);
+        orchestrator.checkpoint = Box::new(Arc::clone(&recorder));
+        let mut state = LoopState::new("test-change");
+        let failure = AttemptFailure::new("tests failed", None);
+
+        let first = orchestrator.handle_failure(&mut state, "1", "First", 1, &failure).await;
+        let second = orchestrator.handle_failure(&mut state, "1", "First", 2, &failure).await;
+
+        assert_eq!(first, FailureOutcome::Retry);
+        assert_eq!(second, FailureOutcome::NextStory);
+        assert_eq!(recorder.archived.lock().unwrap().len(), 2);
+        assert_eq!(*recorder.reverts.lock().unwrap(), 2);
+    }
+
+    #[tokio::test]
+    async fn stream_agent_returns_the_final_response() {
+        let (tx, _rx) = tokio::sync::mpsc::channel(10);
+        let agent = MockAgent {
+            script: r#"echo '{"type":"result","result":"<promise>COMPLETE</promise>"}'"#,
+        };
+        let orchestrator = Orchestrator::new("test-change", Box::new(agent), tx, DEFAULT_MAX_RETRIES);
+
+        let result = orchestrator
+            .stream_agent(&std::env::temp_dir(), "1", &Prompt::default())
+            .await
+            .unwrap();
+        let (response, exit) = result;
+        assert_eq!(response.map(|r| r.content), Some(COMPLETION_SIGNAL.to_string()));
+        assert_eq!(exit.map(|exit| exit.success()), Some(true));
+    }
+
+    #[test]
+    fn no_signal_failure_explains_the_exit() {
+        let failure = no_signal_failure(&AgentExit {
+            code: Some(1),
+            stderr: "Retrying...\nError: rate limit exceeded\n".to_string(),
+        });
+        assert_eq!(
+            failure.message,
+            "agent exited with code 1 without completion signal: Error: rate limit exceeded"
+        );
+        let reason = failure.retry_reason.unwrap();
+        assert!(reason.contains("exited with code 1"));
+        assert!(reason.ends_with("Retrying...\nError: rate limit exceeded"));
+
+        let failure = no_signal_failure(&AgentExit {
+            code: None,
+            stderr: String::new(),
+        });
+        assert_eq!(failure.message, "agent was killed by a signal without completion signal");
+        assert_eq!(failure.retry_reason, None);
+    }
+
     #[test]
     fn completion_signal_constant_is_correct() {
         assert_eq!(COMPLETION_SIGNAL, "<promise>COMPLETE</promise>");
@@ -646,7 +1710,7 @@ mod tests {
         let (tx, _rx) = tokio::sync::mpsc::channel(10);
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             5, // Custom max retries
         );
@@ -659,7 +1723,7 @@ mod tests {
         let (tx, _rx) = tokio::sync::mpsc::channel(10);
         let orchestrator = Orchestrator::new(
             "my-feature",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         );
@@ -809,7 +1873,7 @@ mod tests {
         let (tx, _rx) = tokio::sync::mpsc::channel(10);
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         );
@@ -822,7 +1886,7 @@ mod tests {
         let (tx, _rx) = tokio::sync::mpsc::channel(10);
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         )
@@ -838,7 +1902,7 @@ mod tests {
         let (tx, _rx) = tokio::sync::mpsc::channel(10);
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         )
@@ -855,7 +1919,7 @@ mod tests {
         let timeout = Duration::from_secs(45);
         let orchestrator = Orchestrator::new(
             "test-change",
-            Box::new(MockAgent),
+            Box::new(QUIET_AGENT),
             tx,
             DEFAULT_MAX_RETRIES,
         )
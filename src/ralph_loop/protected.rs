@@ -0,0 +1,59 @@
+//! Protected paths the agent must not touch.
+//!
+//! Before a story is committed as a checkpoint, the orchestrator checks the
+//! attempt's changed files against the configured glob patterns (for example
+//! `.github/workflows/*` or `Cargo.lock`). Any match fails the attempt, so it
+//! is reverted and retried with a reason naming the offending files.
+
+use super::scan::path_matches;
+
+/// Returns the changed paths matching any protected glob, in input order.
+///
+/// Globs without a `/` match the file name, others the whole path relative
+/// to the repository root; `*` also matches across `/`.
+pub fn violations(changed: &[String], protected: &[String]) -> Vec<String> {
+    changed
+        .iter()
+        .filter(|path| protected.iter().any(|glob| path_matches(glob, path)))
+        .cloned()
+        .collect()
+}
+
+/// Builds the retry reason telling the agent which files to leave alone.
+pub fn retry_reason(files: &[String]) -> String {
+    super::list_retry_reason(
+        "Your changes touched protected files, which must not be modified, created or deleted:",
+        files,
+        "Complete the story without changing these files.",
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|v| v.to_string()).collect()
+    }
+
+    #[test]
+    fn violations_match_names_and_paths() {
+        let changed = strings(&[
+            "src/lib.rs",
+            "Cargo.lock",
+            ".github/workflows/ci.yml",
+            "openspec/changes/other/specs/x/spec.md",
+        ]);
+        let protected = strings(&["Cargo.lock", ".github/workflows/*", "openspec/changes/other/*"]);
+
+        assert_eq!(
+            violations(&changed, &protected),
+            strings(&[
+                "Cargo.lock",
+                ".github/workflows/ci.yml",
+                "openspec/changes/other/specs/x/spec.md",
+            ])
+        );
+        assert!(violations(&changed, &[]).is_empty());
+    }
+}
@@ -0,0 +1,199 @@
+//! Orchestrator-owned verification of completed stories.
+//!
+//! After an agent signals `<promise>COMPLETE</promise>`, the orchestrator runs
+//! the project's verification commands itself instead of trusting the signal.
+//! Commands run through `sh -c` so the strings from `VerifyCommands` can use
+//! the same syntax the agent would type in a shell.
+
+use std::path::Path;
+use std::process::Stdio;
+use std::time::Duration;
+
+use crate::async_cmd::ProcessGroupGuard;
+use crate::spec::VerifyCommands;
+
+/// Default timeout in seconds for a single verification command.
+pub const DEFAULT_VERIFY_TIMEOUT_SECS: u64 = 600;
+
+/// Maximum number of output lines kept from a verification command.
+const MAX_OUTPUT_LINES: usize = 60;
+
+/// Result of running a single verification command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationResult {
+    /// The command that was run.
+    pub command: String,
+    /// Whether the command exited successfully.
+    pub passed: bool,
+    /// Tail of the combined stdout/stderr output.
+    pub output: String,
+}
+
+/// Returns the commands to run, checks first and then tests.
+pub fn commands_to_run(verify: &VerifyCommands) -> Vec<String> {
+    let mut commands: Vec<String> = verify
+        .checks
+        .iter()
+        .filter(|c| !c.trim().is_empty())
+        .cloned()
+        .collect();
+    if !verify.tests.trim().is_empty() {
+        commands.push(verify.tests.clone());
+    }
+    commands
+}
+
+/// Runs a verification command in `work_dir` and captures its result.
+///
+/// Spawn failures and timeouts are reported as failed results rather than
+/// errors, since either way the story cannot be accepted. The command runs
+/// in a process group of its own, which is killed on a timeout or when the
+/// returned future is dropped, so a hung test run does not outlive it.
+pub async fn run_command(command: &str, work_dir: &Path, timeout: Duration) -> VerificationResult {
+    let failed = |output: String| VerificationResult {
+        command: command.to_string(),
+        passed: false,
+        output,
+    };
+
+    let child = tokio::process::Command::new("sh")
+        .args(["-c", command])
+        .current_dir(work_dir)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .process_group(0)
+        .kill_on_drop(true)
+        .spawn();
+    let child = match child {
+        Ok(child) => child,
+        Err(e) => return failed(format!("Failed to run command: {}", e)),
+    };
+    let mut group = ProcessGroupGuard::new(child.id());
+
+    let output = match tokio::time::timeout(timeout, child.wait_with_output()).await {
+        Ok(Ok(output)) => output,
+        Ok(Err(e)) => return failed(format!("Failed to run command: {}", e)),
+        Err(_) => return failed(format!("Command timed out after {}s", timeout.as_secs())),
+    };
+    group.disarm();
+
+    let mut combined = String::from_utf8_lossy(&output.stdout).to_string();
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    if !stderr.trim().is_empty() {
+        if !combined.is_empty() && !combined.ends_with('\n') {
+            combined.push('\n');
+        }
+        combined.push_str(&stderr);
+    }
+    VerificationResult {
+        command: command.to_string(),
+        passed: output.status.success(),
+        output: tail_lines(&combined, MAX_OUTPUT_LINES),
+    }
+}
+
+/// Builds the retry reason fed back to the agent for a failed command.
+pub fn retry_reason(result: &VerificationResult) -> String {
+    format!(
+        "You signaled COMPLETE, but the verification command `{}` failed:\n```\n{}\n```",
+        result.command,
+        result.output.trim_end()
+    )
+}
+
+/// Keeps only the last `max` lines of `text`.
+fn tail_lines(text: &str, max: usize) -> String {
+    let lines: Vec<&str> = text.lines().collect();
+    let start = lines.len().saturating_sub(max);
+    lines[start..].join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn commands_to_run_orders_checks_before_tests() {
+        let verify = VerifyCommands {
+            checks: vec!["cargo check".to_string(), "cargo clippy".to_string()],
+            tests: "cargo test".to_string(),
+        };
+        assert_eq!(
+            commands_to_run(&verify),
+            vec!["cargo check", "cargo clippy", "cargo test"]
+        );
+    }
+
+    #[test]
+    fn commands_to_run_skips_empty_commands() {
+        let verify = VerifyCommands {
+            checks: vec!["  ".to_string()],
+            tests: String::new(),
+        };
+        assert!(commands_to_run(&verify).is_empty());
+    }
+
+    #[tokio::test]
+    async fn run_command_reports_success() {
+        let result = run_command("echo ok", Path::new("."), Duration::from_secs(5)).await;
+        assert!(result.passed);
+        assert_eq!(result.output, "ok");
+    }
+
+    #[tokio::test]
+    async fn run_command_captures_stderr_on_failure() {
+        let result = run_command("echo broken >&2; exit 1", Path::new("."), Duration::from_secs(5)).await;
+        assert!(!result.passed);
+        assert!(result.output.contains("broken"));
+    }
+
+    #[tokio::test]
+    async fn run_command_runs_in_work_dir() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("marker.txt"), "").unwrap();
+        let result = run_command("test -f marker.txt", dir.path(), Duration::from_secs(5)).await;
+        assert!(result.passed);
+    }
+
+    #[tokio::test]
+    async fn run_command_times_out_as_failure() {
+        let result = run_command("sleep 5", Path::new("."), Duration::from_millis(50)).await;
+        assert!(!result.passed);
+        assert!(result.output.contains("timed out"));
+    }
+
+    #[tokio::test]
+    async fn run_command_timeout_kills_subprocesses() {
+        let dir = tempfile::TempDir::new().unwrap();
+        let result = run_command(
+            "(sleep 1; touch leaked.txt) & wait",
+            dir.path(),
+            Duration::from_millis(100),
+        )
+        .await;
+        assert!(!result.passed);
+
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(!dir.path().join("leaked.txt").exists());
+    }
+
+    #[test]
+    fn tail_lines_keeps_last_lines() {
+        let text = "1\n2\n3\n4";
+        assert_eq!(tail_lines(text, 2), "3\n4");
+        assert_eq!(tail_lines(text, 10), text);
+    }
+
+    #[test]
+    fn retry_reason_names_command_and_output() {
+        let result = VerificationResult {
+            command: "cargo test".to_string(),
+            passed: false,
+            output: "test foo ... FAILED".to_string(),
+        };
+        let reason = retry_reason(&result);
+        assert!(reason.contains("`cargo test`"));
+        assert!(reason.contains("test foo ... FAILED"));
+    }
+}
@@ -13,6 +13,7 @@ mod types;
 
 pub use types::*;
 
+use std::path::{Path, PathBuf};
 use std::time::Duration;
 
 use crate::error::Result;
@@ -22,7 +23,7 @@ use crate::error::Result;
 /// Provides read-only operations needed by the agent layer:
 /// - Reading stories and scenarios
 /// - Getting context for a story
-/// - Getting verification commands
+/// - Getting verification commands (run by the orchestrator's verification gate)
 /// - Providing spec-tool-specific usage instructions
 ///
 /// Note: Agents mark tasks complete by directly editing tasks.md files,
@@ -38,9 +39,15 @@ pub trait SpecAdapter {
     fn context(&self, story_id: &str) -> Result<Context>;
 
     /// Returns verification commands (checks and tests) for the project.
-    #[allow(dead_code)]
+    ///
+    /// The orchestrator runs these itself after an agent signals completion.
     fn verify_commands(&self) -> Result<VerifyCommands>;
 
+    /// Returns the path of the file the agent edits to mark tasks complete.
+    ///
+    /// The orchestrator snapshots it before each attempt to guard the plan.
+    fn tasks_file(&self) -> PathBuf;
+
     /// Returns spec-tool-specific usage instructions for the agent.
     ///
     /// This provides instructions on how to use the spec tool, including:
@@ -69,13 +76,14 @@ pub async fn create_adapter_async(change_name: &str) -> Result<Box<dyn SpecAdapt
     Ok(Box::new(adapter))
 }
 
-/// Creates a spec adapter for the given change with configurable timeout (async version).
+/// Creates a spec adapter for a change in the project at `root` (async version).
 ///
-/// Uses async command execution to avoid blocking tokio worker threads.
-pub async fn create_adapter_async_with_timeout(
+/// Used when the loop works in a checkout other than the current directory.
+pub async fn create_adapter_in_dir(
     change_name: &str,
+    root: &Path,
     timeout: Duration,
 ) -> Result<Box<dyn SpecAdapter>> {
-    let adapter = openspec::OpenSpecAdapter::new_async_with_timeout(change_name, timeout).await?;
+    let adapter = openspec::OpenSpecAdapter::new_async_in_dir(change_name, root, timeout).await?;
     Ok(Box::new(adapter))
 }
@@ -51,6 +51,8 @@ struct StatusResponse {
 pub struct OpenSpecAdapter {
     #[allow(dead_code)]
     change_name: String,
+    /// Project root the change lives in, used to infer verification commands.
+    root: PathBuf,
     change_dir: PathBuf,
     stories: Vec<Story>,
     scenarios: Vec<Scenario>,
@@ -65,7 +67,8 @@ impl OpenSpecAdapter {
         Self::get_status(change_name)?;
 
         // Determine change directory
-        let change_dir = Self::get_change_dir(change_name)?;
+        let root = std::env::current_dir()?;
+        let change_dir = Self::get_change_dir(&root, change_name)?;
 
         // Parse tasks.md
         let tasks_path = change_dir.join("tasks.md");
@@ -85,6 +88,7 @@ impl OpenSpecAdapter {
 
         Ok(Self {
             change_name: change_name.to_string(),
+            root,
             change_dir,
             stories,
             scenarios,
@@ -116,10 +120,9 @@ impl OpenSpecAdapter {
         Ok(response)
     }
 
-    fn get_change_dir(change_name: &str) -> Result<PathBuf> {
+    fn get_change_dir(root: &Path, change_name: &str) -> Result<PathBuf> {
         // OpenSpec stores changes in openspec/changes/<name>/
-        let cwd = std::env::current_dir()?;
-        let change_dir = cwd.join("openspec").join("changes").join(change_name);
+        let change_dir = root.join("openspec").join("changes").join(change_name);
         if !change_dir.exists() {
             return Err(Error::ChangeNotFound(change_name.to_string()));
         }
@@ -168,16 +171,21 @@ impl OpenSpecAdapter {
     /// Uses async command execution to avoid blocking tokio worker threads.
     #[allow(dead_code)]
     pub async fn new_async(change_name: &str) -> Result<Self> {
-        Self::new_async_with_timeout(change_name, async_cmd::DEFAULT_TIMEOUT).await
+        let root = std::env::current_dir()?;
+        Self::new_async_in_dir(change_name, &root, async_cmd::DEFAULT_TIMEOUT).await
     }
 
-    /// Creates a new OpenSpecAdapter with configurable timeout.
-    pub async fn new_async_with_timeout(change_name: &str, timeout: Duration) -> Result<Self> {
+    /// Creates a new OpenSpecAdapter for a change in the project at `root`.
+    ///
+    /// The openspec CLI runs in `root`, so this works for checkouts other
+    /// than the current directory, such as a checkpoint worktree.
+    pub async fn new_async_in_dir(change_name: &str, root: &Path, timeout: Duration) -> Result<Self> {
         // Verify the change exists by getting its status
-        Self::get_status_async_with_timeout(change_name, timeout).await?;
+        Self::get_status_async_in_dir(change_name, root, timeout).await?;
 
         // Determine change directory (this is just path operations, no I/O needed async)
-        let change_dir = Self::get_change_dir(change_name)?;
+        let root = root.to_path_buf();
+        let change_dir = Self::get_change_dir(&root, change_name)?;
 
         // Parse tasks.md (file I/O is fast enough to do sync)
         let tasks_path = change_dir.join("tasks.md");
@@ -197,6 +205,7 @@ impl OpenSpecAdapter {
 
         Ok(Self {
             change_name: change_name.to_string(),
+            root,
             change_dir,
             stories,
             scenarios,
@@ -220,15 +229,18 @@ impl OpenSpecAdapter {
 
     #[allow(dead_code)]
     async fn get_status_async(change_name: &str) -> Result<StatusResponse> {
-        Self::get_status_async_with_timeout(change_name, async_cmd::DEFAULT_TIMEOUT).await
+        let root = std::env::current_dir()?;
+        Self::get_status_async_in_dir(change_name, &root, async_cmd::DEFAULT_TIMEOUT).await
     }
 
-    async fn get_status_async_with_timeout(
+    async fn get_status_async_in_dir(
         change_name: &str,
+        root: &Path,
         timeout: Duration,
     ) -> Result<StatusResponse> {
-        let output = run_openspec_command_async_with_timeout(
+        let output = run_openspec_command_async_in_dir(
             &["status", "--change", change_name, "--json"],
+            root,
             timeout,
         )
         .await?;
@@ -276,6 +288,15 @@ async fn run_openspec_command_async_with_timeout(
     async_cmd::run_stdout_with_timeout("openspec", args, timeout).await
 }
 
+/// Runs an openspec CLI command asynchronously in `dir` with configurable timeout.
+async fn run_openspec_command_async_in_dir(
+    args: &[&str],
+    dir: &Path,
+    timeout: Duration,
+) -> Result<String> {
+    async_cmd::run_stdout_in_dir_with_timeout("openspec", args, dir, timeout).await
+}
+
 /// Parses tasks.md content into Story hierarchy.
 ///
 /// Format:
@@ -480,12 +501,10 @@ fn extract_step(line: &str) -> String {
     line.to_string()
 }
 
-/// Infers verification commands from project type.
-fn infer_verify_commands() -> Result<VerifyCommands> {
-    let cwd = std::env::current_dir()?;
-
+/// Infers verification commands from the type of the project at `root`.
+fn infer_verify_commands(root: &Path) -> Result<VerifyCommands> {
     // Check for Cargo.toml (Rust project)
-    if cwd.join("Cargo.toml").exists() {
+    if root.join("Cargo.toml").exists() {
         return Ok(VerifyCommands {
             checks: vec![
                 "cargo check".to_string(),
@@ -496,7 +515,7 @@ fn infer_verify_commands() -> Result<VerifyCommands> {
     }
 
     // Check for package.json (Node.js project)
-    if cwd.join("package.json").exists() {
+    if root.join("package.json").exists() {
         return Ok(VerifyCommands {
             checks: vec!["npm run lint".to_string()],
             tests: "npm test".to_string(),
@@ -504,7 +523,7 @@ fn infer_verify_commands() -> Result<VerifyCommands> {
     }
 
     // Check for pyproject.toml or setup.py (Python project)
-    if cwd.join("pyproject.toml").exists() || cwd.join("setup.py").exists() {
+    if root.join("pyproject.toml").exists() || root.join("setup.py").exists() {
         return Ok(VerifyCommands {
             checks: vec!["python -m mypy .".to_string()],
             tests: "python -m pytest".to_string(),
@@ -552,7 +571,7 @@ impl SpecAdapter for OpenSpecAdapter {
         let scenarios = self.scenarios.clone();
 
         // Infer verification commands
-        let verify = infer_verify_commands()?;
+        let verify = infer_verify_commands(&self.root)?;
 
         Ok(Context {
             story,
@@ -564,11 +583,15 @@ impl SpecAdapter for OpenSpecAdapter {
     }
 
     fn verify_commands(&self) -> Result<VerifyCommands> {
-        infer_verify_commands()
+        infer_verify_commands(&self.root)
+    }
+
+    fn tasks_file(&self) -> PathBuf {
+        self.change_dir.join("tasks.md")
     }
 
     fn tool_prompt(&self) -> String {
-        let verify = infer_verify_commands().unwrap_or_default();
+        let verify = infer_verify_commands(&self.root).unwrap_or_default();
         let change_dir = self.change_dir.display();
 
         let mut sections = Vec::new();
@@ -721,6 +744,7 @@ mod tests {
 
         let adapter = OpenSpecAdapter {
             change_name: "test-change".to_string(),
+            root: PathBuf::from("/test"),
             change_dir: PathBuf::from("/test/openspec/changes/test-change"),
             stories: Vec::new(),
             scenarios: Vec::new(),
@@ -741,6 +765,7 @@ mod tests {
 
         let adapter = OpenSpecAdapter {
             change_name: "test-change".to_string(),
+            root: PathBuf::from("/test"),
             change_dir: PathBuf::from("/test/openspec/changes/test-change"),
             stories: Vec::new(),
             scenarios: Vec::new(),
@@ -760,6 +785,7 @@ mod tests {
 
         let adapter = OpenSpecAdapter {
             change_name: "test-change".to_string(),
+            root: PathBuf::from("/test"),
             change_dir: PathBuf::from("/test/openspec/changes/test-change"),
             stories: Vec::new(),
             scenarios: Vec::new(),
@@ -773,4 +799,13 @@ mod tests {
         assert!(prompt.contains("WHEN"));
         assert!(prompt.contains("THEN"));
     }
+
+    #[test]
+    fn infer_verify_commands_uses_given_root() {
+        let dir = tempfile::TempDir::new().unwrap();
+        assert!(infer_verify_commands(dir.path()).unwrap().tests.is_empty());
+
+        fs::write(dir.path().join("Cargo.toml"), "").unwrap();
+        assert_eq!(infer_verify_commands(dir.path()).unwrap().tests, "cargo test");
+    }
 }
@@ -1,4 +1,4 @@
-//! Completion screen for displaying cleanup/keep options after Ralph loop finishes.
+//! Completion screen for choosing what to do with the changes after Ralph loop finishes.
 
 use ratatui::prelude::*;
 use ratatui::widgets::Paragraph;
@@ -16,7 +16,11 @@ pub struct CompletionData {
     pub original_branch: String,
     /// Name of the Ralph branch (ralph/{change}).
     pub ralph_branch: String,
-    /// Currently selected option (0 = Cleanup, 1 = Keep).
+    /// Directory the export-patch option writes to.
+    pub patch_dir: String,
+    /// Options the checkpoint backend supports, in display order.
+    pub options: &'static [CompletionOption],
+    /// Index of the selected option in `options`.
     pub selected_option: usize,
     /// Whether an operation is in progress.
     pub in_progress: bool,
@@ -24,6 +28,19 @@ pub struct CompletionData {
     pub progress_message: Option<String>,
     /// Reason for completion (success, max retries, user stop).
     pub completion_reason: CompletionReason,
+    /// Conflict that stopped the last chosen option, if any.
+    pub conflict: Option<CleanupConflict>,
+}
+
+/// A completion option that was aborted because of conflicts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CleanupConflict {
+    /// The option to retry.
+    pub option: CompletionOption,
+    /// The original branch the changes conflict with.
+    pub branch: String,
+    /// Files with conflicts.
+    pub files: Vec<String>,
 }
 
 /// Reason why the loop completed.
@@ -31,8 +48,8 @@ pub struct CompletionData {
 pub enum CompletionReason {
     /// All stories completed successfully.
     Success,
-    /// Max retries exceeded for a story.
-    MaxRetries { story_id: String },
+    /// Max retries exceeded for one or more stories, in order.
+    MaxRetries { story_ids: Vec<String> },
     /// User requested stop via 'q' key.
     UserStop,
 }
@@ -44,10 +61,13 @@ impl Default for CompletionData {
             stories_total: 0,
             original_branch: "main".to_string(),
             ralph_branch: "ralph/change".to_string(),
+            patch_dir: "/tmp/ralphtool/change-patches".to_string(),
+            options: &CompletionOption::ALL,
             selected_option: 0,
             in_progress: false,
             progress_message: None,
             completion_reason: CompletionReason::Success,
+            conflict: None,
         }
     }
 }
@@ -55,25 +75,36 @@ impl Default for CompletionData {
 impl CompletionData {
     /// Returns the currently selected CompletionOption.
     pub fn selected_completion_option(&self) -> CompletionOption {
-        match self.selected_option {
-            0 => CompletionOption::Cleanup,
-            _ => CompletionOption::Keep,
+        self.options[self.selected_option.min(self.options.len() - 1)]
+    }
+
+    /// Selects the given option; options the backend does not offer are
+    /// ignored.
+    pub fn select(&mut self, option: CompletionOption) {
+        if let Some(index) = self.options.iter().position(|o| *o == option) {
+            self.selected_option = index;
         }
     }
 
     /// Selects the cleanup option.
     pub fn select_cleanup(&mut self) {
-        self.selected_option = 0;
+        self.select(CompletionOption::Cleanup);
     }
 
     /// Selects the keep option.
     pub fn select_keep(&mut self) {
-        self.selected_option = 1;
+        self.select(CompletionOption::Keep);
     }
 
-    /// Toggles between cleanup and keep options.
-    pub fn toggle_option(&mut self) {
-        self.selected_option = 1 - self.selected_option;
+    /// Moves the selection to the next option, wrapping around.
+    pub fn select_next(&mut self) {
+        self.selected_option = (self.selected_option + 1) % self.options.len();
+    }
+
+    /// Moves the selection to the previous option, wrapping around.
+    pub fn select_previous(&mut self) {
+        let len = self.options.len();
+        self.selected_option = (self.selected_option + len - 1) % len;
     }
 }
 
@@ -83,10 +114,15 @@ pub fn render_completion_screen(frame: &mut Frame, data: &CompletionData) {
     let centered = centered_rect(area);
 
     // Header
+    let keybindings = if data.conflict.is_some() {
+        "r Retry  k Keep  q Cancel".to_string()
+    } else {
+        option_keybindings(data.options)
+    };
     let header = HeaderSection {
         title: "\u{25c6} Loop Complete",
         description: &completion_description(data),
-        keybindings: "c Cleanup  k Keep  Enter Confirm  q Cancel",
+        keybindings: &keybindings,
     };
 
     let header_height = render_header_auto(frame, centered, &header);
@@ -98,6 +134,8 @@ pub fn render_completion_screen(frame: &mut Frame, data: &CompletionData) {
 
     if data.in_progress {
         render_progress(frame, content_area, data);
+    } else if let Some(conflict) = &data.conflict {
+        render_conflict(frame, content_area, data, conflict);
     } else {
         render_options(frame, content_area, data);
     }
@@ -112,10 +150,11 @@ fn completion_description(data: &CompletionData) -> String {
                 data.stories_total
             )
         }
-        CompletionReason::MaxRetries { story_id } => {
+        CompletionReason::MaxRetries { story_ids } => {
             format!(
                 "Max retries exceeded for {}. {} of {} stories completed.",
-                story_id, data.stories_completed, data.stories_total
+                story_ids.join(", "),
+                data.stories_completed, data.stories_total
             )
         }
         CompletionReason::UserStop => {
@@ -166,32 +205,121 @@ fn render_options(frame: &mut Frame, area: Rect, data: &CompletionData) {
     );
     y += 2;
 
-    // Cleanup option
-    render_option(
-        frame,
-        Rect::new(area.x, y, area.width, 3),
-        "Cleanup",
-        &format!(
+    for (index, option) in data.options.iter().enumerate() {
+        let (title, shortcut) = option_label(*option);
+        render_option(
+            frame,
+            Rect::new(area.x, y, area.width, 3),
+            title,
+            &option_description(*option, data),
+            data.selected_option == index,
+            shortcut,
+        );
+        y += 3;
+    }
+}
+
+/// Returns the header keybindings for the offered options.
+///
+/// Format: `c Cleanup  k Keep  s Squash  h History  p Patch  Enter Confirm  q Cancel`
+fn option_keybindings(options: &[CompletionOption]) -> String {
+    options
+        .iter()
+        .map(|option| match option {
+            CompletionOption::Cleanup => "c Cleanup",
+            CompletionOption::Keep => "k Keep",
+            CompletionOption::Squash => "s Squash",
+            CompletionOption::PreserveHistory => "h History",
+            CompletionOption::ExportPatch => "p Patch",
+        })
+        .chain(["Enter Confirm", "q Cancel"])
+        .collect::<Vec<_>>()
+        .join("  ")
+}
+
+/// Returns the title and shortcut key of an option.
+fn option_label(option: CompletionOption) -> (&'static str, char) {
+    match option {
+        CompletionOption::Cleanup => ("Cleanup", 'c'),
+        CompletionOption::Keep => ("Keep", 'k'),
+        CompletionOption::Squash => ("Squash", 's'),
+        CompletionOption::PreserveHistory => ("Preserve history", 'h'),
+        CompletionOption::ExportPatch => ("Export patch", 'p'),
+    }
+}
+
+/// Returns the one-line description of what an option does.
+fn option_description(option: CompletionOption, data: &CompletionData) -> String {
+    match option {
+        CompletionOption::Cleanup => format!(
             "Return to {} with all changes uncommitted (ready for review)",
             data.original_branch
         ),
-        data.selected_option == 0,
-        'c',
-    );
-    y += 4;
-
-    // Keep option
-    render_option(
-        frame,
-        Rect::new(area.x, y, area.width, 3),
-        "Keep",
-        &format!(
+        CompletionOption::Keep => format!(
             "Stay on {} with checkpoint commits preserved",
             data.ralph_branch
         ),
-        data.selected_option == 1,
-        'k',
-    );
+        CompletionOption::Squash => format!(
+            "Return to {} with all changes in a single commit",
+            data.original_branch
+        ),
+        CompletionOption::PreserveHistory => format!(
+            "Land the checkpoint commits on {} as they are",
+            data.original_branch
+        ),
+        CompletionOption::ExportPatch => format!(
+            "Write the commits as patches to {} and keep {}",
+            data.patch_dir, data.ralph_branch
+        ),
+    }
+}
+
+/// Renders the files that conflicted and what retry and keep do.
+fn render_conflict(frame: &mut Frame, area: Rect, data: &CompletionData, conflict: &CleanupConflict) {
+    let mut y = area.y + 1;
+    let bottom = area.y + area.height;
+
+    let (title, _) = option_label(conflict.option);
+    let heading = Line::from(Span::styled(
+        format!(
+            "{} stopped: {} moved on and conflicts with these files:",
+            title, conflict.branch
+        ),
+        Style::default().fg(Color::Red),
+    ));
+    frame.render_widget(Paragraph::new(heading), Rect::new(area.x, y, area.width, 1));
+    y += 2;
+
+    // Leave room for the hint below the list
+    let max_files = bottom.saturating_sub(y + 3) as usize;
+    let shown = if conflict.files.len() > max_files {
+        max_files.saturating_sub(1)
+    } else {
+        conflict.files.len()
+    };
+    for file in conflict.files.iter().take(shown) {
+        let line = Line::from(Span::styled(format!("  {}", file), Style::default().fg(Color::White)));
+        frame.render_widget(Paragraph::new(line), Rect::new(area.x, y, area.width, 1));
+        y += 1;
+    }
+    if shown < conflict.files.len() {
+        let more = Line::from(Span::styled(
+            format!("  ... and {} more", conflict.files.len() - shown),
+            Style::default().fg(Color::DarkGray),
+        ));
+        frame.render_widget(Paragraph::new(more), Rect::new(area.x, y, area.width, 1));
+        y += 1;
+    }
+    y += 1;
+
+    let hint = Line::from(Span::styled(
+        format!(
+            "Nothing was changed. Reconcile {} with {} and retry, or keep {}.",
+            conflict.branch, data.ralph_branch, data.ralph_branch
+        ),
+        Style::default().fg(Color::DarkGray),
+    ));
+    frame.render_widget(Paragraph::new(hint), Rect::new(area.x, y, area.width, 1));
 }
 
 /// Renders a single option.
@@ -259,6 +387,7 @@ fn render_progress(frame: &mut Frame, area: Rect, data: &CompletionData) {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::checkpoint::CheckpointBackend;
 
     #[test]
     fn default_completion_data() {
@@ -269,8 +398,10 @@ mod tests {
 
     #[test]
     fn select_cleanup_sets_option_to_zero() {
-        let mut data = CompletionData::default();
-        data.selected_option = 1;
+        let mut data = CompletionData {
+            selected_option: 1,
+            ..Default::default()
+        };
         data.select_cleanup();
         assert_eq!(data.selected_option, 0);
     }
@@ -283,15 +414,25 @@ mod tests {
     }
 
     #[test]
-    fn toggle_option_switches_between_options() {
+    fn select_next_cycles_through_all_options() {
         let mut data = CompletionData::default();
+        for option in CompletionOption::ALL.iter().skip(1) {
+            data.select_next();
+            assert_eq!(data.selected_completion_option(), *option);
+        }
+
+        data.select_next();
         assert_eq!(data.selected_option, 0);
+    }
 
-        data.toggle_option();
-        assert_eq!(data.selected_option, 1);
+    #[test]
+    fn select_previous_wraps_to_last_option() {
+        let mut data = CompletionData::default();
+        data.select_previous();
+        assert_eq!(data.selected_completion_option(), CompletionOption::ExportPatch);
 
-        data.toggle_option();
-        assert_eq!(data.selected_option, 0);
+        data.select_previous();
+        assert_eq!(data.selected_completion_option(), CompletionOption::PreserveHistory);
     }
 
     #[test]
@@ -301,6 +442,36 @@ mod tests {
 
         data.select_keep();
         assert_eq!(data.selected_completion_option(), CompletionOption::Keep);
+
+        data.select(CompletionOption::Squash);
+        assert_eq!(data.selected_completion_option(), CompletionOption::Squash);
+    }
+
+    #[test]
+    fn snapshot_backend_offers_only_supported_options() {
+        let mut data = CompletionData {
+            options: CheckpointBackend::Snapshot.completion_options(),
+            ..Default::default()
+        };
+
+        data.select(CompletionOption::Squash);
+        assert_eq!(data.selected_completion_option(), CompletionOption::Cleanup);
+        data.select_previous();
+        assert_eq!(data.selected_completion_option(), CompletionOption::ExportPatch);
+        assert_eq!(
+            option_keybindings(data.options),
+            "c Cleanup  k Keep  p Patch  Enter Confirm  q Cancel"
+        );
+    }
+
+    #[test]
+    fn export_patch_description_names_patch_dir() {
+        let data = CompletionData {
+            patch_dir: "/tmp/out".to_string(),
+            ..Default::default()
+        };
+        let desc = option_description(CompletionOption::ExportPatch, &data);
+        assert!(desc.contains("/tmp/out"));
     }
 
     #[test]
@@ -320,7 +491,7 @@ mod tests {
             stories_completed: 2,
             stories_total: 5,
             completion_reason: CompletionReason::MaxRetries {
-                story_id: "story-3".to_string(),
+                story_ids: vec!["story-3".to_string()],
             },
             ..Default::default()
         };
@@ -330,6 +501,21 @@ mod tests {
         assert!(desc.contains("2 of 5"));
     }
 
+    #[test]
+    fn completion_description_lists_all_failed_stories() {
+        let data = CompletionData {
+            stories_completed: 1,
+            stories_total: 4,
+            completion_reason: CompletionReason::MaxRetries {
+                story_ids: vec!["2".to_string(), "4".to_string()],
+            },
+            ..Default::default()
+        };
+        let desc = completion_description(&data);
+        assert!(desc.contains("Max retries exceeded for 2, 4."));
+        assert!(desc.contains("1 of 4"));
+    }
+
     #[test]
     fn completion_description_user_stop() {
         let data = CompletionData {
@@ -12,14 +12,41 @@ use ratatui::{
     widgets::{Block, Borders, Gauge, Paragraph, Wrap},
 };
 
-use crate::agent::{Response, StreamEvent};
+use crate::agent::{AgentExit, Response, StreamEvent};
 use crate::app::{App, LoopTab};
-use crate::ralph_loop::LoopState;
+use crate::ralph_loop::{
+    DiffLimitAction, DiffStat, IntegrityAction, LoopState, PlanChange, ScanFinding, VerificationResult,
+};
 use super::{centered_rect, render_header_auto, HeaderSection};
 
 /// Keybindings for the loop execution screen.
 const LOOP_KEYBINDINGS: &str = "←→ Story  Tab Switch  ↑↓ Scroll  q Stop";
 
+/// Keybindings while changes over the diff limits await approval.
+const DIFF_APPROVAL_KEYBINDINGS: &str = "Y Accept  N Reject and retry  q Stop";
+
+/// A story's changes over the diff limits, awaiting the user's approval.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiffApproval {
+    /// ID of the story whose changes await approval.
+    pub story_id: String,
+    /// Size of the changes since the last checkpoint.
+    pub stat: DiffStat,
+    /// Descriptions of the exceeded limits.
+    pub exceeded: Vec<String>,
+}
+
+/// A failed attempt reverted to the last checkpoint.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RevertedAttempt {
+    /// Tracked files restored to their checkpoint content.
+    pub restored: Vec<String>,
+    /// Untracked files the attempt created that were removed.
+    pub removed: Vec<String>,
+    /// Ref the attempt was archived under, if archiving succeeded.
+    pub archive_ref: Option<String>,
+}
+
 /// Renders the loop execution screen.
 pub fn render_loop_screen(frame: &mut Frame, app: &mut App) {
     let area = frame.area();
@@ -35,7 +62,11 @@ pub fn render_loop_screen(frame: &mut Frame, app: &mut App) {
     let header = HeaderSection {
         title: "◆ Loop Execution",
         description: &description,
-        keybindings: LOOP_KEYBINDINGS,
+        keybindings: if app.diff_approval.is_some() {
+            DIFF_APPROVAL_KEYBINDINGS
+        } else {
+            LOOP_KEYBINDINGS
+        },
     };
 
     // Render header (auto-selects full or compact based on terminal height)
@@ -66,6 +97,12 @@ pub fn render_loop_screen(frame: &mut Frame, app: &mut App) {
     // Render tab bar
     render_tab_bar(frame, chunks[2], app.loop_tab);
 
+    // Changes over the diff limits take over the content until decided
+    if let Some(approval) = &app.diff_approval {
+        render_diff_approval(frame, chunks[3], approval);
+        return;
+    }
+
     // Render content based on active tab
     match app.loop_tab {
         LoopTab::Info => render_info_tab(frame, chunks[3], app),
@@ -73,6 +110,44 @@ pub fn render_loop_screen(frame: &mut Frame, app: &mut App) {
     }
 }
 
+/// Renders the prompt to accept or reject changes over the diff limits.
+fn render_diff_approval(frame: &mut Frame, area: Rect, approval: &DiffApproval) {
+    let mut lines = vec![
+        Line::from(Span::styled(
+            format!("Story {} changed more than the diff limits allow:", approval.story_id),
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(""),
+        Line::from(vec![
+            Span::raw(format!("  {} files changed, ", approval.stat.files_changed)),
+            Span::styled(format!("+{}", approval.stat.insertions), Style::default().fg(Color::Green)),
+            Span::raw(" "),
+            Span::styled(format!("-{}", approval.stat.deletions), Style::default().fg(Color::Red)),
+        ]),
+        Line::from(""),
+    ];
+
+    for limit in &approval.exceeded {
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled("⚠", Style::default().fg(Color::Red)),
+            Span::raw(" "),
+            Span::raw(limit.clone()),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Accept the changes, or reject them to revert and retry the story?",
+        Style::default().fg(Color::DarkGray),
+    )));
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
 /// Renders a progress bar showing change name and completion ratio.
 ///
 /// Display format: "change-name [=========>        ] 3/10"
@@ -263,6 +338,36 @@ fn render_info_tab(frame: &mut Frame, area: Rect, app: &App) {
                     Span::styled(task.description.clone(), text_style),
                 ]));
             }
+
+            // Verification results from the orchestrator (if any ran)
+            if let Some(results) = app.story_verifications.get(story_id) {
+                render_verification_lines(&mut lines, results);
+            }
+
+            // COMPLETE signals rejected because tasks.md was not updated
+            if let Some(mismatches) = app.story_signal_mismatches.get(story_id) {
+                render_signal_mismatch_lines(&mut lines, mismatches);
+            }
+
+            // Plan changes the attempts were not allowed to make
+            if let Some(plan_changes) = app.story_plan_changes.get(story_id) {
+                render_plan_change_lines(&mut lines, plan_changes);
+            }
+
+            // Diff limits the attempts exceeded
+            if let Some(limits) = app.story_diff_limits.get(story_id) {
+                render_diff_limit_lines(&mut lines, limits);
+            }
+
+            // Failed attempts and what reverting them touched
+            if let Some(reverts) = app.story_reverts.get(story_id) {
+                render_revert_lines(&mut lines, reverts);
+            }
+
+            // Findings of the pre-commit scan (if it found anything)
+            if let Some(findings) = app.story_scan_findings.get(story_id) {
+                render_scan_lines(&mut lines, findings);
+            }
         } else {
             // Story not found in loaded stories - show ID only
             lines.push(Line::from(vec![
@@ -302,11 +407,199 @@ fn render_info_tab(frame: &mut Frame, area: Rect, app: &App) {
     frame.render_widget(content, area);
 }
 
+/// Renders the verification section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Verification
+///   ✔ cargo check
+///   ✘ cargo test
+/// ```
+fn render_verification_lines<'a>(lines: &mut Vec<Line<'a>>, results: &'a [VerificationResult]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Verification",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for result in results {
+        let (mark, style) = if result.passed {
+            ("✔", Style::default().fg(Color::Green))
+        } else {
+            ("✘", Style::default().fg(Color::Red))
+        };
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(mark, style),
+            Span::raw(" "),
+            Span::raw(result.command.as_str()),
+        ]));
+    }
+}
+
+/// Renders the rejected COMPLETE signals section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Rejected COMPLETE signals
+///   ✘ tasks 1.2, 1.3 still unchecked
+/// ```
+fn render_signal_mismatch_lines(lines: &mut Vec<Line<'_>>, mismatches: &[Vec<String>]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Rejected COMPLETE signals",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for unchecked_tasks in mismatches {
+        let noun = if unchecked_tasks.len() == 1 { "task" } else { "tasks" };
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled("✘", Style::default().fg(Color::Red)),
+            Span::raw(" "),
+            Span::raw(format!("{} {} still unchecked", noun, unchecked_tasks.join(", "))),
+        ]));
+    }
+}
+
+/// Renders the plan changes section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Plan changes
+///   ✘ task 2.1 was removed (rejected)
+///   ✔ story 3 was added (repaired)
+/// ```
+fn render_plan_change_lines(lines: &mut Vec<Line<'_>>, plan_changes: &[(IntegrityAction, Vec<PlanChange>)]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Plan changes",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for (action, changes) in plan_changes {
+        let (mark, style, outcome) = match action {
+            IntegrityAction::Repair => ("✔", Style::default().fg(Color::Green), "repaired"),
+            _ => ("✘", Style::default().fg(Color::Red), "rejected"),
+        };
+        for change in changes {
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                Span::styled(mark, style),
+                Span::raw(" "),
+                Span::raw(format!("{} ({})", change.describe(), outcome)),
+            ]));
+        }
+    }
+}
+
+/// Renders the exceeded diff limits section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Diff limits
+///   ✘ 42 files changed (limit 10), attempt failed
+///   ⚠ 900 lines changed (limit 500), approval asked
+/// ```
+fn render_diff_limit_lines(lines: &mut Vec<Line<'_>>, limits: &[(DiffLimitAction, Vec<String>)]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Diff limits",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for (action, exceeded) in limits {
+        let (mark, outcome) = match action {
+            DiffLimitAction::Fail => ("✘", "attempt failed"),
+            DiffLimitAction::Ask => ("⚠", "approval asked"),
+        };
+        for limit in exceeded {
+            lines.push(Line::from(vec![
+                Span::raw("  "),
+                Span::styled(mark, Style::default().fg(Color::Red)),
+                Span::raw(" "),
+                Span::raw(format!("{}, {}", limit, outcome)),
+            ]));
+        }
+    }
+}
+
+/// Renders the reverted attempts section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Reverted attempts
+///   Attempt 1 → refs/ralph/my-change/attempts/1-1
+///     restored src/lib.rs
+///     removed scratch.txt
+/// ```
+fn render_revert_lines<'a>(lines: &mut Vec<Line<'a>>, reverts: &'a [RevertedAttempt]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Reverted attempts",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for (i, revert) in reverts.iter().enumerate() {
+        let archive = match &revert.archive_ref {
+            Some(ref_name) => format!(" → {}", ref_name),
+            None => " (not archived)".to_string(),
+        };
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(format!("Attempt {}", i + 1), Style::default().add_modifier(Modifier::BOLD)),
+            Span::styled(archive, Style::default().fg(Color::DarkGray)),
+        ]));
+
+        for path in &revert.restored {
+            lines.push(Line::from(vec![
+                Span::raw("    "),
+                Span::styled("restored ", Style::default().fg(Color::DarkGray)),
+                Span::raw(path.as_str()),
+            ]));
+        }
+        for path in &revert.removed {
+            lines.push(Line::from(vec![
+                Span::raw("    "),
+                Span::styled("removed ", Style::default().fg(Color::Red)),
+                Span::raw(path.as_str()),
+            ]));
+        }
+    }
+}
+
+/// Renders the pre-commit scan section of the Info tab.
+///
+/// Display format:
+/// ```text
+/// Scan findings
+///   ⚠ .env: matches denied glob `.env`
+/// ```
+fn render_scan_lines(lines: &mut Vec<Line<'_>>, findings: &[ScanFinding]) {
+    lines.push(Line::from(""));
+    lines.push(Line::from(Span::styled(
+        "Scan findings",
+        Style::default().fg(Color::Yellow),
+    )));
+
+    for finding in findings {
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled("⚠", Style::default().fg(Color::Red)),
+            Span::raw(" "),
+            Span::raw(finding.describe()),
+        ]));
+    }
+}
+
 /// Renders the Agent tab showing messages with role prefixes and spacing.
 ///
 /// Messages display:
 /// - "Assistant:" prefix for regular messages
+/// - "Thinking:" prefix with dimmed text for reasoning
+/// - "Tool:" lines for tool calls, each followed by its result summary
 /// - "Done:" prefix with usage stats in different color for completion
+/// - "Exited:" with the exit status and stderr when the agent died
 /// - Visual spacing between messages
 fn render_agent_tab(frame: &mut Frame, area: Rect, app: &mut App) {
     let mut lines: Vec<Line> = Vec::new();
@@ -316,18 +609,39 @@ fn render_agent_tab(frame: &mut Frame, area: Rect, app: &mut App) {
 
     if let Some(story_id) = selected_story_id {
         if let Some(events) = app.story_events.get(story_id) {
+            // A clean exit adds nothing after the Done section
+            let events: Vec<&StreamEvent> = events
+                .iter()
+                .filter(|e| !matches!(e, StreamEvent::Exited(exit) if exit.success()))
+                .collect();
+
             for (i, event) in events.iter().enumerate() {
                 match event {
                     StreamEvent::Message(text) => {
                         render_message_lines(&mut lines, text);
                     }
+                    StreamEvent::Thinking(text) => {
+                        render_thinking_lines(&mut lines, text);
+                    }
+                    StreamEvent::ToolUse { name, input } => {
+                        render_tool_use_line(&mut lines, name, input);
+                    }
+                    StreamEvent::ToolResult { is_error, summary } => {
+                        render_tool_result_line(&mut lines, *is_error, summary);
+                    }
                     StreamEvent::Done(response) => {
                         render_done_section(&mut lines, response);
                     }
+                    StreamEvent::Exited(exit) => {
+                        render_exit_section(&mut lines, exit);
+                    }
                 }
 
+                // A tool result sits right below its tool call
+                let next_is_result = matches!(events.get(i + 1), Some(StreamEvent::ToolResult { .. }));
+
                 // Add separator between messages (2 blank lines, except after last)
-                if i < events.len() - 1 {
+                if i < events.len() - 1 && !next_is_result {
                     lines.push(Line::from(""));
                     lines.push(Line::from(""));
                 }
@@ -405,6 +719,87 @@ fn render_message_lines<'a>(lines: &mut Vec<Line<'a>>, text: &str) {
     }
 }
 
+/// Renders the agent's reasoning dimmed, below a "Thinking:" label.
+fn render_thinking_lines(lines: &mut Vec<Line<'_>>, text: &str) {
+    let dim = Style::default().fg(Color::DarkGray);
+    lines.push(Line::from(Span::styled(
+        "Thinking:",
+        dim.add_modifier(Modifier::BOLD),
+    )));
+
+    let mut prev_was_blank = false;
+    for line in text.lines() {
+        let is_blank = line.trim().is_empty();
+        if is_blank && prev_was_blank {
+            continue;
+        }
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(line.to_string(), dim.add_modifier(Modifier::ITALIC)),
+        ]));
+        prev_was_blank = is_blank;
+    }
+}
+
+/// Renders a tool call as "Tool: <name>  <main argument>".
+fn render_tool_use_line(lines: &mut Vec<Line<'_>>, name: &str, input: &serde_json::Value) {
+    lines.push(Line::from(vec![
+        Span::styled("Tool: ", Style::default().fg(Color::Magenta).add_modifier(Modifier::BOLD)),
+        Span::styled(name.to_string(), Style::default().fg(Color::Magenta)),
+        Span::raw("  "),
+        Span::raw(tool_input_summary(input)),
+    ]));
+}
+
+/// Renders a tool result summary below its call, red for errors.
+fn render_tool_result_line(lines: &mut Vec<Line<'_>>, is_error: bool, summary: &str) {
+    let color = if is_error { Color::Red } else { Color::DarkGray };
+    lines.push(Line::from(vec![
+        Span::raw("  "),
+        Span::styled(format!("↳ {}", summary), Style::default().fg(color)),
+    ]));
+}
+
+/// Longest tool argument shown next to a tool call, in characters.
+const TOOL_INPUT_MAX_CHARS: usize = 80;
+
+/// Picks the argument that best describes a tool call: the command, path
+/// or pattern if there is one, else the compact JSON input.
+fn tool_input_summary(input: &serde_json::Value) -> String {
+    const KEYS: [&str; 6] = ["command", "file_path", "path", "pattern", "url", "query"];
+
+    let summary = KEYS
+        .iter()
+        .find_map(|key| input.get(key)?.as_str())
+        .map(|value| value.lines().next().unwrap_or_default().to_string())
+        .unwrap_or_else(|| match input {
+            serde_json::Value::Null => String::new(),
+            other => other.to_string(),
+        });
+
+    if summary.chars().count() > TOOL_INPUT_MAX_CHARS {
+        let cut: String = summary.chars().take(TOOL_INPUT_MAX_CHARS).collect();
+        format!("{}…", cut)
+    } else {
+        summary
+    }
+}
+
+/// Renders an abnormal agent exit: its status, then the tail of stderr.
+fn render_exit_section(lines: &mut Vec<Line<'_>>, exit: &AgentExit) {
+    lines.push(Line::from(vec![
+        Span::styled("Exited: ", Style::default().fg(Color::Red).add_modifier(Modifier::BOLD)),
+        Span::styled(format!("agent {}", exit.status()), Style::default().fg(Color::Red)),
+    ]));
+
+    for line in exit.stderr.lines().filter(|line| !line.trim().is_empty()) {
+        lines.push(Line::from(vec![
+            Span::raw("  "),
+            Span::styled(line.to_string(), Style::default().fg(Color::DarkGray)),
+        ]));
+    }
+}
+
 /// Renders the Done section with "Done:" label on its own line.
 /// Content is displayed below with 2-space indentation.
 /// Usage stats are displayed on a separate line.
@@ -462,3 +857,20 @@ fn render_done_section<'a>(lines: &mut Vec<Line<'a>>, response: &Response) {
     ]));
 }
 
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn tool_input_summary_prefers_the_main_argument() {
+        assert_eq!(tool_input_summary(&json!({ "command": "cargo test\ncargo build" })), "cargo test");
+        assert_eq!(
+            tool_input_summary(&json!({ "file_path": "src/main.rs", "old_string": "a" })),
+            "src/main.rs"
+        );
+        assert_eq!(tool_input_summary(&json!({ "todos": [] })), r#"{"todos":[]}"#);
+        assert_eq!(tool_input_summary(&serde_json::Value::Null), "");
+    }
+}
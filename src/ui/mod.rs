@@ -8,8 +8,8 @@ mod preview;
 mod result_screen;
 mod selection;
 
-pub use completion_screen::{render_completion_screen, CompletionData, CompletionReason};
-pub use loop_screen::render_loop_screen;
+pub use completion_screen::{render_completion_screen, CleanupConflict, CompletionData, CompletionReason};
+pub use loop_screen::{render_loop_screen, DiffApproval, RevertedAttempt};
 pub use preview::render_preview;
 pub use result_screen::{render_result_screen, LoopResult};
 pub use selection::render_selection;
@@ -190,13 +190,7 @@ pub fn render(frame: &mut Frame, app: &mut App) {
         Screen::ConversionPreview => render_preview(frame, app),
         Screen::LoopExecution => render_loop_screen(frame, app),
         Screen::LoopCompletion => render_completion_screen(frame, &app.completion_data),
-        Screen::LoopResult => render_result_screen(
-            frame,
-            &app.loop_result,
-            app.result_tab,
-            app.result_tasks_scroll,
-            app.result_scroll_offset,
-        ),
+        Screen::LoopResult => render_result_screen(frame, app),
     }
 }
 
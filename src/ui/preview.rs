@@ -6,11 +6,21 @@ use ratatui::{
 };
 
 use crate::app::{App, PreviewTab};
+use crate::checkpoint::{PreflightIssue, Severity};
 use super::{centered_rect, render_header_auto, HeaderSection};
 
 /// Keybindings for the preview screen (single string for new header format).
 const PREVIEW_KEYBINDINGS: &str = "↑↓ Scroll  Tab Switch  R Run  Esc Back  q Quit";
 
+/// Keybindings for the preview screen when an interrupted run can be resumed.
+const PREVIEW_RESUME_KEYBINDINGS: &str = "↑↓ Scroll  Tab Switch  R Resume  N New run  Esc Back  q Quit";
+
+/// Keybindings while pre-flight warnings await confirmation.
+const PREFLIGHT_WARNING_KEYBINDINGS: &str = "Y Start anyway  N Abort  q Quit";
+
+/// Keybindings while pre-flight errors block the run.
+const PREFLIGHT_ERROR_KEYBINDINGS: &str = "Esc Back  q Quit";
+
 pub fn render_preview(frame: &mut Frame, app: &App) {
     let area = frame.area();
 
@@ -23,16 +33,36 @@ pub fn render_preview(frame: &mut Frame, app: &App) {
     let story_count = app.stories.len();
     let scenario_count = app.scenarios.len();
 
-    let description = format!(
+    let mut description = format!(
         "{}: {} tasks, {} stories, {} scenarios",
         change_name, task_count, story_count, scenario_count
     );
 
+    // Offer to resume when a previous run left checkpoints behind
+    let keybindings = match &app.existing_run {
+        Some(run) => {
+            description.push_str(&format!(
+                " (interrupted run on {} with {} checkpoints)",
+                run.branch,
+                run.checkpoint_story_ids.len()
+            ));
+            PREVIEW_RESUME_KEYBINDINGS
+        }
+        None => PREVIEW_KEYBINDINGS,
+    };
+
+    // Pre-flight issues take over the screen until confirmed or aborted
+    let keybindings = match &app.preflight_issues {
+        Some(issues) if PreflightIssue::any_blocking(issues) => PREFLIGHT_ERROR_KEYBINDINGS,
+        Some(_) => PREFLIGHT_WARNING_KEYBINDINGS,
+        None => keybindings,
+    };
+
     // Header section data
     let header = HeaderSection {
         title: "◆ Preview",
         description: &description,
-        keybindings: PREVIEW_KEYBINDINGS,
+        keybindings,
     };
 
     // Render header (auto-selects full or compact based on terminal height)
@@ -43,6 +73,11 @@ pub fn render_preview(frame: &mut Frame, app: &App) {
     let content_height = centered.height.saturating_sub(header_height);
     let content_area = Rect::new(centered.x, content_y, centered.width, content_height);
 
+    if let Some(issues) = &app.preflight_issues {
+        render_preflight(frame, issues, content_area);
+        return;
+    }
+
     // Split content area into tab bar and main content
     let chunks = Layout::default()
         .direction(Direction::Vertical)
@@ -81,6 +116,42 @@ pub fn render_preview(frame: &mut Frame, app: &App) {
     frame.render_widget(content, chunks[1]);
 }
 
+/// Renders the pre-flight issues and what the user can do about them.
+fn render_preflight(frame: &mut Frame, issues: &[PreflightIssue], area: Rect) {
+    let blocking = PreflightIssue::any_blocking(issues);
+    let mut lines = vec![
+        Line::from(Span::styled(
+            "Pre-flight checks found problems with the repository:",
+            Style::default().fg(Color::Yellow),
+        )),
+        Line::from(""),
+    ];
+
+    for issue in issues {
+        let (label, color) = match issue.severity() {
+            Severity::Error => ("error  ", Color::Red),
+            Severity::Warning => ("warning", Color::Yellow),
+        };
+        lines.push(Line::from(vec![
+            Span::styled(format!("  {} ", label), Style::default().fg(color)),
+            Span::styled(issue.message.clone(), Style::default().fg(Color::White)),
+        ]));
+    }
+
+    lines.push(Line::from(""));
+    let hint = if blocking {
+        "Fix the errors above before starting the loop."
+    } else {
+        "Start the loop anyway?"
+    };
+    lines.push(Line::from(Span::styled(hint, Style::default().fg(Color::DarkGray))));
+
+    let paragraph = Paragraph::new(lines)
+        .block(Block::default().borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
 fn render_tab_bar(frame: &mut Frame, app: &App, area: Rect) {
     let tasks_label = match app.active_tab {
         PreviewTab::Tasks => "[Tasks]",
@@ -2,7 +2,7 @@
 //!
 //! This screen displays:
 //! - Summary of completed work
-//! - Tabbed interface with Tasks and Changed Files tabs
+//! - Tabbed interface with Tasks, Changed Files, Checkpoints and Attempts tabs
 
 use ratatui::{
     prelude::*,
@@ -10,12 +10,23 @@ use ratatui::{
 };
 
 use super::{centered_rect, render_header_auto, HeaderSection};
-use crate::app::ResultTab;
+use crate::app::{App, ResultTab};
+use crate::checkpoint::{Attempt, StoryCheckpoint};
 use crate::spec::Story;
 
 /// Keybindings for the result screen (single string for new header format).
 const RESULT_KEYBINDINGS: &str = "↑↓ Scroll  Tab Switch  Esc Back  q Quit";
 
+/// Keybindings for the Attempts tab, which can open an attempt's diff.
+const RESULT_ATTEMPTS_KEYBINDINGS: &str = "↑↓ Select  Enter Diff  Tab Switch  Esc Back  q Quit";
+
+/// Keybindings for the Checkpoints tab, which can roll back to a checkpoint.
+const RESULT_CHECKPOINTS_KEYBINDINGS: &str =
+    "↑↓ Select  Enter Roll back & re-run  Tab Switch  Esc Back  q Quit";
+
+/// Keybindings while a rollback awaits confirmation.
+const RESULT_ROLLBACK_KEYBINDINGS: &str = "y Roll back & re-run  n Cancel";
+
 /// Result data for display.
 #[derive(Debug, Clone, Default)]
 pub struct LoopResult {
@@ -39,16 +50,18 @@ pub struct LoopResult {
 
     /// Stories with tasks for display in Tasks tab.
     pub stories: Vec<Story>,
+
+    /// Failed attempts archived during the run, oldest first.
+    pub attempts: Vec<Attempt>,
+
+    /// Checkpoint commits on the ralph branch, oldest first.
+    pub checkpoints: Vec<StoryCheckpoint>,
 }
 
 /// Renders the result review screen.
-pub fn render_result_screen(
-    frame: &mut Frame,
-    result: &LoopResult,
-    active_tab: ResultTab,
-    tasks_scroll: usize,
-    files_scroll: usize,
-) {
+pub fn render_result_screen(frame: &mut Frame, app: &App) {
+    let result = &app.loop_result;
+    let active_tab = app.result_tab;
     let area = frame.area();
 
     // Center the content using responsive width
@@ -61,7 +74,12 @@ pub fn render_result_screen(
     let header = HeaderSection {
         title: "◆ Result",
         description: &description,
-        keybindings: RESULT_KEYBINDINGS,
+        keybindings: match active_tab {
+            ResultTab::Checkpoints if app.result_rollback_confirm => RESULT_ROLLBACK_KEYBINDINGS,
+            ResultTab::Checkpoints => RESULT_CHECKPOINTS_KEYBINDINGS,
+            ResultTab::Attempts => RESULT_ATTEMPTS_KEYBINDINGS,
+            _ => RESULT_KEYBINDINGS,
+        },
     };
 
     // Render header (auto-selects full or compact based on terminal height)
@@ -90,8 +108,15 @@ pub fn render_result_screen(
 
     // Tab content
     match active_tab {
-        ResultTab::Tasks => render_tasks_tab(frame, chunks[2], result, tasks_scroll),
-        ResultTab::ChangedFiles => render_changed_files(frame, chunks[2], result, files_scroll),
+        ResultTab::Tasks => render_tasks_tab(frame, chunks[2], result, app.result_tasks_scroll),
+        ResultTab::ChangedFiles => {
+            render_changed_files(frame, chunks[2], result, app.result_scroll_offset)
+        }
+        ResultTab::Checkpoints => render_checkpoints_tab(frame, chunks[2], app),
+        ResultTab::Attempts => match &app.result_attempt_diff {
+            Some(diff) => render_attempt_diff(frame, chunks[2], diff, app.result_attempts_scroll),
+            None => render_attempts_tab(frame, chunks[2], result, app.result_attempts_selected),
+        },
     }
 }
 
@@ -107,7 +132,7 @@ fn render_summary(frame: &mut Frame, area: Rect, result: &LoopResult) {
     frame.render_widget(summary_widget, area);
 }
 
-/// Renders the tab bar with Tasks and Changed Files tabs.
+/// Renders the tab bar with Tasks, Changed Files, Checkpoints and Attempts tabs.
 fn render_tabs(frame: &mut Frame, area: Rect, active_tab: ResultTab) {
     let tasks_style = if active_tab == ResultTab::Tasks {
         Style::default().fg(Color::Black).bg(Color::White)
@@ -121,11 +146,27 @@ fn render_tabs(frame: &mut Frame, area: Rect, active_tab: ResultTab) {
         Style::default().fg(Color::DarkGray)
     };
 
+    let checkpoints_style = if active_tab == ResultTab::Checkpoints {
+        Style::default().fg(Color::Black).bg(Color::White)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
+    let attempts_style = if active_tab == ResultTab::Attempts {
+        Style::default().fg(Color::Black).bg(Color::White)
+    } else {
+        Style::default().fg(Color::DarkGray)
+    };
+
     let tabs = Line::from(vec![
         Span::raw(" "),
         Span::styled(" Tasks ", tasks_style),
         Span::raw("  "),
         Span::styled(" Changed Files ", files_style),
+        Span::raw("  "),
+        Span::styled(" Checkpoints ", checkpoints_style),
+        Span::raw("  "),
+        Span::styled(" Attempts ", attempts_style),
         Span::raw(" "),
     ]);
 
@@ -223,3 +264,159 @@ fn render_changed_files(frame: &mut Frame, area: Rect, result: &LoopResult, scro
     let content = paragraph.scroll((clamped_scroll, 0));
     frame.render_widget(content, area);
 }
+
+/// Renders the Attempts tab listing archived failed attempts.
+/// Renders the Checkpoints tab: one row per story checkpoint with its
+/// diffstat, plus the pending rollback confirmation or rollback error.
+fn render_checkpoints_tab(frame: &mut Frame, area: Rect, app: &App) {
+    let result = &app.loop_result;
+    let selected = app.result_checkpoints_selected;
+    let mut lines: Vec<Line> = if result.checkpoints.is_empty() {
+        vec![Line::from(Span::styled(
+            format!("No checkpoints on ralph/{}.", result.change_name),
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        result
+            .checkpoints
+            .iter()
+            .enumerate()
+            .map(|(i, checkpoint)| {
+                let style = if i == selected {
+                    Style::default().fg(Color::Black).bg(Color::White)
+                } else {
+                    Style::default()
+                };
+                let short_commit = checkpoint.commit.get(..7).unwrap_or(&checkpoint.commit);
+                Line::from(vec![
+                    Span::styled(
+                        format!("Story {}", checkpoint.story_id),
+                        style.add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(short_commit.to_string(), Style::default().fg(Color::Yellow)),
+                    Span::raw("  "),
+                    Span::raw(format!("{} files", checkpoint.files_changed)),
+                    Span::raw(" "),
+                    Span::styled(
+                        format!("+{}", checkpoint.insertions),
+                        Style::default().fg(Color::Green),
+                    ),
+                    Span::raw(" "),
+                    Span::styled(
+                        format!("-{}", checkpoint.deletions),
+                        Style::default().fg(Color::Red),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(checkpoint.subject.clone(), Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect()
+    };
+
+    if let Some(checkpoint) = result.checkpoints.get(selected).filter(|_| app.result_rollback_confirm) {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!(
+                "Reset ralph/{} to story {} and re-run the loop? Later checkpoints are discarded.",
+                result.change_name, checkpoint.story_id
+            ),
+            Style::default().fg(Color::Yellow).add_modifier(Modifier::BOLD),
+        )));
+    } else if let Some(error) = &app.result_rollback_error {
+        lines.push(Line::from(""));
+        lines.push(Line::from(Span::styled(
+            format!("Rollback failed: {}", error),
+            Style::default().fg(Color::Red),
+        )));
+    }
+
+    let title = format!(" Checkpoints ({}) ", result.checkpoints.len());
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner_area = block.inner(area);
+    let paragraph = Paragraph::new(lines).block(block);
+
+    // Keep the selected checkpoint visible
+    let visible_height = inner_area.height as usize;
+    let scroll = selected.saturating_sub(visible_height.saturating_sub(1)) as u16;
+
+    frame.render_widget(paragraph.scroll((scroll, 0)), area);
+}
+
+fn render_attempts_tab(frame: &mut Frame, area: Rect, result: &LoopResult, selected: usize) {
+    let lines: Vec<Line> = if result.attempts.is_empty() {
+        vec![Line::from(Span::styled(
+            "No failed attempts were archived.",
+            Style::default().fg(Color::DarkGray),
+        ))]
+    } else {
+        result
+            .attempts
+            .iter()
+            .enumerate()
+            .map(|(i, attempt)| {
+                let reason = attempt.reason.lines().next().unwrap_or_default();
+                let style = if i == selected {
+                    Style::default().fg(Color::Black).bg(Color::White)
+                } else {
+                    Style::default()
+                };
+                Line::from(vec![
+                    Span::styled(
+                        format!("Story {} attempt {}", attempt.story_id, attempt.attempt),
+                        style.add_modifier(Modifier::BOLD),
+                    ),
+                    Span::raw("  "),
+                    Span::styled(reason.to_string(), Style::default().fg(Color::DarkGray)),
+                ])
+            })
+            .collect()
+    };
+
+    let title = format!(" Attempts ({}) ", result.attempts.len());
+    let block = Block::default().title(title).borders(Borders::ALL);
+    let inner_area = block.inner(area);
+    let paragraph = Paragraph::new(lines).block(block);
+
+    // Keep the selected attempt visible
+    let visible_height = inner_area.height as usize;
+    let scroll = selected.saturating_sub(visible_height.saturating_sub(1)) as u16;
+
+    frame.render_widget(paragraph.scroll((scroll, 0)), area);
+}
+
+/// Renders the diff of the selected attempt with colored +/- lines.
+fn render_attempt_diff(frame: &mut Frame, area: Rect, diff: &str, scroll_offset: usize) {
+    let lines: Vec<Line> = diff
+        .lines()
+        .map(|line| {
+            let style = if line.starts_with("+++") || line.starts_with("---") {
+                Style::default().add_modifier(Modifier::BOLD)
+            } else if line.starts_with('+') {
+                Style::default().fg(Color::Green)
+            } else if line.starts_with('-') {
+                Style::default().fg(Color::Red)
+            } else if line.starts_with("@@") {
+                Style::default().fg(Color::Cyan)
+            } else {
+                Style::default()
+            };
+            Line::from(Span::styled(line.to_string(), style))
+        })
+        .collect();
+
+    // Create paragraph to calculate actual rendered line count
+    let block = Block::default().title(" Attempt Diff (Esc to close) ").borders(Borders::ALL);
+    let inner_area = block.inner(area);
+    let paragraph = Paragraph::new(lines).block(block);
+
+    // Get actual rendered line count
+    let total_lines = paragraph.line_count(inner_area.width);
+    let visible_height = inner_area.height as usize;
+    let max_scroll = total_lines.saturating_sub(visible_height);
+
+    // Clamp scroll offset to valid bounds
+    let clamped_scroll = scroll_offset.min(max_scroll) as u16;
+
+    frame.render_widget(paragraph.scroll((clamped_scroll, 0)), area);
+}